@@ -0,0 +1,411 @@
+//! Arbitrary output variables: images of what the camera rays hit, rendered alongside the
+//! beauty pass for compositing and denoising.
+
+use nalgebra::*;
+use rayon::prelude::*;
+
+use std::fmt;
+use std::io::{self, Seek, Write};
+use std::ptr;
+use std::str::FromStr;
+
+use crate::prelude::*;
+
+/// An auxiliary image that can be rendered along with the final one.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Aov {
+    /// Distance from the camera to the first surface hit, or infinity where nothing was hit
+    Depth,
+    /// World space shading normal of the first surface hit, facing the camera
+    Normal,
+    /// How much light the first surface hit reflects, or the background where nothing was hit
+    Albedo,
+    /// One more than the index of the object hit, or zero where nothing was hit.  Taken from the
+    /// first sample of each pixel rather than averaged.
+    ObjectId,
+    /// One more than the index of the material hit, or zero where nothing was hit.  Taken from
+    /// the first sample of each pixel rather than averaged.
+    MaterialId,
+    /// Light given off by the first surface hit or arriving at it straight from the lights
+    Direct,
+    /// Light arriving at the first surface hit after bouncing off of the rest of the scene
+    Indirect,
+}
+
+impl Aov {
+    pub const ALL: [Aov; 7] = [
+        Aov::Depth,
+        Aov::Normal,
+        Aov::Albedo,
+        Aov::ObjectId,
+        Aov::MaterialId,
+        Aov::Direct,
+        Aov::Indirect,
+    ];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Aov::Depth => "depth",
+            Aov::Normal => "normal",
+            Aov::Albedo => "albedo",
+            Aov::ObjectId => "object_id",
+            Aov::MaterialId => "material_id",
+            Aov::Direct => "direct",
+            Aov::Indirect => "indirect",
+        }
+    }
+
+    /// Whether the variable holds colors, which depend on the color space, rather than
+    /// distances, directions or ids.
+    pub fn is_color(&self) -> bool {
+        matches!(self, Aov::Albedo | Aov::Direct | Aov::Indirect)
+    }
+
+    /// The channels the variable is stored as in OpenEXR files.
+    pub fn channels(&self) -> &'static [&'static str] {
+        match self {
+            Aov::Depth => &["Z"],
+            Aov::Normal => &["X", "Y", "Z"],
+            Aov::ObjectId | Aov::MaterialId => &["id"],
+            Aov::Albedo | Aov::Direct | Aov::Indirect => &["R", "G", "B"],
+        }
+    }
+
+    /// Converts the image into something that can be looked at in an ordinary image file.
+    /// Depths are scaled so the farthest surface is white, normals are mapped from `[-1, 1]` to
+    /// `[0, 1]`, and ids are given arbitrary but distinct colors.  The rest are left alone.
+    pub fn visualize(&self, image: &Framebuffer) -> Framebuffer {
+        let pixels = match self {
+            Aov::Depth => {
+                let farthest = image
+                    .pixels()
+                    .iter()
+                    .map(|depth| depth.red)
+                    .filter(|depth| depth.is_finite())
+                    .fold(0.0_f32, f32::max);
+                image
+                    .pixels()
+                    .iter()
+                    .map(|depth| match depth.red {
+                        d if d.is_finite() && farthest > 0.0 => {
+                            let d = d / farthest;
+                            Color::new(d, d, d)
+                        }
+                        _ => Color::default(),
+                    })
+                    .collect()
+            }
+            Aov::Normal => image
+                .pixels()
+                .iter()
+                .map(|n| Color::new(0.5 + 0.5 * n.red, 0.5 + 0.5 * n.green, 0.5 + 0.5 * n.blue))
+                .collect(),
+            Aov::ObjectId | Aov::MaterialId => image
+                .pixels()
+                .iter()
+                .map(|id| id_color(id.red as u32))
+                .collect(),
+            Aov::Albedo | Aov::Direct | Aov::Indirect => image.pixels().to_vec(),
+        };
+        Framebuffer::from_pixels(image.width(), image.height(), pixels)
+    }
+}
+
+impl fmt::Display for Aov {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+impl FromStr for Aov {
+    type Err = String;
+
+    fn from_str(name: &str) -> Result<Aov, String> {
+        Aov::ALL
+            .iter()
+            .copied()
+            .find(|aov| aov.name() == name)
+            .ok_or_else(|| format!("there's no output variable called `{}`", name))
+    }
+}
+
+/// Picks a color for an id, so that neighbouring ids look different.  Zero stays black.
+fn id_color(id: u32) -> Color {
+    if id == 0 {
+        return Color::default();
+    }
+    let hash = id.wrapping_mul(0x9e37_79b9);
+    let channel = |shift: u32| 0.2 + 0.8 * ((hash >> shift) & 0xff) as f32 / 255.0;
+    Color::new(channel(24), channel(16), channel(8))
+}
+
+/// The beauty pass together with the output variables asked for.
+#[derive(Debug, Clone)]
+pub struct AovImages {
+    pub beauty: Framebuffer,
+    aovs: Vec<(Aov, Framebuffer)>,
+}
+
+impl AovImages {
+    pub fn get(&self, aov: Aov) -> Option<&Framebuffer> {
+        self.aovs
+            .iter()
+            .find(|(a, _)| *a == aov)
+            .map(|(_, image)| image)
+    }
+
+    /// Takes an output variable out, so it won't be written with the rest.
+    pub fn remove(&mut self, aov: Aov) -> Option<Framebuffer> {
+        let index = self.aovs.iter().position(|(a, _)| *a == aov)?;
+        Some(self.aovs.remove(index).1)
+    }
+
+    /// The output variables, in the order they were asked for.
+    pub fn aovs(&self) -> impl Iterator<Item = (Aov, &Framebuffer)> {
+        self.aovs.iter().map(|(aov, image)| (*aov, image))
+    }
+
+    /// Converts the beauty pass and every output variable holding colors into another color
+    /// space.  The rest are left as they are.
+    pub fn converted(&self, color_space: ColorSpace) -> AovImages {
+        AovImages {
+            beauty: self.beauty.converted(color_space),
+            aovs: self
+                .aovs
+                .iter()
+                .map(|(aov, image)| {
+                    if aov.is_color() {
+                        (*aov, image.converted(color_space))
+                    } else {
+                        (*aov, image.clone())
+                    }
+                })
+                .collect(),
+        }
+    }
+
+    /// Writes every image into one OpenEXR file, with the chromaticities of the beauty pass.
+    /// The beauty pass is stored as `R`, `G` and `B`, and each output variable as channels named
+    /// after it, such as `depth.Z` or `normal.X`.  Ids are always stored as 32 bit floats, so
+    /// they come back exactly.
+    pub fn write_exr<W: Write + Seek>(
+        &self,
+        writer: W,
+        precision: ExrPrecision,
+        compression: ExrCompression,
+    ) -> io::Result<()> {
+        let mut layers = vec![ExrLayer::rgb(None, &self.beauty)];
+        for (aov, image) in self.aovs() {
+            let layer = ExrLayer::new(Some(aov.name()), image, aov.channels());
+            layers.push(match aov {
+                Aov::ObjectId | Aov::MaterialId => layer.exact(),
+                _ => layer,
+            });
+        }
+        write_exr(writer, &layers, precision, compression)
+    }
+}
+
+/// Everything gathered from the samples of one pixel.
+struct PixelAovs {
+    beauty: Color,
+    depth: f32,
+    normal: Vector3<f32>,
+    albedo: Color,
+    object: Option<usize>,
+    material: Option<usize>,
+    direct: Color,
+    indirect: Color,
+    /// How many samples hit something
+    hits: u32,
+}
+
+impl PixelAovs {
+    fn new() -> PixelAovs {
+        PixelAovs {
+            beauty: Color::default(),
+            depth: 0.0,
+            normal: Vector3::zeros(),
+            albedo: Color::default(),
+            object: None,
+            material: None,
+            direct: Color::default(),
+            indirect: Color::default(),
+            hits: 0,
+        }
+    }
+
+    fn value(&self, aov: Aov, samples: u32) -> Color {
+        let samples = samples.max(1) as f32;
+        let id = |id: Option<usize>| {
+            let id = id.map_or(0.0, |id| (id + 1) as f32);
+            Color::new(id, id, id)
+        };
+        match aov {
+            Aov::Depth if self.hits == 0 => Color::new(f32::INFINITY, f32::INFINITY, f32::INFINITY),
+            Aov::Depth => {
+                let depth = self.depth / self.hits as f32;
+                Color::new(depth, depth, depth)
+            }
+            Aov::Normal => {
+                let n = self
+                    .normal
+                    .try_normalize(0.0)
+                    .unwrap_or_else(Vector3::zeros);
+                Color::new(n.x, n.y, n.z)
+            }
+            Aov::Albedo => self.albedo / samples,
+            Aov::ObjectId => id(self.object),
+            Aov::MaterialId => id(self.material),
+            Aov::Direct => self.direct / samples,
+            Aov::Indirect => self.indirect / samples,
+        }
+    }
+}
+
+impl Scene {
+    /// Renders `region` along with the given output variables.  The beauty pass comes out
+    /// exactly as `render_region` would draw it.
+    ///
+    /// # Panics
+    /// Panics if `region` doesn't fit within the image.
+    pub fn render_aovs(&self, region: Region, aovs: &[Aov]) -> AovImages {
+        assert!(
+            region.fits(self.width, self.height),
+            "region lies outside the image"
+        );
+        let pixels = (0..region.pixel_count())
+            .into_par_iter()
+            .map(|index| {
+                let x = region.x + (index % u64::from(region.width)) as u32;
+                let y = region.y + (index / u64::from(region.width)) as u32;
+                self.sample_aovs(x, y)
+            })
+            .collect::<Vec<_>>();
+
+        let image = |value: &dyn Fn(&PixelAovs) -> Color| {
+            let pixels = pixels.iter().map(value).collect();
+            Framebuffer::from_pixels(region.width, region.height, pixels)
+        };
+        AovImages {
+            beauty: image(&|pixel| pixel.beauty / self.samples as f32),
+            aovs: aovs
+                .iter()
+                .map(|&aov| (aov, image(&|pixel| pixel.value(aov, self.samples))))
+                .collect(),
+        }
+    }
+
+    /// Takes every sample of the pixel at (x, y), in the same way as `sample_pixel`.
+    fn sample_aovs(&self, x: u32, y: u32) -> PixelAovs {
+        let mut pixel = PixelAovs::new();
+        for sample in 0..self.samples {
+            let mut rng = self.sample_rng(x, y, sample);
+            let ray = self.create_camera_ray(x, y, &mut rng);
+            let i = match self.trace(&ray, 0) {
+                Some(i) => i,
+                None => {
+                    pixel.beauty += self.background;
+                    pixel.albedo += self.background;
+                    pixel.direct += self.background;
+                    continue;
+                }
+            };
+
+            let (direct, indirect) = self.shade_parts(&i, 0, &mut rng);
+            let material = self.material_at(&i);
+            pixel.beauty += direct + indirect;
+            pixel.direct += direct;
+            pixel.indirect += indirect;
+            pixel.albedo += self.material(material).albedo(&i.surface);
+            pixel.depth += i.surface.t * ray.direction().norm();
+            pixel.normal += i.surface.shading_normal;
+            pixel.hits += 1;
+            if sample == 0 {
+                pixel.object = self.objects().position(|g| ptr::eq(g, i.elem));
+                pixel.material = Some(material.0);
+            }
+        }
+        pixel
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_aovs() {
+        let mut scene = Scene::new(4, 3, 10.0, 2, Color::new(0.1, 0.2, 0.3));
+        let white = scene.add_material(Diffuse::new(Color::new(1.0, 0.5, 1.0), 0.5));
+        scene.add_geometry(Sphere::new(Point3::new(0.0, 0.0, -20.0), 1.0, white));
+        scene.add_geometry(Sphere::new(Point3::new(0.0, 0.0, 102.0), 100.0, white));
+        scene.add_light(SphericalLight::new(
+            Point3::new(0.0, 0.0, 0.0),
+            Color::new(1.0, 1.0, 1.0),
+            50.0,
+        ));
+
+        let images = scene.render_aovs(Region::new(0, 0, 4, 3), &Aov::ALL);
+        assert_eq!(images.beauty, scene.render_image());
+
+        // the middle of the image looks straight at the wall two units away
+        let depth = images.get(Aov::Depth).unwrap().pixel(2, 1).red;
+        assert!((depth - 2.0).abs() < 0.1);
+        let normal = images.get(Aov::Normal).unwrap().pixel(2, 1);
+        assert!(normal.blue < -0.9);
+        assert_eq!(
+            images.get(Aov::Albedo).unwrap().pixel(2, 1),
+            Color::new(0.5, 0.25, 0.5)
+        );
+        assert_eq!(images.get(Aov::ObjectId).unwrap().pixel(2, 1).red, 2.0);
+        assert_eq!(images.get(Aov::MaterialId).unwrap().pixel(2, 1).red, 1.0);
+        let direct = images.get(Aov::Direct).unwrap().pixel(2, 1);
+        assert!(direct.red > 0.0);
+
+        assert_eq!("object_id".parse(), Ok(Aov::ObjectId));
+        assert!("beauty".parse::<Aov>().is_err());
+    }
+
+    #[test]
+    fn test_converted_exr() {
+        use exr::prelude::{ReadChannels, ReadLayers};
+        use std::io::Cursor;
+
+        let red = Framebuffer::from_pixels(1, 1, vec![Color::new(1.0, 0.0, 0.0)]);
+        let depth = Framebuffer::from_pixels(1, 1, vec![Color::new(2.0, 2.0, 2.0)]);
+        let images = AovImages {
+            beauty: red.clone(),
+            aovs: vec![(Aov::Albedo, red), (Aov::Depth, depth)],
+        }
+        .converted(ColorSpace::AcesCg);
+
+        let mut data = Cursor::new(Vec::new());
+        images
+            .write_exr(&mut data, ExrPrecision::Float, ExrCompression::None)
+            .unwrap();
+        let image = exr::prelude::read()
+            .no_deep_data()
+            .largest_resolution_level()
+            .all_channels()
+            .first_valid_layer()
+            .all_attributes()
+            .from_buffered(Cursor::new(data.into_inner()))
+            .unwrap();
+        let chromaticities = image.attributes.chromaticities.unwrap();
+        assert!((chromaticities.red.0 - 0.713).abs() < 1e-6);
+        let channel = |name: &str| {
+            let channels = &image.layer_data.channel_data.list;
+            let channel = channels.iter().find(|c| c.name.to_string() == name);
+            channel.unwrap().sample_data.values_as_f32().next().unwrap()
+        };
+
+        // the colors all went into ACEScg together, while depth stays a distance
+        let aces = ColorSpace::LinearSrgb.convert(Color::new(1.0, 0.0, 0.0), ColorSpace::AcesCg);
+        for &(name, value) in &[("R", aces.red), ("G", aces.green), ("B", aces.blue)] {
+            assert_eq!(channel(name), value);
+            assert_eq!(channel(&format!("albedo.{}", name)), value);
+        }
+        assert_eq!(channel("depth.Z"), 2.0);
+    }
+}
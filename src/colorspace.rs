@@ -0,0 +1,204 @@
+//! The RGB color spaces images can be stored in, and converting colors between them.
+//!
+//! Rendering happens in linear sRGB, the primaries of Rec. 709 with a D65 white.  Finished
+//! images can be converted to a wider gamut for displays and compositing software that expect
+//! one.  Colors are converted through CIE XYZ, with a Bradford transform to adapt between white
+//! points.
+
+use nalgebra::*;
+
+use std::fmt;
+use std::str::FromStr;
+
+use crate::prelude::*;
+
+/// The chromaticity of D65, the white point of sRGB and Display P3.
+const D65: [f64; 2] = [0.3127, 0.3290];
+
+/// The chromaticity of the white point ACES uses, which is close to D60.
+const ACES_WHITE: [f64; 2] = [0.32168, 0.33767];
+
+/// An RGB color space holding linear light.  The transfer curve is applied separately, when
+/// colors are encoded into a file that needs one.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum ColorSpace {
+    /// The Rec. 709 primaries and D65 white shared by sRGB, which the renderer works in
+    #[default]
+    LinearSrgb,
+    /// The DCI-P3 primaries with a D65 white, as used by wide gamut displays
+    DisplayP3,
+    /// The AP1 primaries and the ACES white point, for compositing and grading
+    AcesCg,
+}
+
+impl ColorSpace {
+    pub const ALL: [ColorSpace; 3] = [
+        ColorSpace::LinearSrgb,
+        ColorSpace::DisplayP3,
+        ColorSpace::AcesCg,
+    ];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            ColorSpace::LinearSrgb => "srgb",
+            ColorSpace::DisplayP3 => "display-p3",
+            ColorSpace::AcesCg => "acescg",
+        }
+    }
+
+    /// The chromaticities of the red, green and blue primaries, then of the white point.
+    pub fn chromaticities(&self) -> [[f64; 2]; 4] {
+        match self {
+            ColorSpace::LinearSrgb => [[0.64, 0.33], [0.30, 0.60], [0.15, 0.06], D65],
+            ColorSpace::DisplayP3 => [[0.680, 0.320], [0.265, 0.690], [0.150, 0.060], D65],
+            ColorSpace::AcesCg => [[0.713, 0.293], [0.165, 0.830], [0.128, 0.044], ACES_WHITE],
+        }
+    }
+
+    /// The code for the primaries in ITU-T H.273, as stored in the cICP chunk of PNG files, or
+    /// `None` for spaces it has no code for.
+    pub fn cicp_primaries(&self) -> Option<u8> {
+        match self {
+            ColorSpace::LinearSrgb => Some(1),
+            ColorSpace::DisplayP3 => Some(12),
+            ColorSpace::AcesCg => None,
+        }
+    }
+
+    /// The matrix taking colors in this space to CIE XYZ, relative to the space's own white.
+    pub fn to_xyz(&self) -> Matrix3<f64> {
+        let [red, green, blue, white] = self.chromaticities();
+        let xyz = |[x, y]: [f64; 2]| Vector3::new(x / y, 1.0, (1.0 - x - y) / y);
+        let primaries = Matrix3::from_columns(&[xyz(red), xyz(green), xyz(blue)]);
+        // scale each primary so that equal amounts of all three make the white point
+        let inverse = primaries
+            .try_inverse()
+            .expect("primaries are linearly independent");
+        primaries * Matrix3::from_diagonal(&(inverse * xyz(white)))
+    }
+
+    /// The matrix taking colors in this space to `other`.
+    pub fn conversion_to(&self, other: ColorSpace) -> Matrix3<f64> {
+        let from_xyz = other
+            .to_xyz()
+            .try_inverse()
+            .expect("primaries are linearly independent");
+        let adapt = bradford(self.chromaticities()[3], other.chromaticities()[3]);
+        from_xyz * adapt * self.to_xyz()
+    }
+
+    /// Converts a linear color in this space to `other`.
+    pub fn convert(&self, color: Color, other: ColorSpace) -> Color {
+        if *self == other {
+            return color;
+        }
+        transform(&self.conversion_to(other), color)
+    }
+}
+
+impl fmt::Display for ColorSpace {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+impl FromStr for ColorSpace {
+    type Err = String;
+
+    fn from_str(name: &str) -> Result<ColorSpace, String> {
+        ColorSpace::ALL
+            .iter()
+            .copied()
+            .find(|space| space.name() == name)
+            .ok_or_else(|| format!("there's no color space called `{}`", name))
+    }
+}
+
+/// Multiplies a color by a matrix.
+fn transform(m: &Matrix3<f64>, c: Color) -> Color {
+    let v = m * Vector3::new(f64::from(c.red), f64::from(c.green), f64::from(c.blue));
+    Color::new(v.x as f32, v.y as f32, v.z as f32)
+}
+
+/// The Bradford chromatic adaptation from one white point to another, in XYZ.
+fn bradford(from: [f64; 2], to: [f64; 2]) -> Matrix3<f64> {
+    if from == to {
+        return Matrix3::identity();
+    }
+    #[rustfmt::skip]
+    let cone = Matrix3::new(
+        0.8951, 0.2664, -0.1614,
+        -0.7502, 1.7135, 0.0367,
+        0.0389, -0.0685, 1.0296,
+    );
+    let xyz = |[x, y]: [f64; 2]| Vector3::new(x / y, 1.0, (1.0 - x - y) / y);
+    let (source, destination) = (cone * xyz(from), cone * xyz(to));
+    let scale = Matrix3::from_diagonal(&destination.component_div(&source));
+    cone.try_inverse()
+        .expect("the Bradford matrix is invertible")
+        * scale
+        * cone
+}
+
+impl Framebuffer {
+    /// Converts the image's colors into another color space.
+    pub fn converted(&self, color_space: ColorSpace) -> Framebuffer {
+        if self.color_space == color_space {
+            return self.clone();
+        }
+        let matrix = self.color_space.conversion_to(color_space);
+        let pixels = self
+            .pixels()
+            .iter()
+            .map(|&color| transform(&matrix, color))
+            .collect();
+        Framebuffer::from_pixels(self.width(), self.height(), pixels).with_color_space(color_space)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn close(a: Color, b: Color) -> bool {
+        (a.red - b.red).abs() < 1e-3
+            && (a.green - b.green).abs() < 1e-3
+            && (a.blue - b.blue).abs() < 1e-3
+    }
+
+    #[test]
+    fn test_conversion() {
+        // the well known sRGB to XYZ matrix
+        let xyz = ColorSpace::LinearSrgb.to_xyz();
+        assert!((xyz[(0, 0)] - 0.4124).abs() < 1e-4);
+        assert!((xyz[(1, 1)] - 0.7152).abs() < 1e-4);
+        assert!((xyz[(2, 2)] - 0.9505).abs() < 1e-4);
+
+        // sRGB red is inside the P3 gamut, and white stays white everywhere
+        let red = ColorSpace::LinearSrgb.convert(Color::new(1.0, 0.0, 0.0), ColorSpace::DisplayP3);
+        assert!(close(red, Color::new(0.8225, 0.0332, 0.0171)));
+        let white = Color::new(1.0, 1.0, 1.0);
+        for &from in &ColorSpace::ALL {
+            for &to in &ColorSpace::ALL {
+                assert!(close(from.convert(white, to), white), "{} to {}", from, to);
+                let there = from.convert(Color::new(0.2, 0.5, 0.9), to);
+                assert!(close(to.convert(there, from), Color::new(0.2, 0.5, 0.9)));
+            }
+        }
+        let aces = ColorSpace::LinearSrgb.convert(Color::new(1.0, 0.0, 0.0), ColorSpace::AcesCg);
+        assert!(close(aces, Color::new(0.6131, 0.0702, 0.0206)));
+        assert_eq!("display-p3".parse(), Ok(ColorSpace::DisplayP3));
+    }
+
+    #[test]
+    fn test_srgb() {
+        for &(linear, encoded) in &[(0.0, 0.0), (0.002, 0.02584), (0.214, 0.5), (1.0, 1.0)] {
+            let color = Color::new(linear, linear, linear);
+            assert!(close(
+                color.to_srgb(),
+                Color::new(encoded, encoded, encoded)
+            ));
+            assert!(close(color.to_srgb().from_srgb(), color));
+        }
+    }
+}
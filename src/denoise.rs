@@ -0,0 +1,259 @@
+//! Removing the noise left by rendering with few samples, guided by output variables.
+//!
+//! The filter is the edge-avoiding à-trous wavelet transform of Dammertz et al.: a small blur
+//! is applied several times with ever wider gaps between its taps, and each tap is weighted by
+//! how alike the two pixels are in color, albedo, normal and depth, so edges and texture survive
+//! while the noise on flat surfaces is smoothed out.
+
+use rayon::prelude::*;
+
+use crate::prelude::*;
+
+/// Weights of the taps of the B3 spline kernel, from the leftmost to the rightmost.
+const KERNEL: [f32; 5] = [1.0 / 16.0, 1.0 / 4.0, 3.0 / 8.0, 1.0 / 4.0, 1.0 / 16.0];
+
+/// Keeps albedo from dividing by zero when it's taken out of the image.
+const MIN_ALBEDO: f32 = 1e-3;
+
+/// Smooths out noise in rendered images.  The `*_sigma` settings say how different two pixels
+/// may be before they stop being blurred together; larger values blur more.
+#[derive(Debug, Clone)]
+pub struct Denoiser {
+    /// Passes of the filter.  Each doubles the gap between taps, so five passes reach 62 pixels
+    /// across.
+    pub iterations: u32,
+    /// Allowed difference in color, which shrinks by half with each pass
+    pub color_sigma: f32,
+    pub albedo_sigma: f32,
+    pub normal_sigma: f32,
+    /// Allowed difference in depth, relative to the depth of the pixel being filtered
+    pub depth_sigma: f32,
+}
+
+impl Default for Denoiser {
+    fn default() -> Denoiser {
+        Denoiser {
+            iterations: 5,
+            color_sigma: 0.6,
+            albedo_sigma: 0.1,
+            normal_sigma: 0.3,
+            depth_sigma: 0.1,
+        }
+    }
+}
+
+/// The per-pixel information the denoiser is guided by.  Each is optional, but the more there
+/// are the better edges are kept.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct DenoiseGuides<'a> {
+    pub albedo: Option<&'a Framebuffer>,
+    pub normal: Option<&'a Framebuffer>,
+    pub depth: Option<&'a Framebuffer>,
+}
+
+impl<'a> DenoiseGuides<'a> {
+    /// Uses whichever of the albedo, normal and depth variables were rendered.
+    pub fn from_aovs(images: &'a AovImages) -> DenoiseGuides<'a> {
+        DenoiseGuides {
+            albedo: images.get(Aov::Albedo),
+            normal: images.get(Aov::Normal),
+            depth: images.get(Aov::Depth),
+        }
+    }
+}
+
+impl Denoiser {
+    /// Denoises `image`.  When there's an albedo guide, it's divided out of the image first and
+    /// put back afterwards, so that only the lighting is blurred and textures stay sharp.
+    ///
+    /// # Panics
+    /// Panics if a guide isn't the same size as the image.
+    pub fn denoise(&self, image: &Framebuffer, guides: &DenoiseGuides) -> Framebuffer {
+        let (width, height) = (image.width(), image.height());
+        for guide in [guides.albedo, guides.normal, guides.depth]
+            .iter()
+            .flatten()
+        {
+            assert!(
+                (guide.width(), guide.height()) == (width, height),
+                "guides must be the same size as the image"
+            );
+        }
+
+        let albedo = |index: usize| {
+            guides.albedo.map(|albedo| {
+                let a = albedo.pixels()[index];
+                Color::new(
+                    a.red.max(MIN_ALBEDO),
+                    a.green.max(MIN_ALBEDO),
+                    a.blue.max(MIN_ALBEDO),
+                )
+            })
+        };
+        let mut pixels = image
+            .pixels()
+            .iter()
+            .enumerate()
+            .map(|(index, &color)| match albedo(index) {
+                Some(a) => Color::new(
+                    color.red / a.red,
+                    color.green / a.green,
+                    color.blue / a.blue,
+                ),
+                None => color,
+            })
+            .collect::<Vec<_>>();
+
+        let mut color_sigma = self.color_sigma;
+        for iteration in 0..self.iterations {
+            let step = 1_i64 << iteration.min(30);
+            pixels = self.pass(&pixels, width, height, step, color_sigma, guides);
+            color_sigma /= 2.0;
+        }
+
+        let pixels = pixels
+            .into_iter()
+            .enumerate()
+            .map(|(index, color)| match albedo(index) {
+                Some(a) => color * a,
+                None => color,
+            })
+            .collect();
+        Framebuffer::from_pixels(width, height, pixels).with_color_space(image.color_space())
+    }
+
+    /// Blurs the image once, with `step` pixels between taps.
+    fn pass(
+        &self,
+        pixels: &[Color],
+        width: u32,
+        height: u32,
+        step: i64,
+        color_sigma: f32,
+        guides: &DenoiseGuides,
+    ) -> Vec<Color> {
+        let row_length = (width as usize).max(1);
+        let mut filtered = vec![Color::default(); pixels.len()];
+        filtered
+            .par_chunks_mut(row_length)
+            .enumerate()
+            .for_each(|(y, row)| {
+                for (x, out) in row.iter_mut().enumerate() {
+                    let p = y * row_length + x;
+                    let mut sum = Color::default();
+                    let mut total = 0.0;
+                    for (j, ky) in KERNEL.iter().enumerate() {
+                        let qy = y as i64 + (j as i64 - 2) * step;
+                        if qy < 0 || qy >= i64::from(height) {
+                            continue;
+                        }
+                        for (i, kx) in KERNEL.iter().enumerate() {
+                            let qx = x as i64 + (i as i64 - 2) * step;
+                            if qx < 0 || qx >= i64::from(width) {
+                                continue;
+                            }
+                            let q = qy as usize * row_length + qx as usize;
+                            let weight = ky
+                                * kx
+                                * gaussian(distance2(pixels[p], pixels[q]), color_sigma)
+                                * self.guide_weight(p, q, guides);
+                            sum += pixels[q] * weight;
+                            total += weight;
+                        }
+                    }
+                    // the center tap always has a weight of its own, so `total` isn't zero
+                    *out = sum / total;
+                }
+            });
+        filtered
+    }
+
+    /// How much the guides say pixels `p` and `q` belong together, from 0 to 1.
+    fn guide_weight(&self, p: usize, q: usize, guides: &DenoiseGuides) -> f32 {
+        let mut weight = 1.0;
+        if let Some(albedo) = guides.albedo {
+            let (a, b) = (albedo.pixels()[p], albedo.pixels()[q]);
+            weight *= gaussian(distance2(a, b), self.albedo_sigma);
+        }
+        if let Some(normal) = guides.normal {
+            let (a, b) = (normal.pixels()[p], normal.pixels()[q]);
+            weight *= gaussian(distance2(a, b), self.normal_sigma);
+        }
+        if let Some(depth) = guides.depth {
+            let (a, b) = (depth.pixels()[p].red, depth.pixels()[q].red);
+            weight *= match (a.is_finite(), b.is_finite()) {
+                (true, true) => {
+                    let difference = (a - b) / a.abs().max(1e-6);
+                    gaussian(difference * difference, self.depth_sigma)
+                }
+                // both looking out at the background
+                (false, false) => 1.0,
+                _ => 0.0,
+            };
+        }
+        weight
+    }
+}
+
+fn distance2(a: Color, b: Color) -> f32 {
+    let (r, g, b) = (a.red - b.red, a.green - b.green, a.blue - b.blue);
+    r * r + g * g + b * b
+}
+
+fn gaussian(distance2: f32, sigma: f32) -> f32 {
+    (-distance2 / (sigma * sigma).max(1e-12)).exp()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::prelude::*;
+
+    /// The spread of the red channel over part of an image.
+    fn variance(image: &Framebuffer, xs: std::ops::Range<u32>) -> f32 {
+        let values = (0..image.height())
+            .flat_map(|y| xs.clone().map(move |x| (x, y)))
+            .map(|(x, y)| image.pixel(x, y).red)
+            .collect::<Vec<_>>();
+        let mean = values.iter().sum::<f32>() / values.len() as f32;
+        values.iter().map(|v| (v - mean) * (v - mean)).sum::<f32>() / values.len() as f32
+    }
+
+    #[test]
+    fn test_denoise() {
+        // two walls meeting down the middle, one lit more brightly than the other, with noise
+        let (width, height) = (32, 16);
+        let mut rng = StdRng::seed_from_u64(1);
+        let mut noisy = Framebuffer::new(width, height);
+        let mut normal = Framebuffer::new(width, height);
+        let albedo = Framebuffer::from_pixels(
+            width,
+            height,
+            vec![Color::new(0.5, 0.5, 0.5); (width * height) as usize],
+        );
+        for (x, y) in (0..width).flat_map(|x| (0..height).map(move |y| (x, y))) {
+            let (light, facing) = if x < width / 2 {
+                (0.2, Color::new(1.0, 0.0, 0.0))
+            } else {
+                (0.4, Color::new(0.0, 1.0, 0.0))
+            };
+            let value = light + rng.gen_range(-0.05, 0.05);
+            noisy.set_pixel(x, y, Color::new(value, value, value));
+            normal.set_pixel(x, y, facing);
+        }
+
+        let guides = DenoiseGuides {
+            albedo: Some(&albedo),
+            normal: Some(&normal),
+            depth: None,
+        };
+        let clean = Denoiser::default().denoise(&noisy, &guides);
+
+        // the noise is smoothed out on each wall
+        assert!(variance(&clean, 0..16) < variance(&noisy, 0..16) / 10.0);
+        assert!(variance(&clean, 16..32) < variance(&noisy, 16..32) / 10.0);
+        // but the walls aren't blurred into each other
+        assert!((clean.pixel(15, 8).red - 0.2).abs() < 0.02);
+        assert!((clean.pixel(16, 8).red - 0.4).abs() < 0.02);
+    }
+}
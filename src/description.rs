@@ -0,0 +1,1593 @@
+//! Declarative scene files, so scenes can be tweaked without recompiling.  Scenes are written in
+//! TOML:
+//!
+//! ```toml
+//! [render]
+//! width = 1280
+//! height = 720
+//! samples = 100
+//!
+//! [camera]
+//! eye = [0, 1, -5]
+//! target = [0, 0, 0]
+//! fov = 60
+//!
+//! [materials.red]
+//! type = "diffuse"
+//! color = { gamma = [1, 0, 0] }
+//! albedo = 0.4
+//!
+//! [[lights]]
+//! type = "spherical"
+//! position = [1, 1, 1]
+//! color = [1, 1, 1]
+//! intensity = 100
+//!
+//! [[objects]]
+//! material = "red"
+//! shape = { type = "sphere", center = [0, 0, 0], radius = 1 }
+//! ```
+//!
+//! Colors are either linear `[r, g, b]` triples, `{ srgb = [r, g, b] }` for sRGB encoded colors
+//! like the ones picked in an image editor, or `{ gamma = [r, g, b] }` for colors encoded with a
+//! plain 2.2 power curve.  Relative paths, such as height map images, are resolved against the
+//! directory of the scene file.
+//!
+//! The camera's `fov` is its vertical field of view in degrees, 90 unless given.  How much it
+//! sees across follows from the image's aspect ratio.
+//!
+//! Scenes built in code can be written out in the same format with `Scene::save`, as long as
+//! they don't contain custom shapes, materials or distance functions.
+
+use nalgebra::*;
+use serde::ser::{Error as _, SerializeStruct};
+use serde::{Deserialize, Serialize, Serializer};
+use std::collections::BTreeMap;
+use std::error::Error;
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use crate::prelude::*;
+
+/// Errors that can occur while loading a scene file.  Each one carries the path to the offending
+/// value within the file, such as `objects[2].shape.radius`.
+#[derive(Debug)]
+pub enum SceneError {
+    Io(io::Error),
+    /// The file isn't valid TOML, or a value has the wrong type or an unknown key
+    Parse {
+        path: String,
+        message: String,
+    },
+    /// A value is well formed but doesn't make sense, such as a reference to a missing material
+    Invalid {
+        path: String,
+        message: String,
+    },
+    /// A file the scene refers to couldn't be loaded
+    Asset {
+        path: String,
+        source: Box<dyn Error + Send + Sync>,
+    },
+    /// The scene contains something that can't be written to a file, such as a custom material
+    Save {
+        path: String,
+        message: String,
+    },
+}
+
+impl fmt::Display for SceneError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SceneError::Io(e) => write!(f, "couldn't read scene: {}", e),
+            SceneError::Parse { path, message }
+            | SceneError::Invalid { path, message }
+            | SceneError::Save { path, message } => write!(f, "{}: {}", path, message),
+            SceneError::Asset { path, source } => write!(f, "{}: {}", path, source),
+        }
+    }
+}
+
+impl Error for SceneError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            SceneError::Io(e) => Some(e),
+            SceneError::Asset { source, .. } => Some(source.as_ref()),
+            _ => None,
+        }
+    }
+}
+
+impl From<io::Error> for SceneError {
+    fn from(e: io::Error) -> Self {
+        SceneError::Io(e)
+    }
+}
+
+impl From<serde_path_to_error::Error<toml::de::Error>> for SceneError {
+    fn from(e: serde_path_to_error::Error<toml::de::Error>) -> Self {
+        SceneError::Parse {
+            path: e.path().to_string(),
+            message: e.inner().to_string(),
+        }
+    }
+}
+
+impl From<serde_path_to_error::Error<toml::ser::Error>> for SceneError {
+    fn from(e: serde_path_to_error::Error<toml::ser::Error>) -> Self {
+        SceneError::Save {
+            path: e.path().to_string(),
+            message: e.inner().to_string(),
+        }
+    }
+}
+
+fn invalid<P: fmt::Display, M: fmt::Display>(path: P, message: M) -> SceneError {
+    SceneError::Invalid {
+        path: path.to_string(),
+        message: message.to_string(),
+    }
+}
+
+impl Scene {
+    /// Loads a scene from a TOML file.
+    pub fn from_file<P: AsRef<Path>>(path: P) -> Result<Scene, SceneError> {
+        let path = path.as_ref();
+        let text = fs::read_to_string(path)?;
+        let directory = path.parent().unwrap_or_else(|| Path::new(""));
+        SceneDescription::parse(&text)?.build(directory)
+    }
+
+    /// Lists the files a scene is made from: the scene file itself, followed by every file it
+    /// refers to, such as height map images.
+    pub fn source_files<P: AsRef<Path>>(path: P) -> Result<Vec<PathBuf>, SceneError> {
+        let path = path.as_ref();
+        let text = fs::read_to_string(path)?;
+        let directory = path.parent().unwrap_or_else(|| Path::new(""));
+        let mut files = vec![path.to_path_buf()];
+        files.extend(
+            SceneDescription::parse(&text)?
+                .assets()
+                .map(|asset| directory.join(asset)),
+        );
+        Ok(files)
+    }
+
+    /// Loads a scene from TOML text.  Relative paths are resolved against the working
+    /// directory.
+    pub fn from_toml(text: &str) -> Result<Scene, SceneError> {
+        SceneDescription::parse(text)?.build(Path::new(""))
+    }
+
+    /// Writes the scene to a TOML file that `Scene::from_file` can load again.  Materials are
+    /// named after their ids, and the scene graph is flattened into a list of objects.
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> Result<(), SceneError> {
+        fs::write(path, self.to_toml()?)?;
+        Ok(())
+    }
+
+    /// Describes the scene in TOML.
+    pub fn to_toml(&self) -> Result<String, SceneError> {
+        let mut text = String::new();
+        serde_path_to_error::serialize(self, &mut toml::Serializer::new(&mut text))?;
+        Ok(text)
+    }
+}
+
+type Triple = [f32; 3];
+
+fn point(p: Triple) -> Point3<f32> {
+    Point3::new(p[0], p[1], p[2])
+}
+
+fn vector(v: Triple) -> Vector3<f32> {
+    Vector3::new(v[0], v[1], v[2])
+}
+
+/// Checks that the field `name` of the item at `path` is a positive length.
+fn positive(path: &str, name: &str, value: f32) -> Result<f32, SceneError> {
+    if value > 0.0 && value.is_finite() {
+        Ok(value)
+    } else {
+        Err(invalid(format!("{}.{}", path, name), "must be positive"))
+    }
+}
+
+/// Checks that the field `name` of the item at `path` is a vector with a direction, so it can
+/// be normalized.
+fn direction(path: &str, name: &str, v: Triple) -> Result<Vector3<f32>, SceneError> {
+    let v = vector(v);
+    if v.norm_squared() > 0.0 && v.iter().all(|c| c.is_finite()) {
+        Ok(v)
+    } else {
+        Err(invalid(format!("{}.{}", path, name), "must not be zero"))
+    }
+}
+
+fn triple(v: &Vector3<f32>) -> Triple {
+    [v.x, v.y, v.z]
+}
+
+/// Names the materials of saved scenes, since scenes only know them by id.
+fn material_name(id: MaterialId) -> String {
+    format!("material{}", id.0)
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub(crate) struct SceneDescription {
+    render: RenderDescription,
+    #[serde(default)]
+    camera: CameraDescription,
+    #[serde(default)]
+    materials: BTreeMap<String, MaterialDescription>,
+    #[serde(default)]
+    lights: Vec<LightDescription>,
+    #[serde(default)]
+    objects: Vec<ObjectDescription>,
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
+struct RenderDescription {
+    width: u32,
+    height: u32,
+    samples: u32,
+    /// How many times rays may bounce
+    #[serde(default = "default_depth")]
+    depth: u32,
+    /// Seeds the random numbers used to sample each pixel
+    #[serde(default)]
+    seed: u64,
+    #[serde(default)]
+    background: ColorDescription,
+}
+
+fn default_depth() -> u32 {
+    3
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+#[serde(deny_unknown_fields, default)]
+struct CameraDescription {
+    /// Vertical field of view, in degrees
+    fov: f32,
+    eye: Triple,
+    target: Triple,
+    up: Triple,
+}
+
+impl Default for CameraDescription {
+    fn default() -> Self {
+        CameraDescription {
+            fov: 90.0,
+            eye: [0.0, 0.0, 0.0],
+            target: [0.0, 0.0, 1.0],
+            up: [0.0, 1.0, 0.0],
+        }
+    }
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+#[serde(untagged)]
+enum ColorDescription {
+    Linear(Triple),
+    Gamma { gamma: Triple },
+    Srgb { srgb: Triple },
+}
+
+impl Default for ColorDescription {
+    fn default() -> Self {
+        ColorDescription::Linear([0.0, 0.0, 0.0])
+    }
+}
+
+impl From<Color> for ColorDescription {
+    fn from(color: Color) -> Self {
+        ColorDescription::Linear([color.red, color.green, color.blue])
+    }
+}
+
+impl From<&ColorDescription> for Color {
+    fn from(color: &ColorDescription) -> Self {
+        match color {
+            ColorDescription::Linear(c) => Color::new(c[0], c[1], c[2]),
+            ColorDescription::Gamma { gamma: c } => Color::new(c[0], c[1], c[2]).from_gamma(),
+            ColorDescription::Srgb { srgb: c } => Color::new(c[0], c[1], c[2]).from_srgb(),
+        }
+    }
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+#[serde(tag = "type", rename_all = "lowercase", deny_unknown_fields)]
+enum MaterialDescription {
+    Diffuse {
+        color: ColorDescription,
+        albedo: f32,
+    },
+    Reflective {
+        color: ColorDescription,
+        albedo: f32,
+    },
+    Pbr {
+        base_color: ColorDescription,
+        metallic: f32,
+        roughness: f32,
+        #[serde(default)]
+        emission: ColorDescription,
+        /// Multiplies the base color by the colors of mesh vertices
+        #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+        vertex_colors: bool,
+    },
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+#[serde(tag = "type", rename_all = "lowercase", deny_unknown_fields)]
+enum LightDescription {
+    Global {
+        direction: Triple,
+        color: ColorDescription,
+        intensity: f32,
+    },
+    Spherical {
+        position: Triple,
+        color: ColorDescription,
+        intensity: f32,
+    },
+    /// Cone angles are in degrees, measured from the axis
+    Spot {
+        position: Triple,
+        direction: Triple,
+        color: ColorDescription,
+        intensity: f32,
+        inner_angle: f32,
+        outer_angle: f32,
+    },
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
+struct ObjectDescription {
+    material: String,
+    shape: ShapeDescription,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    transform: Option<TransformDescription>,
+}
+
+/// Moves an object into place.  The object is scaled, then rotated about x, y and z in turn,
+/// then translated.  Transforms that can't be broken down like that, such as those of saved
+/// scenes, are given as a matrix instead.
+#[derive(Debug, Default, Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
+struct TransformDescription {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    translate: Option<Triple>,
+    /// Rotation about each axis, in degrees
+    #[serde(skip_serializing_if = "Option::is_none")]
+    rotate: Option<Triple>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    scale: Option<Triple>,
+    /// The top three rows of the transformation matrix
+    #[serde(skip_serializing_if = "Option::is_none")]
+    matrix: Option<[[f32; 4]; 3]>,
+}
+
+impl TransformDescription {
+    fn from_matrix(matrix: &Matrix4<f32>) -> TransformDescription {
+        let row = |i: usize| {
+            [
+                matrix[(i, 0)],
+                matrix[(i, 1)],
+                matrix[(i, 2)],
+                matrix[(i, 3)],
+            ]
+        };
+        TransformDescription {
+            matrix: Some([row(0), row(1), row(2)]),
+            ..TransformDescription::default()
+        }
+    }
+
+    /// The transform as a matrix, which is checked to be invertible so that objects can be
+    /// placed with it.
+    fn to_matrix(&self, path: &str) -> Result<Matrix4<f32>, SceneError> {
+        let matrix = self.build_matrix(path)?;
+        if matrix.try_inverse().is_none() {
+            return Err(invalid(
+                path,
+                "transforms must be invertible, so they can't scale anything to nothing",
+            ));
+        }
+        Ok(matrix)
+    }
+
+    fn build_matrix(&self, path: &str) -> Result<Matrix4<f32>, SceneError> {
+        if let Some(rows) = self.matrix {
+            if self.translate.is_some() || self.rotate.is_some() || self.scale.is_some() {
+                return Err(invalid(
+                    path,
+                    "transforms can't have both a `matrix` and `translate`, `rotate` or `scale`",
+                ));
+            }
+            let mut matrix = Matrix4::identity();
+            for (i, row) in rows.iter().enumerate() {
+                for (j, value) in row.iter().enumerate() {
+                    matrix[(i, j)] = *value;
+                }
+            }
+            return Ok(matrix);
+        }
+
+        let [rx, ry, rz] = self.rotate.unwrap_or_default();
+        let rotation = Rotation3::from_axis_angle(&Vector3::z_axis(), rz.to_radians())
+            * Rotation3::from_axis_angle(&Vector3::y_axis(), ry.to_radians())
+            * Rotation3::from_axis_angle(&Vector3::x_axis(), rx.to_radians());
+        Ok(
+            Matrix4::new_translation(&vector(self.translate.unwrap_or_default()))
+                * rotation.to_homogeneous()
+                * Matrix4::new_nonuniform_scaling(&vector(self.scale.unwrap_or([1.0; 3]))),
+        )
+    }
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+#[serde(tag = "type", rename_all = "lowercase", deny_unknown_fields)]
+enum ShapeDescription {
+    Sphere {
+        center: Triple,
+        radius: f32,
+    },
+    Plane {
+        point: Triple,
+        normal: Triple,
+    },
+    Cuboid {
+        min: Triple,
+        max: Triple,
+    },
+    Cylinder {
+        base: Triple,
+        radius: f32,
+        height: f32,
+    },
+    Cone {
+        base: Triple,
+        radius: f32,
+        height: f32,
+    },
+    Disk {
+        center: Triple,
+        normal: Triple,
+        radius: f32,
+    },
+    Quad {
+        corner: Triple,
+        u: Triple,
+        v: Triple,
+    },
+    Torus {
+        center: Triple,
+        major_radius: f32,
+        minor_radius: f32,
+    },
+    Heightfield {
+        corner: Triple,
+        size: Triple,
+        /// A PGM or PNG image
+        #[serde(skip_serializing_if = "Option::is_none")]
+        image: Option<PathBuf>,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        noise: Option<NoiseDescription>,
+        /// The heights themselves, one row at a time
+        #[serde(skip_serializing_if = "Option::is_none")]
+        heights: Option<Vec<Vec<f32>>>,
+    },
+    Csg(CsgDescription),
+    /// A triangle mesh, given vertex by vertex
+    Mesh {
+        positions: Vec<Triple>,
+        triangles: Vec<[usize; 3]>,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        normals: Option<Vec<Triple>>,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        uvs: Option<Vec<[f32; 2]>>,
+        /// Linear colors, one per vertex
+        #[serde(skip_serializing_if = "Option::is_none")]
+        colors: Option<Vec<Triple>>,
+    },
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
+struct NoiseDescription {
+    resolution: [usize; 2],
+    #[serde(default)]
+    seed: u64,
+    /// Four unless given, or fewer if the height map can't show that much detail
+    #[serde(skip_serializing_if = "Option::is_none")]
+    octaves: Option<u32>,
+}
+
+const DEFAULT_OCTAVES: u32 = 4;
+
+#[derive(Debug, Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
+struct CsgDescription {
+    operation: CsgOperationDescription,
+    left: Box<SolidDescription>,
+    right: Box<SolidDescription>,
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+enum CsgOperationDescription {
+    Union,
+    Intersection,
+    Difference,
+}
+
+/// An operand of a CSG operation.  Solids without a material use the object's.
+#[derive(Debug, Deserialize, Serialize)]
+#[serde(tag = "type", rename_all = "lowercase", deny_unknown_fields)]
+enum SolidDescription {
+    Sphere {
+        center: Triple,
+        radius: f32,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        material: Option<String>,
+    },
+    Cuboid {
+        min: Triple,
+        max: Triple,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        material: Option<String>,
+    },
+    Cylinder {
+        base: Triple,
+        radius: f32,
+        height: f32,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        material: Option<String>,
+    },
+    Csg(CsgDescription),
+}
+
+/// Keeps track of the materials declared so far while building a scene.
+struct Builder<'a> {
+    materials: BTreeMap<&'a str, MaterialId>,
+    directory: &'a Path,
+}
+
+impl<'a> Builder<'a> {
+    fn material(&self, path: &str, name: &str) -> Result<MaterialId, SceneError> {
+        self.materials
+            .get(name)
+            .copied()
+            .ok_or_else(|| invalid(path, format!("unknown material `{}`", name)))
+    }
+
+    fn shape(
+        &self,
+        path: &str,
+        shape: &ShapeDescription,
+        material: MaterialId,
+    ) -> Result<Geometry, SceneError> {
+        Ok(match *shape {
+            ShapeDescription::Sphere { center, radius } => {
+                Sphere::new(point(center), positive(path, "radius", radius)?, material).into()
+            }
+            ShapeDescription::Plane { point: p, normal } => {
+                Plane::new(point(p), direction(path, "normal", normal)?, material).into()
+            }
+            ShapeDescription::Cuboid { min, max } => {
+                Cuboid::new(point(min), point(max), material).into()
+            }
+            ShapeDescription::Cylinder {
+                base,
+                radius,
+                height,
+            } => Cylinder::new(
+                point(base),
+                positive(path, "radius", radius)?,
+                positive(path, "height", height)?,
+                material,
+            )
+            .into(),
+            ShapeDescription::Cone {
+                base,
+                radius,
+                height,
+            } => Cone::new(
+                point(base),
+                positive(path, "radius", radius)?,
+                positive(path, "height", height)?,
+                material,
+            )
+            .into(),
+            ShapeDescription::Disk {
+                center,
+                normal,
+                radius,
+            } => Disk::new(
+                point(center),
+                direction(path, "normal", normal)?,
+                positive(path, "radius", radius)?,
+                material,
+            )
+            .into(),
+            ShapeDescription::Quad { corner, u, v } => {
+                let (u, v) = (direction(path, "u", u)?, direction(path, "v", v)?);
+                if u.cross(&v).norm_squared() == 0.0 {
+                    return Err(invalid(
+                        format!("{}.v", path),
+                        "must not be parallel to `u`",
+                    ));
+                }
+                Quad::new(point(corner), u, v, material).into()
+            }
+            ShapeDescription::Torus {
+                center,
+                major_radius,
+                minor_radius,
+            } => {
+                let major_radius = positive(path, "major_radius", major_radius)?;
+                let minor_radius = positive(path, "minor_radius", minor_radius)?;
+                if minor_radius > major_radius {
+                    return Err(invalid(
+                        format!("{}.minor_radius", path),
+                        "must be no larger than `major_radius`",
+                    ));
+                }
+                Torus::new(point(center), major_radius, minor_radius, material).into()
+            }
+            ShapeDescription::Heightfield {
+                corner,
+                size,
+                ref image,
+                ref noise,
+                ref heights,
+            } => {
+                let map = self.height_map(path, image, noise, heights)?;
+                Heightfield::new(map, point(corner), vector(size), material).into()
+            }
+            ShapeDescription::Csg(ref csg) => self.csg(path, csg, material)?.into(),
+            ShapeDescription::Mesh {
+                ref positions,
+                ref triangles,
+                ref normals,
+                ref uvs,
+                ref colors,
+            } => {
+                let count = positions.len();
+                if let Some(i) = triangles.iter().position(|t| t.iter().any(|&v| v >= count)) {
+                    return Err(invalid(
+                        format!("{}.triangles[{}]", path, i),
+                        "triangle refers to a missing vertex",
+                    ));
+                }
+                let mut mesh = Mesh::new(
+                    positions.iter().copied().map(point).collect(),
+                    triangles.clone(),
+                    material,
+                );
+                if let Some(normals) = normals {
+                    if normals.len() != count {
+                        return Err(invalid(
+                            format!("{}.normals", path),
+                            "one normal per vertex",
+                        ));
+                    }
+                    mesh = mesh.with_normals(normals.iter().copied().map(vector).collect());
+                }
+                if let Some(uvs) = uvs {
+                    if uvs.len() != count {
+                        return Err(invalid(format!("{}.uvs", path), "one uv per vertex"));
+                    }
+                    mesh = mesh.with_uvs(uvs.iter().map(|uv| Point2::new(uv[0], uv[1])).collect());
+                }
+                if let Some(colors) = colors {
+                    if colors.len() != count {
+                        return Err(invalid(format!("{}.colors", path), "one color per vertex"));
+                    }
+                    mesh = mesh.with_colors(
+                        colors
+                            .iter()
+                            .map(|&[r, g, b]| Color::new(r, g, b))
+                            .collect(),
+                    );
+                }
+                mesh.into()
+            }
+        })
+    }
+
+    fn height_map(
+        &self,
+        path: &str,
+        image: &Option<PathBuf>,
+        noise: &Option<NoiseDescription>,
+        heights: &Option<Vec<Vec<f32>>>,
+    ) -> Result<HeightMap, SceneError> {
+        match (image, noise, heights) {
+            (Some(image), None, None) => {
+                HeightMap::load(self.directory.join(image)).map_err(|e| SceneError::Asset {
+                    path: format!("{}.image", path),
+                    source: Box::new(e),
+                })
+            }
+            (None, Some(noise), None) => {
+                let [width, depth] = noise.resolution;
+                if width < 2 || depth < 2 {
+                    return Err(invalid(
+                        format!("{}.noise.resolution", path),
+                        "height maps need at least 2x2 samples",
+                    ));
+                }
+                let max_octaves = HeightMap::max_noise_octaves(width, depth);
+                let octaves = noise.octaves.unwrap_or(DEFAULT_OCTAVES.min(max_octaves));
+                if octaves > max_octaves {
+                    return Err(invalid(
+                        format!("{}.noise.octaves", path),
+                        format!(
+                            "a {}x{} height map can't show more than {} octaves of detail",
+                            width, depth, max_octaves
+                        ),
+                    ));
+                }
+                Ok(HeightMap::noise(width, depth, noise.seed, octaves))
+            }
+            (None, None, Some(rows)) => {
+                let width = rows.first().map_or(0, Vec::len);
+                if rows.len() < 2 || width < 2 || rows.iter().any(|r| r.len() != width) {
+                    return Err(invalid(
+                        format!("{}.heights", path),
+                        "heights must be at least 2x2, with rows of the same length",
+                    ));
+                }
+                Ok(HeightMap::new(width, rows.len(), rows.concat()))
+            }
+            _ => Err(invalid(
+                path,
+                "heightfields need exactly one of `image`, `noise` or `heights`",
+            )),
+        }
+    }
+
+    fn csg(
+        &self,
+        path: &str,
+        csg: &CsgDescription,
+        material: MaterialId,
+    ) -> Result<Csg, SceneError> {
+        let operation = match csg.operation {
+            CsgOperationDescription::Union => CsgOperation::Union,
+            CsgOperationDescription::Intersection => CsgOperation::Intersection,
+            CsgOperationDescription::Difference => CsgOperation::Difference,
+        };
+        let left = self.solid(&format!("{}.left", path), &csg.left, material)?;
+        let right = self.solid(&format!("{}.right", path), &csg.right, material)?;
+        Ok(Csg::new(operation, left, right))
+    }
+
+    fn solid(
+        &self,
+        path: &str,
+        solid: &SolidDescription,
+        material: MaterialId,
+    ) -> Result<Solid, SceneError> {
+        let own = |name: &Option<String>| match name {
+            Some(name) => self.material(&format!("{}.material", path), name),
+            None => Ok(material),
+        };
+        Ok(match solid {
+            SolidDescription::Sphere {
+                center,
+                radius,
+                material,
+            } => Sphere::new(
+                point(*center),
+                positive(path, "radius", *radius)?,
+                own(material)?,
+            )
+            .into(),
+            SolidDescription::Cuboid { min, max, material } => {
+                Cuboid::new(point(*min), point(*max), own(material)?).into()
+            }
+            SolidDescription::Cylinder {
+                base,
+                radius,
+                height,
+                material,
+            } => Cylinder::new(
+                point(*base),
+                positive(path, "radius", *radius)?,
+                positive(path, "height", *height)?,
+                own(material)?,
+            )
+            .into(),
+            SolidDescription::Csg(csg) => self.csg(path, csg, material)?.into(),
+        })
+    }
+}
+
+impl Serialize for Color {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        [self.red, self.green, self.blue].serialize(serializer)
+    }
+}
+
+impl Serialize for Material {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let description = match self {
+            Material::Diffuse(d) => MaterialDescription::Diffuse {
+                color: d.color.into(),
+                albedo: d.albedo,
+            },
+            Material::Reflective(r) => MaterialDescription::Reflective {
+                color: r.color.into(),
+                albedo: r.albedo,
+            },
+            Material::Pbr(pbr) => {
+                let (base_color, vertex_colors) = match pbr.base_color {
+                    ColorSource::Constant(base) => (base, false),
+                    ColorSource::Vertex(factor) => (factor, true),
+                    ColorSource::Texture(..) => {
+                        return Err(S::Error::custom("textured materials can't be saved"))
+                    }
+                };
+                match pbr.emission {
+                    ColorSource::Constant(emission) if pbr.metallic_roughness.is_none() => {
+                        MaterialDescription::Pbr {
+                            base_color: base_color.into(),
+                            metallic: pbr.metallic,
+                            roughness: pbr.roughness,
+                            emission: emission.into(),
+                            vertex_colors,
+                        }
+                    }
+                    _ => return Err(S::Error::custom("textured materials can't be saved")),
+                }
+            }
+            Material::Custom(_) => return Err(S::Error::custom("custom materials can't be saved")),
+        };
+        description.serialize(serializer)
+    }
+}
+
+impl Serialize for Light {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let description = match self {
+            Light::Global(g) => LightDescription::Global {
+                direction: triple(&g.direction),
+                color: g.color.into(),
+                intensity: g.intensity,
+            },
+            Light::Spherical(s) => LightDescription::Spherical {
+                position: triple(&s.pos.coords),
+                color: s.color.into(),
+                intensity: s.intensity,
+            },
+            Light::Spot(s) => LightDescription::Spot {
+                position: triple(&s.pos.coords),
+                direction: triple(&s.direction),
+                color: s.color.into(),
+                intensity: s.intensity,
+                inner_angle: s.inner_angle.to_degrees(),
+                outer_angle: s.outer_angle.to_degrees(),
+            },
+        };
+        description.serialize(serializer)
+    }
+}
+
+impl Serialize for Geometry {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        describe(self)
+            .map_err(S::Error::custom)?
+            .serialize(serializer)
+    }
+}
+
+/// Describes an object.  Instances become the shape they wrap, with its transform and material
+/// folded into the description.
+fn describe(geometry: &Geometry) -> Result<ObjectDescription, &'static str> {
+    let p = |p: &Point3<f32>| triple(&p.coords);
+    let (shape, material) = match geometry {
+        Geometry::Sphere(s) => (
+            ShapeDescription::Sphere {
+                center: p(&s.center),
+                radius: s.radius,
+            },
+            s.material,
+        ),
+        Geometry::Plane(plane) => (
+            ShapeDescription::Plane {
+                point: p(&plane.vertex),
+                normal: triple(&plane.normal),
+            },
+            plane.material,
+        ),
+        Geometry::Cuboid(c) => (
+            ShapeDescription::Cuboid {
+                min: p(&c.min),
+                max: p(&c.max),
+            },
+            c.material,
+        ),
+        Geometry::Cylinder(c) => (
+            ShapeDescription::Cylinder {
+                base: p(&c.base),
+                radius: c.radius,
+                height: c.height,
+            },
+            c.material,
+        ),
+        Geometry::Cone(c) => (
+            ShapeDescription::Cone {
+                base: p(&c.base),
+                radius: c.radius,
+                height: c.height,
+            },
+            c.material,
+        ),
+        Geometry::Disk(d) => (
+            ShapeDescription::Disk {
+                center: p(&d.center),
+                normal: triple(&d.normal),
+                radius: d.radius,
+            },
+            d.material,
+        ),
+        Geometry::Quad(q) => (
+            ShapeDescription::Quad {
+                corner: p(&q.corner),
+                u: triple(&q.u),
+                v: triple(&q.v),
+            },
+            q.material,
+        ),
+        Geometry::Torus(t) => (
+            ShapeDescription::Torus {
+                center: p(&t.center),
+                major_radius: t.major_radius,
+                minor_radius: t.minor_radius,
+            },
+            t.material,
+        ),
+        Geometry::Heightfield(h) => (
+            ShapeDescription::Heightfield {
+                corner: p(&h.bounds.min),
+                size: triple(&(h.bounds.max - h.bounds.min)),
+                image: None,
+                noise: None,
+                heights: Some(
+                    h.map
+                        .heights
+                        .chunks(h.map.width)
+                        .map(<[f32]>::to_vec)
+                        .collect(),
+                ),
+            },
+            h.material,
+        ),
+        Geometry::Csg(csg) => (ShapeDescription::Csg(describe_csg(csg)), csg.material()),
+        Geometry::Mesh(mesh) => (
+            ShapeDescription::Mesh {
+                positions: mesh.positions.iter().map(p).collect(),
+                triangles: mesh.triangles.clone(),
+                normals: mesh
+                    .normals
+                    .as_ref()
+                    .map(|normals| normals.iter().map(triple).collect()),
+                uvs: mesh
+                    .uvs
+                    .as_ref()
+                    .map(|uvs| uvs.iter().map(|uv| [uv.x, uv.y]).collect()),
+                colors: mesh
+                    .colors
+                    .as_ref()
+                    .map(|colors| colors.iter().map(|c| [c.red, c.green, c.blue]).collect()),
+            },
+            mesh.material,
+        ),
+        Geometry::Instance(instance) => {
+            let mut object = describe(&instance.shape)?;
+            let inner = match &object.transform {
+                Some(transform) => transform.to_matrix("").map_err(|_| "invalid transform")?,
+                None => Matrix4::identity(),
+            };
+            object.transform = Some(TransformDescription::from_matrix(
+                &(instance.transform.matrix() * inner),
+            ));
+            if let Some(material) = instance.material {
+                // the instance's material wins over any the shape picks itself
+                object.material = material_name(material);
+                if let ShapeDescription::Csg(csg) = &mut object.shape {
+                    override_materials(csg, &object.material);
+                }
+            }
+            return Ok(object);
+        }
+        Geometry::Sdf(_) => return Err("signed distance fields can't be saved"),
+        Geometry::Custom(_) => return Err("custom shapes can't be saved"),
+    };
+    Ok(ObjectDescription {
+        material: material_name(material),
+        shape,
+        transform: None,
+    })
+}
+
+fn describe_csg(csg: &Csg) -> CsgDescription {
+    let operation = match csg.operation {
+        CsgOperation::Union => CsgOperationDescription::Union,
+        CsgOperation::Intersection => CsgOperationDescription::Intersection,
+        CsgOperation::Difference => CsgOperationDescription::Difference,
+    };
+    CsgDescription {
+        operation,
+        left: Box::new(describe_solid(&csg.left)),
+        right: Box::new(describe_solid(&csg.right)),
+    }
+}
+
+fn describe_solid(solid: &Solid) -> SolidDescription {
+    let p = |p: &Point3<f32>| triple(&p.coords);
+    match solid {
+        Solid::Sphere(s) => SolidDescription::Sphere {
+            center: p(&s.center),
+            radius: s.radius,
+            material: Some(material_name(s.material)),
+        },
+        Solid::Cuboid(c) => SolidDescription::Cuboid {
+            min: p(&c.min),
+            max: p(&c.max),
+            material: Some(material_name(c.material)),
+        },
+        Solid::Cylinder(c) => SolidDescription::Cylinder {
+            base: p(&c.base),
+            radius: c.radius,
+            height: c.height,
+            material: Some(material_name(c.material)),
+        },
+        Solid::Csg(csg) => SolidDescription::Csg(describe_csg(csg)),
+    }
+}
+
+/// Makes every solid use the same material.
+fn override_materials(csg: &mut CsgDescription, name: &str) {
+    for solid in [&mut csg.left, &mut csg.right] {
+        match solid.as_mut() {
+            SolidDescription::Sphere { material, .. }
+            | SolidDescription::Cuboid { material, .. }
+            | SolidDescription::Cylinder { material, .. } => *material = Some(name.to_string()),
+            SolidDescription::Csg(csg) => override_materials(csg, name),
+        }
+    }
+}
+
+impl Serialize for Scene {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let render = RenderDescription {
+            width: self.width,
+            height: self.height,
+            samples: self.samples,
+            depth: self.tracing_depth,
+            seed: self.seed,
+            background: self.background.into(),
+        };
+        let camera = CameraDescription {
+            fov: self.fov,
+            eye: triple(&self.camera.eye.coords),
+            target: triple(&self.camera.target.coords),
+            up: triple(&self.camera.up),
+        };
+        let materials = self
+            .materials
+            .iter()
+            .enumerate()
+            .map(|(i, material)| (material_name(MaterialId(i)), material.as_ref()))
+            .collect::<BTreeMap<_, _>>();
+
+        let mut scene = serializer.serialize_struct("Scene", 5)?;
+        scene.serialize_field("render", &render)?;
+        scene.serialize_field("camera", &camera)?;
+        scene.serialize_field("materials", &materials)?;
+        // TOML can't write empty arrays after tables, so leave them out
+        let lights = self.all_lights().collect::<Vec<_>>();
+        if lights.is_empty() {
+            scene.skip_field("lights")?;
+        } else {
+            scene.serialize_field("lights", &lights)?;
+        }
+        let objects = self.objects().collect::<Vec<_>>();
+        if objects.is_empty() {
+            scene.skip_field("objects")?;
+        } else {
+            scene.serialize_field("objects", &objects)?;
+        }
+        scene.end()
+    }
+}
+
+impl SceneDescription {
+    pub(crate) fn parse(text: &str) -> Result<SceneDescription, SceneError> {
+        let mut deserializer = toml::Deserializer::new(text);
+        Ok(serde_path_to_error::deserialize(&mut deserializer)?)
+    }
+
+    /// The files the scene refers to, relative to the scene file.
+    fn assets(&self) -> impl Iterator<Item = &Path> {
+        self.objects.iter().filter_map(|object| match &object.shape {
+            ShapeDescription::Heightfield {
+                image: Some(image), ..
+            } => Some(image.as_path()),
+            _ => None,
+        })
+    }
+
+    /// Creates the scene, loading any files it refers to from `directory`.
+    pub(crate) fn build(&self, directory: &Path) -> Result<Scene, SceneError> {
+        let render = &self.render;
+        if render.width == 0 || render.height == 0 {
+            return Err(invalid("render", "images need at least one pixel"));
+        }
+        let mut scene = Scene::new(
+            render.width,
+            render.height,
+            self.camera.fov,
+            render.samples,
+            (&render.background).into(),
+        );
+        scene.set_tracing_depth(render.depth);
+        scene.set_seed(render.seed);
+        scene.set_camera(Camera::look_at(
+            point(self.camera.eye),
+            point(self.camera.target),
+            vector(self.camera.up),
+        ));
+
+        let mut builder = Builder {
+            materials: BTreeMap::new(),
+            directory,
+        };
+        for (name, material) in &self.materials {
+            let id = match material {
+                MaterialDescription::Diffuse { color, albedo } => {
+                    scene.add_material(Diffuse::new(color.into(), *albedo))
+                }
+                MaterialDescription::Reflective { color, albedo } => {
+                    scene.add_material(Reflective::new(color.into(), *albedo))
+                }
+                MaterialDescription::Pbr {
+                    base_color,
+                    metallic,
+                    roughness,
+                    emission,
+                    vertex_colors,
+                } => {
+                    let base_color = Color::from(base_color);
+                    let base_color = if *vertex_colors {
+                        ColorSource::Vertex(base_color)
+                    } else {
+                        base_color.into()
+                    };
+                    scene.add_material(
+                        Pbr::new(base_color, *metallic, *roughness)
+                            .with_emission(Color::from(emission)),
+                    )
+                }
+            };
+            builder.materials.insert(name, id);
+        }
+
+        for (i, light) in self.lights.iter().enumerate() {
+            let path = format!("lights[{}]", i);
+            match *light {
+                LightDescription::Global {
+                    direction: d,
+                    ref color,
+                    intensity,
+                } => scene.add_light(GlobalLight::new(
+                    direction(&path, "direction", d)?,
+                    color.into(),
+                    intensity,
+                )),
+                LightDescription::Spherical {
+                    position,
+                    ref color,
+                    intensity,
+                } => scene.add_light(SphericalLight::new(
+                    point(position),
+                    color.into(),
+                    intensity,
+                )),
+                LightDescription::Spot {
+                    position,
+                    direction: d,
+                    ref color,
+                    intensity,
+                    inner_angle,
+                    outer_angle,
+                } => scene.add_light(SpotLight::new(
+                    point(position),
+                    direction(&path, "direction", d)?,
+                    color.into(),
+                    intensity,
+                    inner_angle.to_radians(),
+                    outer_angle.to_radians(),
+                )),
+            }
+        }
+
+        for (i, object) in self.objects.iter().enumerate() {
+            let path = format!("objects[{}]", i);
+            let material = builder.material(&format!("{}.material", path), &object.material)?;
+            let shape = builder.shape(&format!("{}.shape", path), &object.shape, material)?;
+            match &object.transform {
+                Some(transform) => {
+                    let matrix = transform.to_matrix(&format!("{}.transform", path))?;
+                    scene.add_geometry(Instance::new(shape, Affine3::from_matrix_unchecked(matrix)))
+                }
+                None => scene.add_geometry(shape),
+            }
+        }
+
+        Ok(scene)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const MINIMAL: &str = r#"
+        [render]
+        width = 4
+        height = 3
+        samples = 1
+
+        [materials.white]
+        type = "diffuse"
+        color = [1, 1, 1]
+        albedo = 1
+    "#;
+
+    fn error(text: &str) -> SceneError {
+        match Scene::from_toml(&format!("{}\n{}", MINIMAL, text)) {
+            Ok(_) => panic!("scene should have been rejected"),
+            Err(e) => e,
+        }
+    }
+
+    #[test]
+    fn test_sample_scenes() {
+        let directory = Path::new(env!("CARGO_MANIFEST_DIR")).join("scenes");
+        let basic = Scene::from_file(directory.join("basic.toml")).unwrap();
+        assert_eq!((basic.width, basic.height), (2560, 1440));
+        assert_eq!(basic.materials.len(), 5);
+        assert_eq!(basic.lights.len(), 2);
+        assert_eq!(basic.geometry.len(), 5);
+
+        let cornell = Scene::from_file(directory.join("cornell.toml")).unwrap();
+        assert_eq!(cornell.materials.len(), 9);
+        assert_eq!(cornell.geometry.len(), 9);
+    }
+
+    #[test]
+    fn test_source_files() {
+        let directory = std::env::temp_dir().join(format!("raytracer-{}", std::process::id()));
+        fs::create_dir_all(&directory).unwrap();
+        let path = directory.join("terrain.toml");
+        let text = format!(
+            "{}{}",
+            MINIMAL,
+            r#"
+            [[objects]]
+            material = "white"
+            shape = { type = "heightfield", corner = [0, 0, 0], size = [1, 1, 1], image = "hills.pgm" }
+            "#
+        );
+        fs::write(&path, text).unwrap();
+        let files = Scene::source_files(&path);
+        fs::remove_dir_all(&directory).unwrap();
+        assert_eq!(files.unwrap(), vec![path, directory.join("hills.pgm")]);
+    }
+
+    #[test]
+    fn test_objects() {
+        let scene = Scene::from_toml(&format!(
+            "{}{}",
+            MINIMAL,
+            r#"
+            [camera]
+            eye = [0, 0, -5]
+
+            [[objects]]
+            material = "white"
+            shape = { type = "sphere", center = [0, 0, 0], radius = 1 }
+            transform = { translate = [0, 0, 5], scale = [2, 2, 2] }
+
+            [[objects]]
+            material = "white"
+            [objects.shape]
+            type = "csg"
+            operation = "difference"
+            left = { type = "cuboid", min = [-1, -1, -1], max = [1, 1, 1] }
+            right = { type = "sphere", center = [0, 0, 0], radius = 1.2 }
+            "#
+        ))
+        .unwrap();
+        assert_eq!(scene.camera.eye, Point3::new(0.0, 0.0, -5.0));
+
+        let ray = Ray::new(Point3::new(0.0, 0.0, -5.0), Vector3::new(0.0, 0.0, 1.0));
+        let hit = scene.trace(&ray, 0).unwrap();
+        assert!((hit.surface.t - 8.0).abs() < 1e-4);
+        assert!(matches!(hit.elem, Geometry::Instance(_)));
+
+        let colors: BTreeMap<String, ColorDescription> =
+            toml::from_str("a = { srgb = [0.5, 0, 1] }\nb = [0.5, 0, 1]").unwrap();
+        let srgb = Color::from(&colors["a"]);
+        assert!((srgb.red - 0.214).abs() < 1e-3);
+        assert_eq!((srgb.green, srgb.blue), (0.0, 1.0));
+        assert_eq!(Color::from(&colors["b"]), Color::new(0.5, 0.0, 1.0));
+    }
+
+    #[test]
+    fn test_errors() {
+        let unknown_key = error(
+            r#"
+            [[objects]]
+            material = "white"
+            shape = { type = "sphere", center = [0, 0, 0], radius = 1, colour = "red" }
+            "#,
+        );
+        assert!(
+            matches!(&unknown_key, SceneError::Parse { path, .. } if path == "objects[0].shape")
+        );
+        assert!(unknown_key.to_string().contains("colour"));
+
+        let bad_value = error(
+            r#"
+            [camera]
+            fov = "wide"
+            "#,
+        );
+        assert!(matches!(bad_value, SceneError::Parse { path, .. } if path == "camera.fov"));
+
+        let missing_material = error(
+            r#"
+            [[objects]]
+            material = "black"
+            shape = { type = "sphere", center = [0, 0, 0], radius = 1 }
+            "#,
+        );
+        assert_eq!(
+            missing_material.to_string(),
+            "objects[0].material: unknown material `black`"
+        );
+
+        let missing_image = error(
+            r#"
+            [[objects]]
+            material = "white"
+            shape = { type = "heightfield", corner = [0, 0, 0], size = [1, 1, 1], image = "missing.png" }
+            "#,
+        );
+        assert!(
+            matches!(missing_image, SceneError::Asset { path, .. } if path == "objects[0].shape.image")
+        );
+
+        for (text, path) in &[
+            (
+                r#"shape = { type = "sphere", center = [0, 0, 0], radius = -2 }"#,
+                "objects[0].shape.radius",
+            ),
+            (
+                r#"shape = { type = "plane", point = [0, 0, 0], normal = [0, 0, 0] }"#,
+                "objects[0].shape.normal",
+            ),
+            (
+                r#"shape = { type = "cylinder", base = [0, 0, 0], radius = 1, height = 0 }"#,
+                "objects[0].shape.height",
+            ),
+            (
+                r#"shape = { type = "cone", base = [0, 0, 0], radius = 0, height = 1 }"#,
+                "objects[0].shape.radius",
+            ),
+            (
+                r#"shape = { type = "disk", center = [0, 0, 0], normal = [0, 0, 0], radius = 1 }"#,
+                "objects[0].shape.normal",
+            ),
+            (
+                r#"shape = { type = "quad", corner = [0, 0, 0], u = [1, 0, 0], v = [2, 0, 0] }"#,
+                "objects[0].shape.v",
+            ),
+            (
+                r#"shape = { type = "torus", center = [0, 0, 0], major_radius = 1, minor_radius = 2 }"#,
+                "objects[0].shape.minor_radius",
+            ),
+            (
+                r#"shape = { type = "csg", operation = "union", left = { type = "sphere", center = [0, 0, 0], radius = 0 }, right = { type = "sphere", center = [0, 0, 0], radius = 1 } }"#,
+                "objects[0].shape.left.radius",
+            ),
+        ] {
+            let bad_shape = error(&format!("[[objects]]\nmaterial = \"white\"\n{}", text));
+            assert!(
+                matches!(&bad_shape, SceneError::Invalid { path: p, .. } if p == path),
+                "{}",
+                bad_shape
+            );
+        }
+        for light in &[
+            r#"type = "global"
+            direction = [0, 0, 0]"#,
+            r#"type = "spot"
+            position = [0, 0, 0]
+            direction = [0, 0, 0]
+            inner_angle = 10
+            outer_angle = 20"#,
+        ] {
+            let bad_light = error(&format!(
+                "[[lights]]\ncolor = [1, 1, 1]\nintensity = 1\n{}",
+                light
+            ));
+            assert!(
+                matches!(&bad_light, SceneError::Invalid { path, .. } if path == "lights[0].direction"),
+                "{}",
+                bad_light
+            );
+        }
+
+        let too_detailed = error(
+            r#"
+            [[objects]]
+            material = "white"
+            shape = { type = "heightfield", corner = [0, 0, 0], size = [1, 1, 1], noise = { resolution = [4, 4], octaves = 40 } }
+            "#,
+        );
+        assert!(
+            matches!(too_detailed, SceneError::Invalid { path, .. } if path == "objects[0].shape.noise.octaves")
+        );
+        let small_noise = format!(
+            "{}{}",
+            MINIMAL,
+            r#"
+            [[objects]]
+            material = "white"
+            shape = { type = "heightfield", corner = [0, 0, 0], size = [1, 1, 1], noise = { resolution = [4, 4] } }
+            "#
+        );
+        assert!(Scene::from_toml(&small_noise).is_ok());
+
+        for transform in &[
+            "{ scale = [0, 1, 1] }",
+            "{ matrix = [[1, 0, 0, 0], [0, 1, 0, 0], [0, 0, 0, 0]] }",
+        ] {
+            let flattened = error(&format!(
+                r#"
+                [[objects]]
+                material = "white"
+                shape = {{ type = "sphere", center = [0, 0, 0], radius = 1 }}
+                transform = {}
+                "#,
+                transform
+            ));
+            assert!(
+                matches!(&flattened, SceneError::Invalid { path, .. } if path == "objects[0].transform"),
+                "{}",
+                flattened
+            );
+        }
+    }
+
+    #[test]
+    fn test_round_trip() {
+        let mut scene = Scene::new(32, 18, 70.0, 4, Color::new(0.1, 0.2, 0.3));
+        scene.set_seed(42);
+        scene.set_tracing_depth(4);
+        scene.set_camera(Camera::look_at(
+            Point3::new(0.0, 2.0, -3.0),
+            Point3::new(0.0, 0.0, 6.0),
+            Vector3::new(0.0, 1.0, 0.0),
+        ));
+        let red = scene.add_material(Diffuse::new(Color::new(0.8, 0.1, 0.1), 0.6));
+        let blue = scene.add_material(Diffuse::new(Color::new(0.1, 0.1, 0.8), 0.6));
+        let mirror = scene.add_material(Reflective::new(Color::new(0.3, 0.3, 0.3), 0.3));
+        scene.add_light(GlobalLight::new(
+            Vector3::new(1.0, -2.0, 1.0),
+            Color::new(1.0, 0.9, 0.8),
+            2.0,
+        ));
+        scene.add_light(SphericalLight::new(
+            Point3::new(0.0, 4.0, 3.0),
+            Color::new(1.0, 1.0, 1.0),
+            80.0,
+        ));
+
+        let floor = Point3::new(0.0, -1.0, 0.0);
+        scene.add_geometry(Plane::new(floor, Vector3::new(0.0, 1.0, 0.0), blue));
+        scene.add_geometry(Sphere::new(Point3::new(-2.0, 0.0, 6.0), 1.0, mirror));
+        scene.add_geometry(Cylinder::new(Point3::new(2.0, -1.0, 7.0), 0.5, 2.0, red));
+        scene.add_geometry(Cone::new(Point3::new(0.0, -1.0, 9.0), 1.0, 2.0, red));
+        scene.add_geometry(Disk::new(
+            Point3::new(-3.0, 2.0, 9.0),
+            Vector3::new(1.0, 0.0, -1.0),
+            1.0,
+            blue,
+        ));
+        scene.add_geometry(Quad::new(
+            Point3::new(-5.0, -1.0, 12.0),
+            Vector3::new(10.0, 0.0, 0.0),
+            Vector3::new(0.0, 6.0, 0.0),
+            red,
+        ));
+        scene.add_geometry(Torus::new(Point3::new(3.0, 1.5, 8.0), 0.8, 0.2, mirror));
+        scene.add_geometry(Heightfield::new(
+            HeightMap::noise(9, 9, 1, 2),
+            Point3::new(-4.0, -1.0, 3.0),
+            Vector3::new(3.0, 0.5, 3.0),
+            blue,
+        ));
+        let carved = Csg::difference(
+            Cuboid::new(
+                Point3::new(-0.5, -0.5, -0.5),
+                Point3::new(0.5, 0.5, 0.5),
+                red,
+            ),
+            Sphere::new(Point3::new(0.0, 0.0, 0.0), 0.65, blue),
+        );
+        let tilted = Affine3::from_matrix_unchecked(
+            Matrix4::new_translation(&Vector3::new(0.5, 0.5, 5.0))
+                * Rotation3::from_euler_angles(0.3, 0.6, 0.0).to_homogeneous(),
+        );
+        scene.add_geometry(Instance::new(carved, tilted).with_material(mirror));
+        let mut node =
+            Node::new("pedestal").with_transform(convert(Translation3::new(1.5, -0.5, 4.0)));
+        node.add_geometry(Cuboid::new(
+            Point3::new(-0.3, -0.5, -0.3),
+            Point3::new(0.3, 0.5, 0.3),
+            blue,
+        ));
+        scene.add_node(node);
+        let painted = scene.add_material(Pbr::new(
+            ColorSource::Vertex(Color::new(1.0, 1.0, 1.0)),
+            0.0,
+            0.6,
+        ));
+        let triangle = vec![
+            Point3::new(-1.5, -1.0, 4.0),
+            Point3::new(-0.5, -1.0, 4.0),
+            Point3::new(-1.0, 0.0, 4.0),
+        ];
+        let colors = vec![
+            Color::new(1.0, 0.0, 0.0),
+            Color::new(0.0, 1.0, 0.0),
+            Color::new(0.0, 0.0, 1.0),
+        ];
+        scene.add_geometry(Mesh::new(triangle, vec![[0, 1, 2]], painted).with_colors(colors));
+
+        let text = scene.to_toml().unwrap();
+        let loaded = Scene::from_toml(&text).unwrap();
+        assert_eq!(loaded.to_toml().unwrap(), text);
+
+        let (original, reloaded) = (scene.render_image(), loaded.render_image());
+        assert_eq!(original.pixels(), reloaded.pixels());
+    }
+
+    #[test]
+    fn test_unsaveable() {
+        let mut scene = Scene::from_toml(MINIMAL).unwrap();
+        let white = MaterialId(0);
+        scene.add_geometry(Sphere::new(Point3::new(0.0, 0.0, 0.0), 1.0, white));
+        scene.add_geometry(SdfShape::new(
+            Sdf::sphere(1.0),
+            Bounds::new(Point3::new(-1.0, -1.0, -1.0), Point3::new(1.0, 1.0, 1.0)),
+            white,
+        ));
+        let error = scene.to_toml().unwrap_err();
+        assert!(matches!(error, SceneError::Save { path, .. } if path == "objects[1]"));
+    }
+}
@@ -26,35 +26,29 @@ fn main() -> io::Result<()> {
         15.0,
     ));
 
-    scene.add_geometry(Sphere::new(
-        Point3::new(4.0, depth + 1.0, 5.0),
-        1.0,
-        Diffuse::new(Color::new(0.5, 0.0, 0.2).from_gamma(), 0.4).into(),
-    ));
+    let maroon = scene.add_material(Diffuse::new(Color::new(0.5, 0.0, 0.2).from_gamma(), 0.4));
+    let magenta = scene.add_material(Diffuse::new(Color::new(1.0, 0.0, 1.0).from_gamma(), 0.3));
+    let green = scene.add_material(Diffuse::new(Color::new(0.0, 1.0, 0.0).from_gamma(), 0.3));
+    let floor = scene.add_material(Diffuse::new(Color::new(0.4, 0.1, 0.3).from_gamma(), 0.4));
+    let mirror = scene.add_material(Reflective::new(Color::new(0.2, 0.2, 0.3).from_gamma(), 0.4));
+
+    scene.add_geometry(Sphere::new(Point3::new(4.0, depth + 1.0, 5.0), 1.0, maroon));
 
     scene.add_geometry(Sphere::new(
         Point3::new(1.0, depth + 0.8, 4.0),
         0.8,
-        Diffuse::new(Color::new(1.0, 0.0, 1.0).from_gamma(), 0.3).into(),
+        magenta,
     ));
 
-    scene.add_geometry(Sphere::new(
-        Point3::new(-3.0, depth + 2.5, 6.0),
-        2.5,
-        Diffuse::new(Color::new(0.0, 1.0, 0.0).from_gamma(), 0.3).into(),
-    ));
+    scene.add_geometry(Sphere::new(Point3::new(-3.0, depth + 2.5, 6.0), 2.5, green));
 
     scene.add_geometry(Plane::new(
         Point3::new(0.0, depth, 10.0),
         Vector3::new(0.0, 1.0, 0.0),
-        Diffuse::new(Color::new(0.4, 0.1, 0.3).from_gamma(), 0.4).into(),
+        floor,
     ));
 
-    scene.add_geometry(Sphere::new(
-        Point3::new(1.0, depth + 1.5, 6.0),
-        1.5,
-        Reflective::new(Color::new(0.2, 0.2, 0.3).from_gamma(), 0.4).into(),
-    ));
+    scene.add_geometry(Sphere::new(Point3::new(1.0, depth + 1.5, 6.0), 1.5, mirror));
 
     let mut file = env::args()
         .nth(1)
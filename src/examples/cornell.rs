@@ -21,58 +21,82 @@ fn main() -> io::Result<()> {
         200.0,
     ));
 
+    let maroon = scene.add_material(Diffuse::new(Color::new(0.5, 0.0, 0.2).from_gamma(), 0.18));
+    let magenta = scene.add_material(Diffuse::new(Color::new(1.0, 0.0, 1.0).from_gamma(), 0.18));
+    let green = scene.add_material(Diffuse::new(Color::new(0.0, 1.0, 0.0).from_gamma(), 0.18));
+    let floor = scene.add_material(Diffuse::new(Color::new(0.5, 0.1, 0.3).from_gamma(), 0.18));
+    let mirror = scene.add_material(Reflective::new(
+        Color::new(0.1, 0.0, 0.1).from_gamma(),
+        0.18,
+    ));
+    let back_wall = scene.add_material(Diffuse::new(Color::new(1.0, 1.0, 0.2).from_gamma(), 0.18));
+    let left_wall = scene.add_material(Diffuse::new(Color::new(1.0, 0.0, 0.0).from_gamma(), 0.18));
+    let right_wall = scene.add_material(Diffuse::new(Color::new(0.0, 0.0, 1.0).from_gamma(), 0.18));
+    let ceiling = scene.add_material(Diffuse::new(Color::new(0.0, 1.0, 1.0).from_gamma(), 0.18));
+
     scene.add_geometry(Sphere::new(
         Point3::new(4.0, -depth + 1.0, 5.0 + zdepth),
         1.0,
-        Diffuse::new(Color::new(0.5, 0.0, 0.2).from_gamma(), 0.18).into(),
+        maroon,
     ));
 
     scene.add_geometry(Sphere::new(
         Point3::new(1.0, -depth + 0.8, 4.0 + zdepth),
         0.8,
-        Diffuse::new(Color::new(1.0, 0.0, 1.0).from_gamma(), 0.18).into(),
+        magenta,
     ));
 
     scene.add_geometry(Sphere::new(
         Point3::new(-3.0, -depth + 2.5, 6.0 + zdepth),
         2.5,
-        Diffuse::new(Color::new(0.0, 1.0, 0.0).from_gamma(), 0.18).into(),
-    ));
-
-    scene.add_geometry(Plane::new(
-        Point3::new(0.0, -depth, 10.0 + zdepth),
-        Vector3::new(0.0, 1.0, 0.0),
-        Diffuse::new(Color::new(0.5, 0.1, 0.3).from_gamma(), 0.18).into(),
+        green,
     ));
 
     scene.add_geometry(Sphere::new(
         Point3::new(1.0, -depth + 1.5, 6.0 + zdepth),
         1.5,
-        Reflective::new(Color::new(0.1, 0.0, 0.1).from_gamma(), 0.18).into(),
+        mirror,
+    ));
+
+    // the room is a closed box, except for the wall behind the camera
+    let (front, back, width) = (-5.0, 15.0 + zdepth, 10.0);
+    let across = Vector3::new(2.0 * width, 0.0, 0.0);
+    let up = Vector3::new(0.0, 2.0 * depth, 0.0);
+    let along = Vector3::new(0.0, 0.0, back - front);
+
+    scene.add_geometry(Quad::new(
+        Point3::new(-width, -depth, front),
+        across,
+        along,
+        floor,
     ));
 
-    scene.add_geometry(Plane::new(
-        Point3::new(0.0, 0.0, 15.0 + zdepth),
-        Vector3::new(0.0, 0.0, -1.0),
-        Diffuse::new(Color::new(1.0, 1.0, 0.2).from_gamma(), 0.18).into(),
+    scene.add_geometry(Quad::new(
+        Point3::new(-width, -depth, back),
+        across,
+        up,
+        back_wall,
     ));
 
-    scene.add_geometry(Plane::new(
-        Point3::new(-10.0, 0.0, 0.0),
-        Vector3::new(1.0, 0.0, 0.0),
-        Diffuse::new(Color::new(1.0, 0.0, 0.0).from_gamma(), 0.18).into(),
+    scene.add_geometry(Quad::new(
+        Point3::new(-width, -depth, front),
+        along,
+        up,
+        left_wall,
     ));
 
-    scene.add_geometry(Plane::new(
-        Point3::new(10.0, 0.0, 0.0),
-        Vector3::new(-1.0, 0.0, 0.0),
-        Diffuse::new(Color::new(0.0, 0.0, 1.0).from_gamma(), 0.18).into(),
+    scene.add_geometry(Quad::new(
+        Point3::new(width, -depth, front),
+        along,
+        up,
+        right_wall,
     ));
 
-    scene.add_geometry(Plane::new(
-        Point3::new(0.0, depth, 10.0 + zdepth),
-        Vector3::new(0.0, -1.0, 0.0),
-        Diffuse::new(Color::new(0.0, 1.0, 1.0).from_gamma(), 0.18).into(),
+    scene.add_geometry(Quad::new(
+        Point3::new(-width, depth, front),
+        across,
+        along,
+        ceiling,
     ));
 
     let mut file = env::args()
@@ -0,0 +1,428 @@
+//! Rendered images, and the file formats they can be saved in.
+
+use exr::math::Vec2;
+use exr::meta::attribute::Chromaticities;
+use exr::prelude::{
+    f16, AnyChannel, AnyChannels, Blocks, Compression, Encoding, FlatSamples, Image, Layer,
+    LayerAttributes, LineOrder, SmallVec, WritableImage,
+};
+
+use std::io::{self, Seek, Write};
+
+use crate::prelude::*;
+
+/// A rectangle of pixels within an image, such as the part of the frame to render.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Region {
+    pub x: u32,
+    pub y: u32,
+    pub width: u32,
+    pub height: u32,
+}
+
+impl Region {
+    pub fn new(x: u32, y: u32, width: u32, height: u32) -> Region {
+        Region {
+            x,
+            y,
+            width,
+            height,
+        }
+    }
+
+    /// Whether the region lies entirely within an image of the given size.
+    pub fn fits(&self, width: u32, height: u32) -> bool {
+        u64::from(self.x) + u64::from(self.width) <= u64::from(width)
+            && u64::from(self.y) + u64::from(self.height) <= u64::from(height)
+    }
+
+    pub fn pixel_count(&self) -> u64 {
+        u64::from(self.width) * u64::from(self.height)
+    }
+}
+
+/// How precisely OpenEXR files store their samples.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ExrPrecision {
+    /// 16 bit floats, which are plenty for colors and take half the space
+    Half,
+    /// 32 bit floats
+    Float,
+}
+
+/// How OpenEXR files are compressed.  Every option is lossless.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ExrCompression {
+    None,
+    /// zlib, sixteen scanlines at a time
+    Zip,
+    /// A wavelet transform, which suits noisy images best
+    Piz,
+}
+
+/// A group of channels to write to an OpenEXR file, taken from one image.
+#[derive(Debug, Clone)]
+pub struct ExrLayer<'a> {
+    image: &'a Framebuffer,
+    /// Prefixed to the channel names, with a dot, unless it's `None`
+    name: Option<String>,
+    channels: Vec<String>,
+    /// Whether to always store 32 bit floats, whatever precision the rest of the file uses
+    exact: bool,
+}
+
+impl<'a> ExrLayer<'a> {
+    /// Writes the image as the channels `R`, `G` and `B`, prefixed with `name` if there is one.
+    pub fn rgb(name: Option<&str>, image: &'a Framebuffer) -> ExrLayer<'a> {
+        ExrLayer::new(name, image, &["R", "G", "B"])
+    }
+
+    /// Writes the image as the given channels, which are filled with the image's red, green and
+    /// blue in turn.  A single channel holds just the red values, for images of one quantity
+    /// like depth.
+    ///
+    /// # Panics
+    /// Panics unless there are one to three channels.
+    pub fn new(name: Option<&str>, image: &'a Framebuffer, channels: &[&str]) -> ExrLayer<'a> {
+        assert!(
+            (1..=3).contains(&channels.len()),
+            "layers have one to three channels"
+        );
+        ExrLayer {
+            image,
+            name: name.map(str::to_string),
+            channels: channels.iter().map(|c| c.to_string()).collect(),
+            exact: false,
+        }
+    }
+
+    /// Stores the layer as 32 bit floats even in a file of halves, for values like ids that need
+    /// every bit.
+    pub fn exact(self) -> ExrLayer<'a> {
+        ExrLayer {
+            exact: true,
+            ..self
+        }
+    }
+}
+
+/// Writes several images into one scanline OpenEXR file, each as its own named channels.  The
+/// file's chromaticities are those of the first image's color space.
+///
+/// # Panics
+/// Panics if the images aren't all the same size, or two channels have the same name.
+pub fn write_exr<W: Write + Seek>(
+    writer: W,
+    layers: &[ExrLayer],
+    precision: ExrPrecision,
+    compression: ExrCompression,
+) -> io::Result<()> {
+    let (width, height) = layers
+        .first()
+        .map_or((0, 0), |layer| (layer.image.width, layer.image.height));
+    let mut channels = SmallVec::new();
+    for layer in layers {
+        assert!(
+            (layer.image.width, layer.image.height) == (width, height),
+            "layers are different sizes"
+        );
+        for (index, channel) in layer.channels.iter().enumerate() {
+            let name = match &layer.name {
+                Some(prefix) => format!("{}.{}", prefix, channel),
+                None => channel.clone(),
+            };
+            let values = layer.image.pixels.iter().map(|color| match index {
+                0 => color.red,
+                1 => color.green,
+                _ => color.blue,
+            });
+            let samples = match (precision, layer.exact) {
+                (ExrPrecision::Half, false) => {
+                    FlatSamples::F16(values.map(f16::from_f32).collect())
+                }
+                _ => FlatSamples::F32(values.collect()),
+            };
+            channels.push(AnyChannel::new(name.as_str(), samples));
+        }
+    }
+    let channels = AnyChannels::sort(channels);
+    let names = channels.list.iter().map(|c| &c.name).collect::<Vec<_>>();
+    assert!(
+        names.windows(2).all(|pair| pair[0] != pair[1]),
+        "two channels have the same name"
+    );
+
+    let encoding = Encoding {
+        compression: match compression {
+            ExrCompression::None => Compression::Uncompressed,
+            ExrCompression::Zip => Compression::ZIP16,
+            ExrCompression::Piz => Compression::PIZ,
+        },
+        blocks: Blocks::ScanLines,
+        line_order: LineOrder::Increasing,
+    };
+    let size = (width as usize, height as usize);
+    let layer = Layer::new(size, LayerAttributes::default(), encoding, channels);
+    let mut image = Image::from_layer(layer);
+    if let Some(layer) = layers.first() {
+        let [red, green, blue, white] = layer.image.color_space.chromaticities();
+        let point = |[x, y]: [f64; 2]| Vec2(x as f32, y as f32);
+        image.attributes.chromaticities = Some(Chromaticities {
+            red: point(red),
+            green: point(green),
+            blue: point(blue),
+            white: point(white),
+        });
+    }
+    image.write().to_buffered(writer).map_err(io::Error::other)
+}
+
+/// An image of linear colors, as produced by rendering.  Images start out in linear sRGB, the
+/// space the renderer works in.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Framebuffer {
+    pub(crate) width: u32,
+    pub(crate) height: u32,
+    pub(crate) pixels: Vec<Color>,
+    pub(crate) color_space: ColorSpace,
+}
+
+impl Framebuffer {
+    /// Creates a black image.
+    pub fn new(width: u32, height: u32) -> Framebuffer {
+        Framebuffer {
+            width,
+            height,
+            pixels: vec![Color::default(); width as usize * height as usize],
+            color_space: ColorSpace::default(),
+        }
+    }
+
+    /// Creates an image from its pixels, stored one row at a time from the top.
+    pub fn from_pixels(width: u32, height: u32, pixels: Vec<Color>) -> Framebuffer {
+        assert_eq!(
+            pixels.len(),
+            width as usize * height as usize,
+            "wrong number of pixels"
+        );
+        Framebuffer {
+            width,
+            height,
+            pixels,
+            color_space: ColorSpace::default(),
+        }
+    }
+
+    /// Says which color space the pixels are in, without changing them.  Use `converted` to
+    /// change the space the colors are in.
+    pub fn with_color_space(self, color_space: ColorSpace) -> Framebuffer {
+        Framebuffer {
+            color_space,
+            ..self
+        }
+    }
+
+    pub fn width(&self) -> u32 {
+        self.width
+    }
+
+    pub fn height(&self) -> u32 {
+        self.height
+    }
+
+    pub fn color_space(&self) -> ColorSpace {
+        self.color_space
+    }
+
+    /// The pixels, one row at a time from the top.
+    pub fn pixels(&self) -> &[Color] {
+        &self.pixels
+    }
+
+    pub fn pixel(&self, x: u32, y: u32) -> Color {
+        self.pixels[self.index(x, y)]
+    }
+
+    pub fn set_pixel(&mut self, x: u32, y: u32, color: Color) {
+        let index = self.index(x, y);
+        self.pixels[index] = color;
+    }
+
+    fn index(&self, x: u32, y: u32) -> usize {
+        assert!(x < self.width && y < self.height, "pixel out of bounds");
+        y as usize * self.width as usize + x as usize
+    }
+
+    /// The pixels encoded with the sRGB curve as 8 bit RGB, ready to be displayed.  Colors
+    /// outside `[0, 1]` are clipped; use `tone_mapped` first to keep highlights.
+    fn to_rgb8(&self) -> Vec<u8> {
+        self.pixels
+            .iter()
+            .flat_map(|color| {
+                let (red, green, blue) = color.clamp().to_srgb().to_rgb();
+                vec![red, green, blue]
+            })
+            .collect()
+    }
+
+    /// Writes the image as a plain text PPM file.  PPM files can't say what color space they're
+    /// in and are taken to be sRGB, so images in other spaces are converted to it first.
+    pub fn write_ppm<W: Write>(&self, writer: &mut W) -> io::Result<()> {
+        writeln!(writer, "P3")?;
+        writeln!(writer, "{} {}", self.width, self.height)?;
+        writeln!(writer, "255")?;
+        let data = self.converted(ColorSpace::LinearSrgb).to_rgb8();
+        for pixel in data.chunks_exact(3) {
+            writeln!(writer, "{} {} {}", pixel[0], pixel[1], pixel[2])?;
+        }
+        Ok(())
+    }
+
+    /// Writes the image as an 8 bit PNG file, encoded with the sRGB curve.  sRGB images are
+    /// marked with an `sRGB` chunk, and both sRGB and Display P3 images with a `cICP` chunk
+    /// naming their primaries.  Images in other spaces are converted to sRGB first.
+    pub fn write_png<W: Write>(&self, writer: W) -> io::Result<()> {
+        let (image, primaries) = match self.color_space.cicp_primaries() {
+            Some(primaries) => (None, primaries),
+            None => (Some(self.converted(ColorSpace::LinearSrgb)), 1),
+        };
+        let image = image.as_ref().unwrap_or(self);
+
+        let mut encoder = png::Encoder::new(writer, self.width, self.height);
+        encoder.set_color(png::ColorType::Rgb);
+        encoder.set_depth(png::BitDepth::Eight);
+        if image.color_space == ColorSpace::LinearSrgb {
+            encoder.set_source_srgb(png::SrgbRenderingIntent::Perceptual);
+        }
+        let mut writer = encoder.write_header().map_err(io::Error::other)?;
+        // the sRGB transfer curve, with full range RGB samples
+        let cicp = [primaries, 13, 0, 1];
+        writer
+            .write_chunk(png::chunk::cICP, &cicp)
+            .map_err(io::Error::other)?;
+        writer
+            .write_image_data(&image.to_rgb8())
+            .map_err(io::Error::other)?;
+        writer.finish().map_err(io::Error::other)
+    }
+
+    /// Writes the image's linear colors, unclipped, as an OpenEXR file.
+    pub fn write_exr<W: Write + Seek>(
+        &self,
+        writer: W,
+        precision: ExrPrecision,
+        compression: ExrCompression,
+    ) -> io::Result<()> {
+        write_exr(writer, &[ExrLayer::rgb(None, self)], precision, compression)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Cursor;
+
+    #[test]
+    fn test_write() {
+        let mut image = Framebuffer::new(2, 1);
+        image.set_pixel(1, 0, Color::new(1.0, 0.0, 1.0));
+
+        let mut ppm = Vec::new();
+        image.write_ppm(&mut ppm).unwrap();
+        assert_eq!(
+            String::from_utf8(ppm).unwrap(),
+            "P3\n2 1\n255\n0 0 0\n255 0 255\n"
+        );
+
+        let mut png = Vec::new();
+        image.write_png(&mut png).unwrap();
+        let mut reader = png::Decoder::new(Cursor::new(png)).read_info().unwrap();
+        assert!(reader.info().srgb.is_some());
+        let cicp = reader.info().coding_independent_code_points.unwrap();
+        assert_eq!((cicp.color_primaries, cicp.transfer_function), (1, 13));
+        let mut data = vec![0; reader.output_buffer_size().unwrap()];
+        let info = reader.next_frame(&mut data).unwrap();
+        assert_eq!((info.width, info.height), (2, 1));
+        assert_eq!(&data[..info.buffer_size()], &[0, 0, 0, 255, 0, 255]);
+    }
+
+    #[test]
+    fn test_write_color_space() {
+        let image = Framebuffer::from_pixels(1, 1, vec![Color::new(0.5, 0.2, 0.1)]);
+        let p3 = image.converted(ColorSpace::DisplayP3);
+        assert_eq!(p3.color_space(), ColorSpace::DisplayP3);
+
+        let mut png = Vec::new();
+        p3.write_png(&mut png).unwrap();
+        let reader = png::Decoder::new(Cursor::new(png)).read_info().unwrap();
+        assert!(reader.info().srgb.is_none());
+        let cicp = reader.info().coding_independent_code_points.unwrap();
+        assert_eq!(cicp.color_primaries, 12);
+
+        // formats without a way to tag wide gamuts get sRGB
+        let (mut ppm, mut expected) = (Vec::new(), Vec::new());
+        let aces = image.converted(ColorSpace::AcesCg);
+        aces.write_ppm(&mut ppm).unwrap();
+        image.write_ppm(&mut expected).unwrap();
+        assert_eq!(ppm, expected);
+    }
+
+    /// Reads every channel of an OpenEXR file, by name.
+    fn read_exr(data: Vec<u8>) -> Vec<(String, Vec<f32>)> {
+        use exr::prelude::{ReadChannels, ReadLayers};
+        let image = exr::prelude::read()
+            .no_deep_data()
+            .largest_resolution_level()
+            .all_channels()
+            .first_valid_layer()
+            .all_attributes()
+            .from_buffered(Cursor::new(data))
+            .unwrap();
+        image
+            .layer_data
+            .channel_data
+            .list
+            .iter()
+            .map(|c| (c.name.to_string(), c.sample_data.values_as_f32().collect()))
+            .collect()
+    }
+
+    #[test]
+    fn test_write_exr() {
+        let mut image = Framebuffer::new(2, 1);
+        image.set_pixel(1, 0, Color::new(4.5, 0.25, -1.0));
+        let mut depth = Framebuffer::new(2, 1);
+        depth.set_pixel(0, 0, Color::new(16777215.0, 0.0, 0.0));
+
+        for &compression in &[
+            ExrCompression::None,
+            ExrCompression::Zip,
+            ExrCompression::Piz,
+        ] {
+            let mut data = Cursor::new(Vec::new());
+            let layers = [
+                ExrLayer::rgb(None, &image),
+                ExrLayer::new(Some("depth"), &depth, &["Z"]).exact(),
+            ];
+            write_exr(&mut data, &layers, ExrPrecision::Half, compression).unwrap();
+            let channels = read_exr(data.into_inner());
+            assert_eq!(
+                channels,
+                vec![
+                    ("B".to_string(), vec![0.0, -1.0]),
+                    ("G".to_string(), vec![0.0, 0.25]),
+                    ("R".to_string(), vec![0.0, 4.5]),
+                    ("depth.Z".to_string(), vec![16777215.0, 0.0]),
+                ]
+            );
+        }
+    }
+
+    #[test]
+    fn test_region() {
+        let region = Region::new(2, 1, 3, 2);
+        assert!(region.fits(5, 3));
+        assert!(!region.fits(4, 3));
+        assert_eq!(region.pixel_count(), 6);
+    }
+}
@@ -0,0 +1,137 @@
+use crate::prelude::*;
+use nalgebra::*;
+
+/// An axis-aligned bounding box.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Bounds {
+    pub min: Point3<f32>,
+    pub max: Point3<f32>,
+}
+
+impl Bounds {
+    /// Creates the smallest box containing both corners.
+    pub fn new(a: Point3<f32>, b: Point3<f32>) -> Bounds {
+        Bounds {
+            min: Point3::new(a.x.min(b.x), a.y.min(b.y), a.z.min(b.z)),
+            max: Point3::new(a.x.max(b.x), a.y.max(b.y), a.z.max(b.z)),
+        }
+    }
+
+    /// A box that contains all of space, for unbounded shapes like planes.
+    pub fn infinite() -> Bounds {
+        Bounds {
+            min: Point3::new(f32::NEG_INFINITY, f32::NEG_INFINITY, f32::NEG_INFINITY),
+            max: Point3::new(f32::INFINITY, f32::INFINITY, f32::INFINITY),
+        }
+    }
+
+    /// Creates the smallest box containing every point.
+    pub fn from_points<'a, I>(points: I) -> Bounds
+    where
+        I: IntoIterator<Item = &'a Point3<f32>>,
+    {
+        let mut points = points.into_iter();
+        let first = points.next().expect("bounds need at least one point");
+        points.fold(Bounds::new(*first, *first), |b, p| b.include(p))
+    }
+
+    /// Grows the box to contain `point`.
+    pub fn include(&self, point: &Point3<f32>) -> Bounds {
+        self.union(&Bounds::new(*point, *point))
+    }
+
+    /// The smallest box containing both boxes.
+    pub fn union(&self, other: &Bounds) -> Bounds {
+        Bounds {
+            min: Point3::new(
+                self.min.x.min(other.min.x),
+                self.min.y.min(other.min.y),
+                self.min.z.min(other.min.z),
+            ),
+            max: Point3::new(
+                self.max.x.max(other.max.x),
+                self.max.y.max(other.max.y),
+                self.max.z.max(other.max.z),
+            ),
+        }
+    }
+
+    /// The region covered by both boxes.  Empty overlaps have `min` greater than `max`.
+    pub fn overlap(&self, other: &Bounds) -> Bounds {
+        Bounds {
+            min: Point3::new(
+                self.min.x.max(other.min.x),
+                self.min.y.max(other.min.y),
+                self.min.z.max(other.min.z),
+            ),
+            max: Point3::new(
+                self.max.x.min(other.max.x),
+                self.max.y.min(other.max.y),
+                self.max.z.min(other.max.z),
+            ),
+        }
+    }
+
+    pub fn is_finite(&self) -> bool {
+        self.min
+            .coords
+            .iter()
+            .chain(self.max.coords.iter())
+            .all(|c| c.is_finite())
+    }
+
+    pub fn center(&self) -> Point3<f32> {
+        nalgebra::center(&self.min, &self.max)
+    }
+
+    /// Finds the range of distances along the ray that lie inside the box, clipped to the ray's
+    /// interval.  Uses the slab method.
+    pub fn intersect(&self, ray: &Ray) -> Option<(f32, f32)> {
+        let mut t0 = ray.tmin;
+        let mut t1 = ray.tmax;
+        for axis in 0..3 {
+            let inv = 1.0 / ray.direction[axis];
+            let mut near = (self.min[axis] - ray.source[axis]) * inv;
+            let mut far = (self.max[axis] - ray.source[axis]) * inv;
+            if near > far {
+                std::mem::swap(&mut near, &mut far);
+            }
+            // a ray parallel to a slab and starting on its boundary produces NaN; `max`/`min`
+            // ignore it, keeping the other bound
+            t0 = t0.max(near);
+            t1 = t1.min(far);
+            if t0 > t1 {
+                return None;
+            }
+        }
+        Some((t0, t1))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_ray_hits_box() {
+        let bounds = Bounds::new(Point3::new(-1.0, -1.0, 4.0), Point3::new(1.0, 1.0, 6.0));
+        let ray = Ray::new(Point3::new(0.0, 0.0, 0.0), Vector3::new(0.0, 0.0, 1.0));
+        assert_eq!(bounds.intersect(&ray), Some((4.0, 6.0)));
+    }
+
+    #[test]
+    fn test_ray_misses_box() {
+        let bounds = Bounds::new(Point3::new(-1.0, -1.0, 4.0), Point3::new(1.0, 1.0, 6.0));
+        let ray = Ray::new(Point3::new(0.0, 2.0, 0.0), Vector3::new(0.0, 0.0, 1.0));
+        assert!(bounds.intersect(&ray).is_none());
+        let ray = Ray::new(Point3::new(0.0, 0.0, 0.0), Vector3::new(0.0, 0.0, 1.0));
+        assert!(bounds.intersect(&ray.with_interval(0.0, 3.0)).is_none());
+    }
+
+    #[test]
+    fn test_infinite() {
+        let ray = Ray::new(Point3::new(0.0, 0.0, 0.0), Vector3::new(1.0, 2.0, 3.0));
+        assert!(Bounds::infinite().intersect(&ray).is_some());
+        assert!(!Bounds::infinite().is_finite());
+    }
+}
@@ -0,0 +1,188 @@
+use crate::prelude::*;
+
+/// Primitives per leaf, below which nodes aren't split any further
+const LEAF_SIZE: usize = 4;
+
+/// A bounding volume hierarchy over a list of primitives, so that rays only have to be tested
+/// against the few primitives near their path.
+#[derive(Debug, Clone)]
+pub(crate) struct Bvh {
+    nodes: Vec<BvhNode>,
+    /// Primitive indices, ordered so that every leaf covers a contiguous range
+    order: Vec<usize>,
+}
+
+#[derive(Debug, Clone)]
+struct BvhNode {
+    bounds: Bounds,
+    kind: BvhNodeKind,
+}
+
+#[derive(Debug, Clone)]
+enum BvhNodeKind {
+    /// Covers `order[start..start + count]`
+    Leaf { start: usize, count: usize },
+    /// The first child directly follows this node; the second one is at `second`
+    Interior { second: usize },
+}
+
+impl Bvh {
+    /// Builds the hierarchy over primitives with the given bounds, splitting each node in half
+    /// along the axis its primitives are most spread out on.
+    pub(crate) fn new(bounds: &[Bounds]) -> Bvh {
+        let mut bvh = Bvh {
+            nodes: Vec::new(),
+            order: (0..bounds.len()).collect(),
+        };
+        if !bounds.is_empty() {
+            bvh.build(bounds, 0, bounds.len());
+        }
+        bvh
+    }
+
+    fn build(&mut self, bounds: &[Bounds], start: usize, end: usize) {
+        let node_bounds = self.order[start..end]
+            .iter()
+            .map(|&i| bounds[i])
+            .fold(bounds[self.order[start]], |b, other| b.union(&other));
+        let index = self.nodes.len();
+        self.nodes.push(BvhNode {
+            bounds: node_bounds,
+            kind: BvhNodeKind::Leaf {
+                start,
+                count: end - start,
+            },
+        });
+        if end - start <= LEAF_SIZE {
+            return;
+        }
+
+        let centers = Bounds::from_points(
+            &self.order[start..end]
+                .iter()
+                .map(|&i| bounds[i].center())
+                .collect::<Vec<_>>(),
+        );
+        let extent = centers.max - centers.min;
+        let axis = extent.imax();
+        if extent[axis] <= 0.0 {
+            // every primitive is in the same place, so splitting won't help
+            return;
+        }
+
+        let middle = (start + end) / 2;
+        self.order[start..end].select_nth_unstable_by(middle - start, |&a, &b| {
+            bounds[a].center()[axis]
+                .partial_cmp(&bounds[b].center()[axis])
+                .unwrap_or(std::cmp::Ordering::Equal)
+        });
+        self.build(bounds, start, middle);
+        let second = self.nodes.len();
+        self.build(bounds, middle, end);
+        self.nodes[index].kind = BvhNodeKind::Interior { second };
+    }
+
+    /// Finds the closest primitive hit by `ray`.  `test` intersects a single primitive with a
+    /// ray, returning the distance to the hit along with anything else it wants to keep.
+    pub(crate) fn closest<T, F>(&self, ray: &Ray, mut test: F) -> Option<(f32, T)>
+    where
+        F: FnMut(usize, &Ray) -> Option<(f32, T)>,
+    {
+        let mut clipped = ray.clone();
+        let mut closest = None;
+        self.walk(&mut clipped, |i, ray| {
+            if let Some((t, hit)) = test(i, ray) {
+                ray.tmax = t;
+                closest = Some((t, hit));
+            }
+            false
+        });
+        closest
+    }
+
+    /// Determines whether `test` accepts any primitive along `ray`, stopping at the first one.
+    pub(crate) fn any<F>(&self, ray: &Ray, mut test: F) -> bool
+    where
+        F: FnMut(usize, &Ray) -> bool,
+    {
+        self.walk(&mut ray.clone(), |i, ray| test(i, ray))
+    }
+
+    /// Visits the primitives whose nodes `ray` passes through, nearest nodes first, until
+    /// `visit` returns true.  `visit` may shorten the ray to skip farther nodes.
+    fn walk<F>(&self, ray: &mut Ray, mut visit: F) -> bool
+    where
+        F: FnMut(usize, &mut Ray) -> bool,
+    {
+        if self.nodes.is_empty() || self.nodes[0].bounds.intersect(ray).is_none() {
+            return false;
+        }
+
+        let mut stack = vec![0];
+        while let Some(index) = stack.pop() {
+            let node = &self.nodes[index];
+            match node.kind {
+                BvhNodeKind::Leaf { start, count } => {
+                    for &i in &self.order[start..start + count] {
+                        if visit(i, ray) {
+                            return true;
+                        }
+                    }
+                }
+                BvhNodeKind::Interior { second } => {
+                    let first = index + 1;
+                    let near = self.nodes[first].bounds.intersect(ray).map(|(t, _)| t);
+                    let far = self.nodes[second].bounds.intersect(ray).map(|(t, _)| t);
+                    // push the farther child first so the nearer one is visited first
+                    match (near, far) {
+                        (Some(a), Some(b)) if a <= b => stack.extend(&[second, first]),
+                        (Some(_), Some(_)) => stack.extend(&[first, second]),
+                        (Some(_), None) => stack.push(first),
+                        (None, Some(_)) => stack.push(second),
+                        (None, None) => {}
+                    }
+                }
+            }
+        }
+        false
+    }
+
+    pub(crate) fn bounds(&self) -> Option<Bounds> {
+        self.nodes.first().map(|node| node.bounds)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use nalgebra::*;
+
+    #[test]
+    fn test_closest() {
+        // a row of unit boxes along z
+        let boxes = (0..20)
+            .map(|i| {
+                let z = i as f32 * 2.0;
+                Bounds::new(Point3::new(-0.5, -0.5, z), Point3::new(0.5, 0.5, z + 1.0))
+            })
+            .collect::<Vec<_>>();
+        let bvh = Bvh::new(&boxes);
+        assert_eq!(bvh.bounds().unwrap().max.z, 39.0);
+
+        let mut tested = 0;
+        let ray = Ray::new(Point3::new(0.0, 0.0, 9.5), Vector3::new(0.0, 0.0, 1.0));
+        let hit = bvh.closest(&ray, |i, ray| {
+            tested += 1;
+            boxes[i].intersect(ray).map(|(t, _)| (t, i))
+        });
+        assert_eq!(hit, Some((0.5, 5)));
+        // the boxes behind the hit were never looked at
+        assert!(tested < 10);
+
+        let miss = Ray::new(Point3::new(2.0, 0.0, 0.0), Vector3::new(0.0, 0.0, 1.0));
+        assert!(!bvh.any(&miss, |i, ray| boxes[i].intersect(ray).is_some()));
+        assert!(Bvh::new(&[])
+            .closest(&ray, |_, _| Some((0.0, ())))
+            .is_none());
+    }
+}
@@ -0,0 +1,163 @@
+use super::cylinder::cap_parameterization;
+use super::roots::*;
+use super::*;
+use nalgebra::*;
+use std::f32::consts::PI;
+
+/// A cone closed off at its base.  It stands upright, narrowing along the y axis from the center
+/// of its base up to its tip.
+#[derive(Debug)]
+pub struct Cone {
+    pub(crate) base: Point3<f32>,
+    pub(crate) radius: f32,
+    pub(crate) height: f32,
+    pub(crate) material: MaterialId,
+}
+
+impl Cone {
+    pub fn new(base: Point3<f32>, radius: f32, height: f32, material: MaterialId) -> Cone {
+        Cone {
+            base,
+            radius,
+            height,
+            material,
+        }
+    }
+
+    /// Finds every distance along the ray where it crosses the surface, ignoring the ray's
+    /// interval, in ascending order.
+    fn hit_distances(&self, ray: &Ray) -> Vec<f32> {
+        let o = (ray.source - self.base).map(f64::from);
+        let d = ray.direction.map(f64::from);
+        let (radius, height) = (f64::from(self.radius), f64::from(self.height));
+        // how quickly the radius shrinks going up
+        let k = radius / height;
+        let radius_at_origin = radius - k * o.y;
+
+        let mut hits = solve_quadratic(
+            d.x * d.x + d.z * d.z - k * k * d.y * d.y,
+            2.0 * (o.x * d.x + o.z * d.z + k * d.y * radius_at_origin),
+            o.x * o.x + o.z * o.z - radius_at_origin * radius_at_origin,
+        )
+        .into_iter()
+        // the equation also describes a second cone mirrored through the tip
+        .filter(|t| (0.0..=height).contains(&(o.y + t * d.y)))
+        .collect::<Vec<_>>();
+
+        if d.y.abs() > 1e-12 {
+            let t = -o.y / d.y;
+            let (x, z) = (o.x + t * d.x, o.z + t * d.z);
+            if x * x + z * z <= radius * radius {
+                hits.push(t);
+            }
+        }
+
+        let mut hits = hits.into_iter().map(|t| t as f32).collect::<Vec<_>>();
+        hits.sort_by(|a, b| a.partial_cmp(b).unwrap());
+        hits
+    }
+
+    /// Describes the surface of the cone where `ray` hits it at distance `t`.  Around the side,
+    /// `u` runs around the y axis and `v` runs from the tip to the base.  The base is mapped flat
+    /// onto `[0, 1]²`.
+    fn surface_interaction(&self, ray: &Ray, t: f32) -> SurfaceInteraction {
+        let local = (ray.source + t * ray.direction) - self.base;
+        let k = self.radius / self.height;
+        let on_base = local.y.abs() / self.height < 1e-4;
+
+        let (normal, uv, dpdu, dpdv) = if on_base {
+            cap_parameterization(&local, self.radius, false)
+        } else {
+            let mut phi = local.z.atan2(local.x);
+            if phi < 0.0 {
+                phi += 2.0 * PI;
+            }
+            let (cos, sin) = (phi.cos(), phi.sin());
+            (
+                Vector3::new(local.x, k * (self.radius - k * local.y), local.z),
+                Point2::new(phi / (2.0 * PI), 1.0 - local.y / self.height),
+                2.0 * PI * Vector3::new(-local.z, 0.0, local.x),
+                self.height * Vector3::new(k * cos, -1.0, k * sin),
+            )
+        };
+
+        SurfaceInteraction::new(ray, t, normal, uv, dpdu, dpdv)
+    }
+
+    fn hit_distance(&self, ray: &Ray) -> Option<f32> {
+        self.hit_distances(ray)
+            .into_iter()
+            .find(|t| ray.contains(*t))
+    }
+}
+
+impl Intersectable for Cone {
+    fn intersect(&self, ray: &Ray) -> Option<SurfaceInteraction> {
+        self.hit_distance(ray)
+            .map(|t| self.surface_interaction(ray, t))
+    }
+
+    fn occludes(&self, ray: &Ray) -> bool {
+        self.hit_distance(ray).is_some()
+    }
+}
+
+impl Shape for Cone {
+    fn bounds(&self) -> Bounds {
+        Bounds::new(
+            self.base + Vector3::new(-self.radius, 0.0, -self.radius),
+            self.base + Vector3::new(self.radius, self.height, self.radius),
+        )
+    }
+
+    fn material(&self) -> MaterialId {
+        self.material
+    }
+}
+
+impl From<Cone> for Geometry {
+    fn from(cone: Cone) -> Self {
+        Geometry::Cone(cone)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn cone() -> Cone {
+        Cone::new(Point3::new(0.0, 0.0, 5.0), 1.0, 2.0, MaterialId(0))
+    }
+
+    #[test]
+    fn test_cone_side() {
+        // halfway up, the cone's radius is 0.5
+        let ray = Ray::new(Point3::new(0.0, 1.0, 0.0), Vector3::new(0.0, 0.0, 1.0));
+        let hit = cone().intersect(&ray).unwrap();
+        assert!((hit.t - 4.5).abs() < 1e-5);
+        // the side slopes at 1 in 2, so the normal tilts upwards
+        let expected = Vector3::new(0.0, 0.5, -1.0).normalize();
+        assert!((hit.geometric_normal - expected).norm() < 1e-5);
+        assert!((hit.uv.y - 0.5).abs() < 1e-5);
+        let n = hit.dpdu.cross(&hit.dpdv).normalize();
+        assert!((n - hit.geometric_normal).norm() < 1e-5);
+    }
+
+    #[test]
+    fn test_cone_base() {
+        let ray = Ray::new(Point3::new(0.2, -3.0, 5.0), Vector3::new(0.0, 1.0, 0.0));
+        let hit = cone().intersect(&ray).unwrap();
+        assert!((hit.t - 3.0).abs() < 1e-5);
+        assert_eq!(hit.geometric_normal, Vector3::new(0.0, -1.0, 0.0));
+    }
+
+    #[test]
+    fn test_cone_miss() {
+        // passes through where the mirrored cone above the tip would be
+        let ray = Ray::new(Point3::new(0.0, 3.0, 0.0), Vector3::new(0.0, 0.0, 1.0));
+        assert!(cone().intersect(&ray).is_none());
+        // passes beside the narrow top
+        let ray = Ray::new(Point3::new(0.8, 1.8, 0.0), Vector3::new(0.0, 0.0, 1.0));
+        assert!(cone().intersect(&ray).is_none());
+    }
+}
@@ -0,0 +1,349 @@
+use super::*;
+
+/// A closed shape that can be combined with constructive solid geometry.
+#[derive(Debug)]
+pub enum Solid {
+    Sphere(Sphere),
+    Cuboid(Cuboid),
+    Cylinder(Cylinder),
+    Csg(Box<Csg>),
+}
+
+impl From<Sphere> for Solid {
+    fn from(sphere: Sphere) -> Self {
+        Solid::Sphere(sphere)
+    }
+}
+
+impl From<Cuboid> for Solid {
+    fn from(cuboid: Cuboid) -> Self {
+        Solid::Cuboid(cuboid)
+    }
+}
+
+impl From<Cylinder> for Solid {
+    fn from(cylinder: Cylinder) -> Self {
+        Solid::Cylinder(cylinder)
+    }
+}
+
+impl From<Csg> for Solid {
+    fn from(csg: Csg) -> Self {
+        Solid::Csg(Box::new(csg))
+    }
+}
+
+/// A point where a ray crosses the surface of one of the primitives making up a solid.
+#[derive(Debug, Clone, Copy)]
+struct Boundary<'a> {
+    t: f32,
+    /// The primitive whose surface the ray crossed
+    primitive: &'a Solid,
+    /// Whether the primitive's surface faces the other way in the combined solid, as happens to
+    /// the surfaces carved out by a difference
+    flipped: bool,
+}
+
+/// A stretch of a ray lying inside a solid.
+#[derive(Debug, Clone, Copy)]
+struct Span<'a> {
+    enter: Boundary<'a>,
+    exit: Boundary<'a>,
+}
+
+impl Solid {
+    /// Finds every stretch of the ray inside the solid, ignoring the ray's interval, in
+    /// ascending order.
+    fn spans(&self, ray: &Ray) -> Vec<Span<'_>> {
+        let span = match self {
+            Solid::Sphere(s) => s.span(ray),
+            Solid::Cuboid(c) => c.span(ray),
+            Solid::Cylinder(c) => c.span(ray),
+            Solid::Csg(csg) => return csg.spans(ray),
+        };
+        let boundary = |t| Boundary {
+            t,
+            primitive: self,
+            flipped: false,
+        };
+        span.map(|(enter, exit)| Span {
+            enter: boundary(enter),
+            exit: boundary(exit),
+        })
+        .into_iter()
+        .collect()
+    }
+
+    fn bounds(&self) -> Bounds {
+        match self {
+            Solid::Sphere(s) => s.bounds(),
+            Solid::Cuboid(c) => c.bounds(),
+            Solid::Cylinder(c) => c.bounds(),
+            Solid::Csg(csg) => csg.bounds(),
+        }
+    }
+
+    fn material(&self) -> MaterialId {
+        match self {
+            Solid::Sphere(s) => s.material(),
+            Solid::Cuboid(c) => c.material(),
+            Solid::Cylinder(c) => c.material(),
+            Solid::Csg(csg) => csg.material(),
+        }
+    }
+
+    /// Describes the surface of a primitive where the ray crosses it.
+    fn surface_interaction(&self, ray: &Ray, t: f32) -> SurfaceInteraction {
+        match self {
+            Solid::Sphere(s) => s.surface_interaction(ray, t),
+            Solid::Cuboid(c) => c.surface_interaction(ray, t),
+            Solid::Cylinder(c) => c.surface_interaction(ray, t),
+            Solid::Csg(_) => unreachable!("boundaries always lie on primitives"),
+        }
+    }
+}
+
+/// How the two halves of a CSG node are combined.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CsgOperation {
+    /// Everything inside either solid
+    Union,
+    /// Everything inside both solids
+    Intersection,
+    /// Everything inside the first solid but not the second
+    Difference,
+}
+
+impl CsgOperation {
+    fn contains(self, in_left: bool, in_right: bool) -> bool {
+        match self {
+            CsgOperation::Union => in_left || in_right,
+            CsgOperation::Intersection => in_left && in_right,
+            CsgOperation::Difference => in_left && !in_right,
+        }
+    }
+}
+
+/// Combines two solids with a boolean operation.  Surfaces take their material from whichever
+/// primitive they belong to.
+#[derive(Debug)]
+pub struct Csg {
+    pub(crate) operation: CsgOperation,
+    pub(crate) left: Solid,
+    pub(crate) right: Solid,
+}
+
+impl Csg {
+    pub fn new<L, R>(operation: CsgOperation, left: L, right: R) -> Csg
+    where
+        L: Into<Solid>,
+        R: Into<Solid>,
+    {
+        Csg {
+            operation,
+            left: left.into(),
+            right: right.into(),
+        }
+    }
+
+    /// Everything inside either solid.
+    pub fn union<L: Into<Solid>, R: Into<Solid>>(left: L, right: R) -> Csg {
+        Csg::new(CsgOperation::Union, left, right)
+    }
+
+    /// Everything inside both solids.
+    pub fn intersection<L: Into<Solid>, R: Into<Solid>>(left: L, right: R) -> Csg {
+        Csg::new(CsgOperation::Intersection, left, right)
+    }
+
+    /// Everything inside `left` but not `right`.
+    pub fn difference<L: Into<Solid>, R: Into<Solid>>(left: L, right: R) -> Csg {
+        Csg::new(CsgOperation::Difference, left, right)
+    }
+
+    /// Combines the spans of both halves by sweeping along the ray, tracking whether the ray is
+    /// inside each of them.
+    fn spans(&self, ray: &Ray) -> Vec<Span<'_>> {
+        // carving out the right solid turns its surfaces inside out
+        let flip_right = self.operation == CsgOperation::Difference;
+        let mut events = Vec::new();
+        for (is_left, spans) in [(true, self.left.spans(ray)), (false, self.right.spans(ray))] {
+            let flip = !is_left && flip_right;
+            for span in spans {
+                for (mut boundary, entering) in [(span.enter, true), (span.exit, false)] {
+                    boundary.flipped ^= flip;
+                    events.push((boundary, is_left, entering));
+                }
+            }
+        }
+        events.sort_by(|a, b| a.0.t.partial_cmp(&b.0.t).unwrap());
+
+        let (mut in_left, mut in_right) = (false, false);
+        let mut spans = Vec::new();
+        let mut enter = None;
+        for (boundary, is_left, entering) in events {
+            let was_inside = self.operation.contains(in_left, in_right);
+            if is_left {
+                in_left = entering;
+            } else {
+                in_right = entering;
+            }
+            let inside = self.operation.contains(in_left, in_right);
+            if inside && !was_inside {
+                enter = Some(boundary);
+            } else if !inside && was_inside {
+                if let Some(enter) = enter.take() {
+                    spans.push(Span {
+                        enter,
+                        exit: boundary,
+                    });
+                }
+            }
+        }
+        spans
+    }
+
+    /// Finds the first boundary of the combined solid within the ray's interval.
+    fn first_boundary(&self, ray: &Ray) -> Option<Boundary<'_>> {
+        self.spans(ray)
+            .into_iter()
+            .flat_map(|span| vec![span.enter, span.exit])
+            .find(|boundary| ray.contains(boundary.t))
+    }
+}
+
+impl Intersectable for Csg {
+    fn intersect(&self, ray: &Ray) -> Option<SurfaceInteraction> {
+        let boundary = self.first_boundary(ray)?;
+        let mut surface = boundary.primitive.surface_interaction(ray, boundary.t);
+        if boundary.flipped {
+            surface.geometric_normal = -surface.geometric_normal;
+            surface.front_face = !surface.front_face;
+            // keep dpdu × dpdv pointing along the normal
+            std::mem::swap(&mut surface.dpdu, &mut surface.dpdv);
+        }
+        surface.material = Some(boundary.primitive.material());
+        Some(surface)
+    }
+
+    fn occludes(&self, ray: &Ray) -> bool {
+        self.first_boundary(ray).is_some()
+    }
+}
+
+impl Shape for Csg {
+    fn bounds(&self) -> Bounds {
+        let left = self.left.bounds();
+        match self.operation {
+            CsgOperation::Union => left.union(&self.right.bounds()),
+            CsgOperation::Intersection => left.overlap(&self.right.bounds()),
+            CsgOperation::Difference => left,
+        }
+    }
+
+    /// The material of the first primitive.  Hits report the material of the primitive they
+    /// actually landed on.
+    fn material(&self) -> MaterialId {
+        self.left.material()
+    }
+}
+
+impl From<Csg> for Geometry {
+    fn from(csg: Csg) -> Self {
+        Geometry::Csg(csg)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const CUBE: MaterialId = MaterialId(0);
+    const BALL: MaterialId = MaterialId(1);
+
+    fn cube() -> Cuboid {
+        Cuboid::new(
+            Point3::new(-1.0, -1.0, 4.0),
+            Point3::new(1.0, 1.0, 6.0),
+            CUBE,
+        )
+    }
+
+    fn ball(radius: f32) -> Sphere {
+        Sphere::new(Point3::new(0.0, 0.0, 5.0), radius, BALL)
+    }
+
+    #[test]
+    fn test_union() {
+        let csg = Csg::union(cube(), Sphere::new(Point3::new(0.0, 0.0, 7.0), 1.5, BALL));
+        let ray = Ray::new(Point3::new(0.0, 0.0, 0.0), Vector3::new(0.0, 0.0, 1.0));
+        let hit = csg.intersect(&ray).unwrap();
+        assert!((hit.t - 4.0).abs() < 1e-5);
+        assert_eq!(hit.material, Some(CUBE));
+
+        // from inside the cube, the overlapping sphere hides the cube's back face
+        let ray = Ray::new(Point3::new(0.0, 0.0, 5.0), Vector3::new(0.0, 0.0, 1.0));
+        let hit = csg.intersect(&ray).unwrap();
+        assert!((hit.t - 3.5).abs() < 1e-5);
+        assert_eq!(hit.material, Some(BALL));
+        assert!(!hit.front_face);
+    }
+
+    #[test]
+    fn test_intersection() {
+        // a cube with rounded corners
+        let csg = Csg::intersection(cube(), ball(1.2));
+        let ray = Ray::new(Point3::new(0.0, 0.0, 0.0), Vector3::new(0.0, 0.0, 1.0));
+        let hit = csg.intersect(&ray).unwrap();
+        assert!((hit.t - 4.0).abs() < 1e-5);
+        assert_eq!(hit.material, Some(CUBE));
+
+        // the corners are cut away
+        let ray = Ray::new(Point3::new(0.9, 0.9, 0.0), Vector3::new(0.0, 0.0, 1.0));
+        assert!(csg.intersect(&ray).is_none());
+        assert!(cube().intersect(&ray).is_some());
+    }
+
+    #[test]
+    fn test_difference_flips_normals() {
+        // a cube with a hollow in the middle
+        let csg = Csg::difference(cube(), ball(0.5));
+        let ray = Ray::new(Point3::new(0.0, 0.0, 0.0), Vector3::new(0.0, 0.0, 1.0));
+        let hit = csg.intersect(&ray).unwrap();
+        assert!((hit.t - 4.0).abs() < 1e-5);
+
+        // from inside the hollow, the wall ahead faces back towards the ray
+        let ray = Ray::new(Point3::new(0.0, 0.0, 5.0), Vector3::new(0.0, 0.0, 1.0));
+        let hit = csg.intersect(&ray).unwrap();
+        assert!((hit.t - 0.5).abs() < 1e-5);
+        assert_eq!(hit.material, Some(BALL));
+        assert!(hit.front_face);
+        assert!((hit.geometric_normal - Vector3::new(0.0, 0.0, -1.0)).norm() < 1e-5);
+        let n = hit.dpdu.cross(&hit.dpdv).normalize();
+        assert!((n - hit.geometric_normal).norm() < 1e-4);
+    }
+
+    #[test]
+    fn test_difference_removes_everything() {
+        let csg = Csg::difference(cube(), ball(2.0));
+        let ray = Ray::new(Point3::new(0.0, 0.0, 0.0), Vector3::new(0.0, 0.0, 1.0));
+        assert!(csg.intersect(&ray).is_none());
+        assert!(!csg.occludes(&ray));
+    }
+
+    #[test]
+    fn test_nested() {
+        // drill a hole through the hollow cube, then look down the hole
+        let hollow = Csg::difference(cube(), ball(0.5));
+        let drill = Cylinder::new(Point3::new(0.0, -2.0, 5.0), 0.25, 4.0, MaterialId(2));
+        let csg = Csg::difference(hollow, drill);
+        let ray = Ray::new(Point3::new(0.0, 5.0, 5.0), Vector3::new(0.0, -1.0, 0.0));
+        assert!(csg.intersect(&ray).is_none());
+
+        // just beside the hole, the top of the cube is still there
+        let ray = Ray::new(Point3::new(0.3, 5.0, 5.0), Vector3::new(0.0, -1.0, 0.0));
+        let hit = csg.intersect(&ray).unwrap();
+        assert!((hit.t - 4.0).abs() < 1e-5);
+        assert_eq!(hit.material, Some(CUBE));
+    }
+}
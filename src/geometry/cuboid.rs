@@ -0,0 +1,175 @@
+use super::*;
+use nalgebra::*;
+
+/// An axis-aligned box.
+#[derive(Debug)]
+pub struct Cuboid {
+    pub(crate) min: Point3<f32>,
+    pub(crate) max: Point3<f32>,
+    pub(crate) material: MaterialId,
+}
+
+impl Cuboid {
+    /// Creates a box spanning the two opposite corners.
+    pub fn new(a: Point3<f32>, b: Point3<f32>, material: MaterialId) -> Cuboid {
+        let bounds = Bounds::new(a, b);
+        Cuboid {
+            min: bounds.min,
+            max: bounds.max,
+            material,
+        }
+    }
+
+    /// Finds where the ray enters and leaves the box, ignoring the ray's interval.
+    pub(crate) fn span(&self, ray: &Ray) -> Option<(f32, f32)> {
+        let unbounded = ray.clone().with_interval(f32::NEG_INFINITY, f32::INFINITY);
+        Bounds::new(self.min, self.max).intersect(&unbounded)
+    }
+
+    /// Describes the surface of the box where `ray` hits it at distance `t`.  Each face is
+    /// parameterized by the two axes lying in it, scaled to `[0, 1]`.
+    pub(crate) fn surface_interaction(&self, ray: &Ray, t: f32) -> SurfaceInteraction {
+        let point = ray.source + t * ray.direction;
+        let extent = self.max - self.min;
+
+        // the face that was hit is the one the point is closest to, relative to the box's size.
+        // A box that's flat along an axis is hit on whichever of its faces looks at the ray.
+        let (axis, sign) = (0..3)
+            .flat_map(|axis| {
+                if extent[axis] == 0.0 {
+                    let sign = if ray.direction[axis] > 0.0 { -1.0 } else { 1.0 };
+                    return vec![(0.0, axis, sign)];
+                }
+                let to_min = (point[axis] - self.min[axis]).abs() / extent[axis];
+                let to_max = (point[axis] - self.max[axis]).abs() / extent[axis];
+                vec![(to_min, axis, -1.0), (to_max, axis, 1.0)]
+            })
+            .min_by(|a, b| a.0.total_cmp(&b.0))
+            .map(|(_, axis, sign)| (axis, sign))
+            .unwrap();
+
+        let mut normal = Vector3::zeros();
+        normal[axis] = sign;
+        // pick the other two axes so that dpdu × dpdv points out of the box
+        let (u_axis, v_axis) = if sign > 0.0 {
+            ((axis + 1) % 3, (axis + 2) % 3)
+        } else {
+            ((axis + 2) % 3, (axis + 1) % 3)
+        };
+        let mut dpdu = Vector3::zeros();
+        dpdu[u_axis] = extent[u_axis];
+        let mut dpdv = Vector3::zeros();
+        dpdv[v_axis] = extent[v_axis];
+        let uv = Point2::new(
+            (point[u_axis] - self.min[u_axis]) / extent[u_axis],
+            (point[v_axis] - self.min[v_axis]) / extent[v_axis],
+        );
+
+        SurfaceInteraction::new(ray, t, normal, uv, dpdu, dpdv)
+    }
+
+    fn hit_distance(&self, ray: &Ray) -> Option<f32> {
+        let (near, far) = self.span(ray)?;
+        if ray.contains(near) {
+            Some(near)
+        } else if ray.contains(far) {
+            Some(far)
+        } else {
+            None
+        }
+    }
+}
+
+impl Intersectable for Cuboid {
+    fn intersect(&self, ray: &Ray) -> Option<SurfaceInteraction> {
+        self.hit_distance(ray)
+            .map(|t| self.surface_interaction(ray, t))
+    }
+
+    fn occludes(&self, ray: &Ray) -> bool {
+        self.hit_distance(ray).is_some()
+    }
+}
+
+impl Shape for Cuboid {
+    fn bounds(&self) -> Bounds {
+        Bounds::new(self.min, self.max)
+    }
+
+    fn material(&self) -> MaterialId {
+        self.material
+    }
+}
+
+impl From<Cuboid> for Geometry {
+    fn from(cuboid: Cuboid) -> Self {
+        Geometry::Cuboid(cuboid)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn cuboid() -> Cuboid {
+        Cuboid::new(
+            Point3::new(-1.0, -2.0, 4.0),
+            Point3::new(1.0, 2.0, 6.0),
+            MaterialId(0),
+        )
+    }
+
+    #[test]
+    fn test_cuboid_hit() {
+        let ray = Ray::new(Point3::new(0.0, 1.0, 0.0), Vector3::new(0.0, 0.0, 1.0));
+        let hit = cuboid().intersect(&ray).unwrap();
+        assert!((hit.t - 4.0).abs() < 1e-6);
+        assert!(hit.front_face);
+        assert_eq!(hit.geometric_normal, Vector3::new(0.0, 0.0, -1.0));
+        assert!((hit.uv - Point2::new(0.75, 0.5)).norm() < 1e-6);
+        let n = hit.dpdu.cross(&hit.dpdv).normalize();
+        assert!((n - hit.geometric_normal).norm() < 1e-6);
+    }
+
+    #[test]
+    fn test_cuboid_side_and_inside() {
+        let ray = Ray::new(Point3::new(5.0, 0.0, 5.0), Vector3::new(-1.0, 0.0, 0.0));
+        let hit = cuboid().intersect(&ray).unwrap();
+        assert!((hit.t - 4.0).abs() < 1e-6);
+        assert_eq!(hit.geometric_normal, Vector3::new(1.0, 0.0, 0.0));
+        let n = hit.dpdu.cross(&hit.dpdv).normalize();
+        assert!((n - hit.geometric_normal).norm() < 1e-6);
+
+        let ray = Ray::new(Point3::new(0.0, 0.0, 5.0), Vector3::new(0.0, 1.0, 0.0));
+        let hit = cuboid().intersect(&ray).unwrap();
+        assert!((hit.t - 2.0).abs() < 1e-6);
+        assert!(!hit.front_face);
+        assert_eq!(hit.shading_normal, Vector3::new(0.0, -1.0, 0.0));
+    }
+
+    #[test]
+    fn test_flat_cuboid() {
+        let flat = Cuboid::new(
+            Point3::new(-5.0, -5.0, 3.0),
+            Point3::new(5.0, 5.0, 3.0),
+            MaterialId(0),
+        );
+        let ray = Ray::new(Point3::new(0.0, 0.0, 0.0), Vector3::new(0.0, 0.0, 1.0));
+        let hit = flat.intersect(&ray).unwrap();
+        assert!((hit.t - 3.0).abs() < 1e-6);
+        assert_eq!(hit.geometric_normal, Vector3::new(0.0, 0.0, -1.0));
+        assert!((hit.uv - Point2::new(0.5, 0.5)).norm() < 1e-6);
+
+        let ray = Ray::new(Point3::new(0.0, 0.0, 9.0), Vector3::new(0.0, 0.0, -1.0));
+        let hit = flat.intersect(&ray).unwrap();
+        assert_eq!(hit.geometric_normal, Vector3::new(0.0, 0.0, 1.0));
+    }
+
+    #[test]
+    fn test_cuboid_miss() {
+        let ray = Ray::new(Point3::new(0.0, 3.0, 0.0), Vector3::new(0.0, 0.0, 1.0));
+        assert!(cuboid().intersect(&ray).is_none());
+        let ray = Ray::new(Point3::new(0.0, 0.0, 0.0), Vector3::new(0.0, 0.0, -1.0));
+        assert!(cuboid().intersect(&ray).is_none());
+    }
+}
@@ -0,0 +1,219 @@
+use super::roots::*;
+use super::*;
+use nalgebra::*;
+use std::f32::consts::PI;
+
+/// A cylinder closed off at both ends.  It stands upright, extending along the y axis from the
+/// center of its base.
+#[derive(Debug)]
+pub struct Cylinder {
+    pub(crate) base: Point3<f32>,
+    pub(crate) radius: f32,
+    pub(crate) height: f32,
+    pub(crate) material: MaterialId,
+}
+
+impl Cylinder {
+    pub fn new(base: Point3<f32>, radius: f32, height: f32, material: MaterialId) -> Cylinder {
+        Cylinder {
+            base,
+            radius,
+            height,
+            material,
+        }
+    }
+
+    /// Finds every distance along the ray where it crosses the surface, ignoring the ray's
+    /// interval, in ascending order.
+    fn hit_distances(&self, ray: &Ray) -> Vec<f32> {
+        let o = (ray.source - self.base).map(f64::from);
+        let d = ray.direction.map(f64::from);
+        let (radius, height) = (f64::from(self.radius), f64::from(self.height));
+
+        let mut hits = solve_quadratic(
+            d.x * d.x + d.z * d.z,
+            2.0 * (o.x * d.x + o.z * d.z),
+            o.x * o.x + o.z * o.z - radius * radius,
+        )
+        .into_iter()
+        .filter(|t| (0.0..=height).contains(&(o.y + t * d.y)))
+        .collect::<Vec<_>>();
+
+        if d.y.abs() > 1e-12 {
+            for cap in &[0.0, height] {
+                let t = (cap - o.y) / d.y;
+                let (x, z) = (o.x + t * d.x, o.z + t * d.z);
+                if x * x + z * z <= radius * radius {
+                    hits.push(t);
+                }
+            }
+        }
+
+        let mut hits = hits.into_iter().map(|t| t as f32).collect::<Vec<_>>();
+        hits.sort_by(|a, b| a.partial_cmp(b).unwrap());
+        hits
+    }
+
+    /// Describes the surface of the cylinder where `ray` hits it at distance `t`.  Around the
+    /// side, `u` runs around the y axis and `v` runs from top to bottom.  The caps are mapped
+    /// flat onto `[0, 1]²`.
+    pub(crate) fn surface_interaction(&self, ray: &Ray, t: f32) -> SurfaceInteraction {
+        let local = (ray.source + t * ray.direction) - self.base;
+        let rho = (local.x * local.x + local.z * local.z).sqrt();
+
+        // whichever part of the surface the point is closest to, relative to its size
+        let to_side = (rho - self.radius).abs() / self.radius;
+        let to_bottom = local.y.abs() / self.height;
+        let to_top = (local.y - self.height).abs() / self.height;
+
+        let (normal, uv, dpdu, dpdv) = if to_side <= to_bottom && to_side <= to_top {
+            let mut phi = local.z.atan2(local.x);
+            if phi < 0.0 {
+                phi += 2.0 * PI;
+            }
+            (
+                Vector3::new(local.x, 0.0, local.z),
+                Point2::new(phi / (2.0 * PI), 1.0 - local.y / self.height),
+                2.0 * PI * Vector3::new(-local.z, 0.0, local.x),
+                Vector3::new(0.0, -self.height, 0.0),
+            )
+        } else {
+            cap_parameterization(&local, self.radius, to_top < to_bottom)
+        };
+
+        SurfaceInteraction::new(ray, t, normal, uv, dpdu, dpdv)
+    }
+
+    /// Finds where the ray enters and leaves the cylinder, ignoring the ray's interval.
+    pub(crate) fn span(&self, ray: &Ray) -> Option<(f32, f32)> {
+        let hits = self.hit_distances(ray);
+        match (hits.first(), hits.last()) {
+            (Some(near), Some(far)) if hits.len() >= 2 => Some((*near, *far)),
+            _ => None,
+        }
+    }
+
+    fn hit_distance(&self, ray: &Ray) -> Option<f32> {
+        self.hit_distances(ray)
+            .into_iter()
+            .find(|t| ray.contains(*t))
+    }
+}
+
+/// Maps a point on a circular cap of the given radius, centered on the y axis, flat onto
+/// `[0, 1]²`.  Returns the normal, uv and tangents for the point.
+pub(crate) fn cap_parameterization(
+    local: &Vector3<f32>,
+    radius: f32,
+    top: bool,
+) -> (Vector3<f32>, Point2<f32>, Vector3<f32>, Vector3<f32>) {
+    let size = 2.0 * radius;
+    let across_x = Vector3::new(size, 0.0, 0.0);
+    let across_z = Vector3::new(0.0, 0.0, size);
+    let x = local.x / size + 0.5;
+    let z = local.z / size + 0.5;
+    // pick the axes so that dpdu × dpdv points out of the cap
+    if top {
+        (
+            Vector3::new(0.0, 1.0, 0.0),
+            Point2::new(z, x),
+            across_z,
+            across_x,
+        )
+    } else {
+        (
+            Vector3::new(0.0, -1.0, 0.0),
+            Point2::new(x, z),
+            across_x,
+            across_z,
+        )
+    }
+}
+
+impl Intersectable for Cylinder {
+    fn intersect(&self, ray: &Ray) -> Option<SurfaceInteraction> {
+        self.hit_distance(ray)
+            .map(|t| self.surface_interaction(ray, t))
+    }
+
+    fn occludes(&self, ray: &Ray) -> bool {
+        self.hit_distance(ray).is_some()
+    }
+}
+
+impl Shape for Cylinder {
+    fn bounds(&self) -> Bounds {
+        Bounds::new(
+            self.base + Vector3::new(-self.radius, 0.0, -self.radius),
+            self.base + Vector3::new(self.radius, self.height, self.radius),
+        )
+    }
+
+    fn material(&self) -> MaterialId {
+        self.material
+    }
+}
+
+impl From<Cylinder> for Geometry {
+    fn from(cylinder: Cylinder) -> Self {
+        Geometry::Cylinder(cylinder)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn cylinder() -> Cylinder {
+        Cylinder::new(Point3::new(0.0, 0.0, 5.0), 1.0, 2.0, MaterialId(0))
+    }
+
+    fn assert_outward_tangents(hit: &SurfaceInteraction) {
+        let n = hit.dpdu.cross(&hit.dpdv).normalize();
+        assert!((n - hit.geometric_normal).norm() < 1e-5);
+    }
+
+    #[test]
+    fn test_cylinder_side() {
+        let ray = Ray::new(Point3::new(0.0, 1.0, 0.0), Vector3::new(0.0, 0.0, 1.0));
+        let hit = cylinder().intersect(&ray).unwrap();
+        assert!((hit.t - 4.0).abs() < 1e-5);
+        assert!((hit.geometric_normal - Vector3::new(0.0, 0.0, -1.0)).norm() < 1e-5);
+        assert!((hit.uv - Point2::new(0.75, 0.5)).norm() < 1e-5);
+        assert_outward_tangents(&hit);
+    }
+
+    #[test]
+    fn test_cylinder_caps() {
+        let ray = Ray::new(Point3::new(0.5, 10.0, 5.0), Vector3::new(0.0, -1.0, 0.0));
+        let hit = cylinder().intersect(&ray).unwrap();
+        assert!((hit.t - 8.0).abs() < 1e-5);
+        assert_eq!(hit.geometric_normal, Vector3::new(0.0, 1.0, 0.0));
+        assert_outward_tangents(&hit);
+
+        let ray = Ray::new(Point3::new(0.5, -10.0, 5.0), Vector3::new(0.0, 1.0, 0.0));
+        let hit = cylinder().intersect(&ray).unwrap();
+        assert!((hit.t - 10.0).abs() < 1e-5);
+        assert_eq!(hit.geometric_normal, Vector3::new(0.0, -1.0, 0.0));
+        assert_outward_tangents(&hit);
+    }
+
+    #[test]
+    fn test_cylinder_miss() {
+        // passes over the top
+        let ray = Ray::new(Point3::new(0.0, 3.0, 0.0), Vector3::new(0.0, 0.0, 1.0));
+        assert!(cylinder().intersect(&ray).is_none());
+        // passes beside it
+        let ray = Ray::new(Point3::new(2.0, 1.0, 0.0), Vector3::new(0.0, 0.0, 1.0));
+        assert!(cylinder().intersect(&ray).is_none());
+    }
+
+    #[test]
+    fn test_cylinder_inside() {
+        let ray = Ray::new(Point3::new(0.0, 1.0, 5.0), Vector3::new(0.0, 1.0, 0.0));
+        let hit = cylinder().intersect(&ray).unwrap();
+        assert!((hit.t - 1.0).abs() < 1e-5);
+        assert!(!hit.front_face);
+        assert_eq!(cylinder().hit_distances(&ray).len(), 2);
+    }
+}
@@ -0,0 +1,136 @@
+use super::*;
+use nalgebra::*;
+
+/// A flat, round disk.  Unlike a plane, it can be hit from either side.
+#[derive(Debug)]
+pub struct Disk {
+    pub(crate) center: Point3<f32>,
+    pub(crate) normal: Vector3<f32>,
+    pub(crate) radius: f32,
+    pub(crate) material: MaterialId,
+}
+
+impl Disk {
+    pub fn new(
+        center: Point3<f32>,
+        normal: Vector3<f32>,
+        radius: f32,
+        material: MaterialId,
+    ) -> Disk {
+        Disk {
+            center,
+            normal: unit_vector(&normal),
+            radius,
+            material,
+        }
+    }
+
+    fn hit_distance(&self, ray: &Ray) -> Option<f32> {
+        let dot = self.normal.dot(&ray.direction);
+        if dot.abs() < 1e-6 {
+            return None;
+        }
+        let t = (self.center - ray.source).dot(&self.normal) / dot;
+        let offset = (ray.source + t * ray.direction) - self.center;
+        if ray.contains(t) && offset.norm_squared() <= self.radius * self.radius {
+            Some(t)
+        } else {
+            None
+        }
+    }
+}
+
+impl Intersectable for Disk {
+    fn intersect(&self, ray: &Ray) -> Option<SurfaceInteraction> {
+        let t = self.hit_distance(ray)?;
+        // map the disk flat onto [0, 1]² using a pair of axes lying in it
+        let (u_axis, v_axis) = coordinate_system(&self.normal);
+        let size = 2.0 * self.radius;
+        let offset = (ray.source + t * ray.direction) - self.center;
+        let uv = Point2::new(
+            offset.dot(&u_axis) / size + 0.5,
+            offset.dot(&v_axis) / size + 0.5,
+        );
+        Some(SurfaceInteraction::new(
+            ray,
+            t,
+            self.normal,
+            uv,
+            u_axis * size,
+            v_axis * size,
+        ))
+    }
+
+    fn occludes(&self, ray: &Ray) -> bool {
+        self.hit_distance(ray).is_some()
+    }
+}
+
+impl Shape for Disk {
+    fn bounds(&self) -> Bounds {
+        // how far the rim reaches along each axis
+        let extent = self
+            .normal
+            .map(|n| self.radius * (1.0 - n * n).max(0.0).sqrt());
+        Bounds::new(self.center - extent, self.center + extent)
+    }
+
+    fn material(&self) -> MaterialId {
+        self.material
+    }
+}
+
+impl From<Disk> for Geometry {
+    fn from(disk: Disk) -> Self {
+        Geometry::Disk(disk)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn disk() -> Disk {
+        Disk::new(
+            Point3::new(0.0, 0.0, 5.0),
+            Vector3::new(0.0, 0.0, -1.0),
+            1.0,
+            MaterialId(0),
+        )
+    }
+
+    #[test]
+    fn test_disk_hit() {
+        let ray = Ray::new(Point3::new(0.5, 0.0, 0.0), Vector3::new(0.0, 0.0, 1.0));
+        let hit = disk().intersect(&ray).unwrap();
+        assert!((hit.t - 5.0).abs() < 1e-6);
+        assert!(hit.front_face);
+        let n = hit.dpdu.cross(&hit.dpdv).normalize();
+        assert!((n - hit.geometric_normal).norm() < 1e-6);
+        assert!(hit.uv.x >= 0.0 && hit.uv.x <= 1.0 && hit.uv.y >= 0.0 && hit.uv.y <= 1.0);
+    }
+
+    #[test]
+    fn test_disk_back() {
+        let ray = Ray::new(Point3::new(0.0, 0.5, 10.0), Vector3::new(0.0, 0.0, -1.0));
+        let hit = disk().intersect(&ray).unwrap();
+        assert!((hit.t - 5.0).abs() < 1e-6);
+        assert!(!hit.front_face);
+        assert_eq!(hit.shading_normal, Vector3::new(0.0, 0.0, 1.0));
+    }
+
+    #[test]
+    fn test_disk_miss() {
+        let ray = Ray::new(Point3::new(1.5, 0.0, 0.0), Vector3::new(0.0, 0.0, 1.0));
+        assert!(disk().intersect(&ray).is_none());
+        let ray = Ray::new(Point3::new(0.0, 0.0, 0.0), Vector3::new(1.0, 0.0, 0.0));
+        assert!(disk().intersect(&ray).is_none());
+    }
+
+    #[test]
+    fn test_disk_bounds() {
+        let bounds = disk().bounds();
+        assert_eq!(bounds.min, Point3::new(-1.0, -1.0, 5.0));
+        assert_eq!(bounds.max, Point3::new(1.0, 1.0, 5.0));
+    }
+}
@@ -0,0 +1,654 @@
+use nalgebra::*;
+use rand::{rngs::StdRng, Rng, SeedableRng};
+use std::error::Error;
+use std::fmt;
+use std::fs;
+use std::io::{self, BufRead, Cursor, Read, Seek};
+use std::path::Path;
+
+use super::triangle::intersect_triangle;
+use super::*;
+use crate::prelude::*;
+
+/// A grid of heights between 0 and 1, such as a grayscale image.  `x` runs along the rows
+/// and `z` down the columns.
+#[derive(Debug, Clone, PartialEq)]
+pub struct HeightMap {
+    pub(crate) width: usize,
+    pub(crate) depth: usize,
+    pub(crate) heights: Vec<f32>,
+}
+
+/// Errors that can occur while reading a height map.
+#[derive(Debug)]
+pub enum HeightMapError {
+    Io(io::Error),
+    Png(png::DecodingError),
+    /// The file isn't an image we know how to read, or is malformed
+    Format(String),
+}
+
+impl HeightMap {
+    /// Creates a height map from `width * depth` heights, stored one row at a time.
+    pub fn new(width: usize, depth: usize, heights: Vec<f32>) -> HeightMap {
+        assert!(
+            width >= 2 && depth >= 2,
+            "height maps need at least 2x2 samples"
+        );
+        assert_eq!(heights.len(), width * depth, "wrong number of heights");
+        HeightMap {
+            width,
+            depth,
+            heights,
+        }
+    }
+
+    /// Loads a grayscale PGM or PNG image, telling them apart by their contents.  Color images
+    /// use the average of their channels.
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<HeightMap, HeightMapError> {
+        let bytes = fs::read(path)?;
+        if bytes.starts_with(b"\x89PNG") {
+            HeightMap::from_png(Cursor::new(bytes))
+        } else {
+            HeightMap::from_pgm(&bytes[..])
+        }
+    }
+
+    /// Reads an ASCII (P2) or binary (P5) PGM image.
+    pub fn from_pgm<R: Read>(mut reader: R) -> Result<HeightMap, HeightMapError> {
+        let mut bytes = Vec::new();
+        reader.read_to_end(&mut bytes)?;
+
+        let mut header = PgmHeader {
+            bytes: &bytes,
+            position: 0,
+        };
+
+        let binary = match header.token().as_deref() {
+            Some("P2") => false,
+            Some("P5") => true,
+            _ => return Err(HeightMapError::Format("not a PGM image".to_string())),
+        };
+        let width = header.number("width")?;
+        let depth = header.number("height")?;
+        let max = header.number("maximum value")?;
+        if width < 2 || depth < 2 {
+            return Err(HeightMapError::Format(format!(
+                "a {}x{} image is too small for a height map",
+                width, depth
+            )));
+        }
+        if max == 0 || max > 65535 {
+            return Err(HeightMapError::Format(format!(
+                "invalid PGM maximum value {}",
+                max
+            )));
+        }
+
+        let count = width * depth;
+        let values = if binary {
+            // exactly one whitespace character separates the header from the pixels
+            let start = header.position + 1;
+            let size = if max > 255 { 2 } else { 1 };
+            let data = bytes
+                .get(start..start + count * size)
+                .ok_or_else(|| HeightMapError::Format("PGM pixel data is truncated".to_string()))?;
+            data.chunks(size)
+                .map(|c| c.iter().fold(0, |v, &b| (v << 8) | b as usize))
+                .collect::<Vec<_>>()
+        } else {
+            (0..count)
+                .map(|_| header.number("pixel data"))
+                .collect::<Result<Vec<_>, _>>()?
+        };
+
+        let heights = values
+            .into_iter()
+            .map(|v| v.min(max) as f32 / max as f32)
+            .collect();
+        Ok(HeightMap::new(width, depth, heights))
+    }
+
+    /// Reads a PNG image of any bit depth.
+    pub fn from_png<R: BufRead + Seek>(reader: R) -> Result<HeightMap, HeightMapError> {
+        let mut decoder = png::Decoder::new(reader);
+        decoder.set_transformations(png::Transformations::EXPAND);
+        let mut reader = decoder.read_info()?;
+        let mut buffer = vec![0; reader.output_buffer_size().unwrap_or(0)];
+        let info = reader.next_frame(&mut buffer)?;
+
+        let (width, depth) = (info.width as usize, info.height as usize);
+        if width < 2 || depth < 2 {
+            return Err(HeightMapError::Format(format!(
+                "a {}x{} image is too small for a height map",
+                width, depth
+            )));
+        }
+        let channels = info.color_type.samples();
+        let (size, max) = match info.bit_depth {
+            png::BitDepth::Sixteen => (2, 65535.0),
+            _ => (1, 255.0),
+        };
+        // alpha doesn't affect the height
+        let colors = if channels >= 3 { 3 } else { 1 };
+
+        let heights = buffer[..depth * info.line_size]
+            .chunks(info.line_size)
+            .flat_map(|line| line[..width * channels * size].chunks(channels * size))
+            .map(|pixel| {
+                let sum: f32 = pixel
+                    .chunks(size)
+                    .take(colors)
+                    .map(|c| c.iter().fold(0, |v, &b| (v << 8) | b as u32) as f32)
+                    .sum();
+                sum / (colors as f32 * max)
+            })
+            .collect();
+        Ok(HeightMap::new(width, depth, heights))
+    }
+
+    /// Generates rolling terrain from fractal value noise.  Each octave adds detail at twice the
+    /// frequency and half the amplitude of the one before it.  The same seed always produces the
+    /// same terrain.  Octaves finer than the samples can show are left out.
+    ///
+    /// # Panics
+    /// Panics unless the map is at least 2x2.
+    pub fn noise(width: usize, depth: usize, seed: u64, octaves: u32) -> HeightMap {
+        assert!(
+            width >= 2 && depth >= 2,
+            "height maps need at least 2x2 samples"
+        );
+        let mut rng = StdRng::seed_from_u64(seed);
+        let mut heights = vec![0.0; width * depth];
+
+        for octave in 0..octaves.min(HeightMap::max_noise_octaves(width, depth)) {
+            let frequency = 4 << octave;
+            let amplitude = 0.5_f32.powi(octave as i32);
+            let lattice = (0..(frequency + 1) * (frequency + 1))
+                .map(|_| rng.gen::<f32>())
+                .collect::<Vec<_>>();
+            let value = |x: usize, z: usize| lattice[z * (frequency + 1) + x];
+            let smooth = |t: f32| t * t * (3.0 - 2.0 * t);
+
+            for z in 0..depth {
+                for x in 0..width {
+                    let fx = x as f32 / (width - 1) as f32 * frequency as f32;
+                    let fz = z as f32 / (depth - 1) as f32 * frequency as f32;
+                    let (ix, iz) = (
+                        (fx as usize).min(frequency - 1),
+                        (fz as usize).min(frequency - 1),
+                    );
+                    let (tx, tz) = (smooth(fx - ix as f32), smooth(fz - iz as f32));
+                    let near = value(ix, iz) + (value(ix + 1, iz) - value(ix, iz)) * tx;
+                    let far = value(ix, iz + 1) + (value(ix + 1, iz + 1) - value(ix, iz + 1)) * tx;
+                    heights[z * width + x] += amplitude * (near + (far - near) * tz);
+                }
+            }
+        }
+
+        // stretch the result to cover the whole range
+        let (low, high) = heights
+            .iter()
+            .fold((f32::INFINITY, f32::NEG_INFINITY), |(low, high), &h| {
+                (low.min(h), high.max(h))
+            });
+        let range = (high - low).max(f32::EPSILON);
+        let heights = heights.into_iter().map(|h| (h - low) / range).collect();
+        HeightMap::new(width, depth, heights)
+    }
+
+    /// The most octaves of noise that add detail at the given resolution: those whose lattice
+    /// is no finer than the samples, and always at least the first.
+    pub fn max_noise_octaves(width: usize, depth: usize) -> u32 {
+        // octave `n` has `4 << n` cells across
+        usize::BITS - (width.max(depth) / 4).max(1).leading_zeros()
+    }
+
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    pub fn depth(&self) -> usize {
+        self.depth
+    }
+
+    pub fn height(&self, x: usize, z: usize) -> f32 {
+        self.heights[z * self.width + x]
+    }
+}
+
+/// Reads the whitespace separated fields at the start of a PGM file, skipping comments.
+struct PgmHeader<'a> {
+    bytes: &'a [u8],
+    position: usize,
+}
+
+impl<'a> PgmHeader<'a> {
+    fn token(&mut self) -> Option<String> {
+        let bytes = self.bytes;
+        loop {
+            while self.position < bytes.len() && bytes[self.position].is_ascii_whitespace() {
+                self.position += 1;
+            }
+            if self.position < bytes.len() && bytes[self.position] == b'#' {
+                while self.position < bytes.len() && bytes[self.position] != b'\n' {
+                    self.position += 1;
+                }
+            } else {
+                break;
+            }
+        }
+        let start = self.position;
+        while self.position < bytes.len() && !bytes[self.position].is_ascii_whitespace() {
+            self.position += 1;
+        }
+        if start == self.position {
+            None
+        } else {
+            Some(String::from_utf8_lossy(&bytes[start..self.position]).into_owned())
+        }
+    }
+
+    fn number(&mut self, what: &str) -> Result<usize, HeightMapError> {
+        self.token()
+            .and_then(|token| token.parse().ok())
+            .ok_or_else(|| HeightMapError::Format(format!("PGM is missing its {}", what)))
+    }
+}
+
+impl fmt::Display for HeightMapError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            HeightMapError::Io(e) => write!(f, "couldn't read height map: {}", e),
+            HeightMapError::Png(e) => write!(f, "couldn't decode PNG: {}", e),
+            HeightMapError::Format(message) => f.write_str(message),
+        }
+    }
+}
+
+impl Error for HeightMapError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            HeightMapError::Io(e) => Some(e),
+            HeightMapError::Png(e) => Some(e),
+            HeightMapError::Format(_) => None,
+        }
+    }
+}
+
+impl From<io::Error> for HeightMapError {
+    fn from(e: io::Error) -> Self {
+        HeightMapError::Io(e)
+    }
+}
+
+impl From<png::DecodingError> for HeightMapError {
+    fn from(e: png::DecodingError) -> Self {
+        HeightMapError::Png(e)
+    }
+}
+
+/// Terrain built from a height map.  Each cell of the grid is split into two triangles, but
+/// rays walk the grid cell by cell instead of testing every triangle, and skip cells whose
+/// heights they pass over.  Normals are interpolated across the triangles so the terrain
+/// shades smoothly.
+#[derive(Debug)]
+pub struct Heightfield {
+    pub(crate) map: HeightMap,
+    /// Spans from the lowest corner of the grid to the highest
+    pub(crate) bounds: Bounds,
+    pub(crate) material: MaterialId,
+    /// Smoothed normal at each sample
+    normals: Vec<Vector3<f32>>,
+    /// Lowest and highest point in each cell
+    ranges: Vec<(f32, f32)>,
+}
+
+impl Heightfield {
+    /// Stretches `map` over a box starting at `corner`.  The grid covers `size.x` by `size.z`,
+    /// and a height of 1 rises `size.y` above the corner.
+    pub fn new(
+        map: HeightMap,
+        corner: Point3<f32>,
+        size: Vector3<f32>,
+        material: MaterialId,
+    ) -> Heightfield {
+        let mut heightfield = Heightfield {
+            bounds: Bounds::new(corner, corner + size),
+            map,
+            material,
+            normals: Vec::new(),
+            ranges: Vec::new(),
+        };
+
+        let (width, depth) = (heightfield.map.width, heightfield.map.depth);
+        heightfield.normals = (0..depth)
+            .flat_map(|z| (0..width).map(move |x| (x, z)))
+            .map(|(x, z)| {
+                // central differences, falling back to one side at the edges
+                let (x0, x1) = (x.saturating_sub(1), (x + 1).min(width - 1));
+                let (z0, z1) = (z.saturating_sub(1), (z + 1).min(depth - 1));
+                let dx = heightfield.vertex(x1, z) - heightfield.vertex(x0, z);
+                let dz = heightfield.vertex(x, z1) - heightfield.vertex(x, z0);
+                dz.cross(&dx).normalize()
+            })
+            .collect();
+        heightfield.ranges = (0..depth - 1)
+            .flat_map(|z| (0..width - 1).map(move |x| (x, z)))
+            .map(|(x, z)| {
+                let corners = heightfield.cell_corners(x, z);
+                corners
+                    .iter()
+                    .fold((f32::INFINITY, f32::NEG_INFINITY), |(low, high), p| {
+                        (low.min(p.y), high.max(p.y))
+                    })
+            })
+            .collect();
+        heightfield
+    }
+
+    pub fn map(&self) -> &HeightMap {
+        &self.map
+    }
+
+    /// The number of cells along x and z.
+    fn cells(&self) -> (usize, usize) {
+        (self.map.width - 1, self.map.depth - 1)
+    }
+
+    fn cell_size(&self) -> Vector2<f32> {
+        let (columns, rows) = self.cells();
+        let size = self.bounds.max - self.bounds.min;
+        Vector2::new(size.x / columns as f32, size.z / rows as f32)
+    }
+
+    fn vertex(&self, x: usize, z: usize) -> Point3<f32> {
+        let size = self.bounds.max - self.bounds.min;
+        let cell = self.cell_size();
+        self.bounds.min
+            + Vector3::new(
+                x as f32 * cell.x,
+                self.map.height(x, z) * size.y,
+                z as f32 * cell.y,
+            )
+    }
+
+    fn cell_corners(&self, x: usize, z: usize) -> [Point3<f32>; 4] {
+        [
+            self.vertex(x, z),
+            self.vertex(x + 1, z),
+            self.vertex(x + 1, z + 1),
+            self.vertex(x, z + 1),
+        ]
+    }
+
+    /// Walks the cells under the ray from front to back with a 2D DDA, stopping at the first
+    /// cell the ray hits.
+    fn hit(&self, ray: &Ray) -> Option<SurfaceInteraction> {
+        let (start, end) = self.bounds.intersect(ray)?;
+        let (columns, rows) = self.cells();
+        let cell = self.cell_size();
+        let entry = (ray.source + start * ray.direction) - self.bounds.min;
+
+        let first = |offset: f32, size: f32, count: usize| {
+            ((offset / size).floor().max(0.0) as usize).min(count - 1)
+        };
+        let (mut x, mut z) = (
+            first(entry.x, cell.x, columns),
+            first(entry.z, cell.y, rows),
+        );
+
+        // the distance along the ray to the next cell boundary on each axis, and between them
+        let boundary = |index: usize, offset: f32, size: f32, direction: f32| {
+            if direction > 0.0 {
+                start + ((index + 1) as f32 * size - offset) / direction
+            } else if direction < 0.0 {
+                start + (index as f32 * size - offset) / direction
+            } else {
+                f32::INFINITY
+            }
+        };
+        let mut next_x = boundary(x, entry.x, cell.x, ray.direction.x);
+        let mut next_z = boundary(z, entry.z, cell.y, ray.direction.z);
+        let delta_x = (cell.x / ray.direction.x).abs();
+        let delta_z = (cell.y / ray.direction.z).abs();
+
+        let mut enter = start;
+        loop {
+            let exit = next_x.min(next_z).min(end);
+            let (low, high) = self.ranges[z * columns + x];
+            let y0 = ray.source.y + enter * ray.direction.y;
+            let y1 = ray.source.y + exit * ray.direction.y;
+            let slack = 1e-4 * (high - low).max(1.0);
+            if y0.min(y1) <= high + slack && y0.max(y1) >= low - slack {
+                if let Some(hit) = self.hit_cell(ray, x, z) {
+                    return Some(hit);
+                }
+            }
+
+            if exit >= end {
+                return None;
+            }
+            if next_x < next_z {
+                x = step(x, ray.direction.x, columns)?;
+                enter = next_x;
+                next_x += delta_x;
+            } else {
+                z = step(z, ray.direction.z, rows)?;
+                enter = next_z;
+                next_z += delta_z;
+            }
+        }
+    }
+
+    /// Tests the two triangles of a single cell.
+    fn hit_cell(&self, ray: &Ray, x: usize, z: usize) -> Option<SurfaceInteraction> {
+        let (columns, rows) = self.cells();
+        let [p00, p10, p11, p01] = self.cell_corners(x, z);
+        let normal = |x: usize, z: usize| self.normals[z * self.map.width + x];
+        let (n00, n10, n11, n01) = (
+            normal(x, z),
+            normal(x + 1, z),
+            normal(x + 1, z + 1),
+            normal(x, z + 1),
+        );
+
+        // the triangles share the diagonal from (x, z) to (x + 1, z + 1)
+        let lower = intersect_triangle(ray, &p00, &p10, &p11)
+            .map(|(t, b)| (t, b, (p10 - p00, p11 - p10), (n00, n10, n11)));
+        let upper = intersect_triangle(ray, &p00, &p11, &p01)
+            .map(|(t, b)| (t, b, (p11 - p01, p01 - p00), (n00, n11, n01)));
+        let (t, barycentric, (edge_x, edge_z), (na, nb, nc)) = match (lower, upper) {
+            (Some(l), Some(u)) => {
+                if l.0 <= u.0 {
+                    l
+                } else {
+                    u
+                }
+            }
+            (l, u) => l.or(u)?,
+        };
+
+        // the parameterization runs across the whole grid
+        let point = ray.source + t * ray.direction;
+        let cell = self.cell_size();
+        let uv = Point2::new(
+            ((point.x - self.bounds.min.x) / cell.x / columns as f32).clamp(0.0, 1.0),
+            ((point.z - self.bounds.min.z) / cell.y / rows as f32).clamp(0.0, 1.0),
+        );
+        let dpdu = edge_x * columns as f32;
+        let dpdv = edge_z * rows as f32;
+        let shading =
+            na * (1.0 - barycentric.x - barycentric.y) + nb * barycentric.x + nc * barycentric.y;
+
+        Some(
+            SurfaceInteraction::new(ray, t, dpdv.cross(&dpdu), uv, dpdu, dpdv)
+                .with_shading_normal(shading),
+        )
+    }
+}
+
+/// Moves one cell along an axis in the direction the ray is heading, or gives up when it
+/// leaves the grid.
+fn step(index: usize, direction: f32, count: usize) -> Option<usize> {
+    if direction > 0.0 {
+        Some(index + 1).filter(|&i| i < count)
+    } else {
+        index.checked_sub(1)
+    }
+}
+
+impl Intersectable for Heightfield {
+    fn intersect(&self, ray: &Ray) -> Option<SurfaceInteraction> {
+        self.hit(ray)
+    }
+}
+
+impl Shape for Heightfield {
+    fn bounds(&self) -> Bounds {
+        self.bounds
+    }
+
+    fn material(&self) -> MaterialId {
+        self.material
+    }
+}
+
+impl From<Heightfield> for Geometry {
+    fn from(h: Heightfield) -> Self {
+        Geometry::Heightfield(h)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn terrain(map: HeightMap) -> Heightfield {
+        Heightfield::new(
+            map,
+            Point3::new(-1.0, 0.0, -1.0),
+            Vector3::new(2.0, 1.0, 2.0),
+            MaterialId(0),
+        )
+    }
+
+    #[test]
+    fn test_pgm() {
+        let ascii = b"P2\n# a comment\n3 2\n4\n0 1 2\n3 4 4\n";
+        let map = HeightMap::from_pgm(&ascii[..]).unwrap();
+        assert_eq!((map.width(), map.depth()), (3, 2));
+        assert_eq!(map.height(1, 0), 0.25);
+        assert_eq!(map.height(0, 1), 0.75);
+
+        let mut binary = b"P5 2 2 65535\n".to_vec();
+        binary.extend_from_slice(&[0, 0, 0xff, 0xff, 0x80, 0x00, 0, 0]);
+        let map = HeightMap::from_pgm(&binary[..]).unwrap();
+        assert_eq!(map.height(1, 0), 1.0);
+        assert!((map.height(0, 1) - 0.5).abs() < 1e-4);
+
+        assert!(HeightMap::from_pgm(&b"P5 2 2 255\n\x00"[..]).is_err());
+        assert!(HeightMap::from_pgm(&b"P3 2 2 255\n"[..]).is_err());
+    }
+
+    #[test]
+    fn test_png() {
+        let mut bytes = Vec::new();
+        {
+            let mut encoder = png::Encoder::new(&mut bytes, 2, 2);
+            encoder.set_color(png::ColorType::Rgb);
+            encoder.set_depth(png::BitDepth::Eight);
+            let mut writer = encoder.write_header().unwrap();
+            writer
+                .write_image_data(&[0, 0, 0, 255, 255, 255, 255, 0, 0, 51, 51, 51])
+                .unwrap();
+        }
+        let map = HeightMap::from_png(Cursor::new(bytes)).unwrap();
+        assert_eq!(map.height(0, 0), 0.0);
+        assert_eq!(map.height(1, 0), 1.0);
+        assert!((map.height(0, 1) - 1.0 / 3.0).abs() < 1e-6);
+        assert!((map.height(1, 1) - 0.2).abs() < 1e-6);
+    }
+
+    #[test]
+    fn test_noise() {
+        let map = HeightMap::noise(33, 17, 7, 4);
+        assert_eq!(map, HeightMap::noise(33, 17, 7, 4));
+        assert_ne!(map, HeightMap::noise(33, 17, 8, 4));
+        // every octave up to the maximum adds detail, and the ones after it make no difference
+        assert_eq!(HeightMap::max_noise_octaves(33, 17), 4);
+        assert_eq!(HeightMap::max_noise_octaves(9, 9), 2);
+        assert_eq!(HeightMap::max_noise_octaves(2, 3), 1);
+        for &(width, depth) in &[(33, 17), (9, 9), (2, 3)] {
+            let max = HeightMap::max_noise_octaves(width, depth);
+            let noise = |octaves| HeightMap::noise(width, depth, 7, octaves);
+            assert_ne!(noise(max), noise(max - 1));
+            assert_eq!(noise(max), noise(max + 1));
+        }
+        assert!(map.heights.iter().all(|h| (0.0..=1.0).contains(h)));
+    }
+
+    #[test]
+    fn test_flat() {
+        let field = terrain(HeightMap::new(4, 4, vec![0.5; 16]));
+        let ray = Ray::new(Point3::new(0.3, 5.0, -0.2), Vector3::new(0.0, -1.0, 0.0));
+        let hit = field.intersect(&ray).unwrap();
+        assert!((hit.t - 4.5).abs() < 1e-5);
+        assert!(hit.front_face);
+        assert!((hit.shading_normal - Vector3::new(0.0, 1.0, 0.0)).norm() < 1e-5);
+        assert!((hit.uv - Point2::new(0.65, 0.4)).norm() < 1e-5);
+
+        // passes over the whole field without touching it
+        let ray = Ray::new(Point3::new(-2.0, 0.6, 0.0), Vector3::new(1.0, 0.0, 0.0));
+        assert!(field.intersect(&ray).is_none());
+        // the terrain has no walls, so it can't be hit from the side
+        let ray = Ray::new(Point3::new(-2.0, 0.4, 0.0), Vector3::new(1.0, 0.0, 0.0));
+        assert!(field.intersect(&ray).is_none());
+        let ray = Ray::new(Point3::new(-2.0, 2.0, 0.0), Vector3::new(1.0, -1.0, 0.0));
+        let expected = 1.5 * 2.0_f32.sqrt();
+        assert!((field.intersect(&ray).unwrap().t - expected).abs() < 1e-5);
+    }
+
+    #[test]
+    fn test_smooth_normals() {
+        // a ridge along z: the middle column is raised
+        let heights = (0..9).map(|i| if i % 3 == 1 { 1.0 } else { 0.0 }).collect();
+        let field = terrain(HeightMap::new(3, 3, heights));
+        let ray = Ray::new(Point3::new(-0.5, 5.0, 0.5), Vector3::new(0.0, -1.0, 0.0));
+        let hit = field.intersect(&ray).unwrap();
+        // halfway up the slope, the shading normal is between the flat edge and the peak
+        assert!((hit.t - 4.5).abs() < 1e-5);
+        let geometric = hit.geometric_normal;
+        assert!(hit.shading_normal.y > geometric.y);
+        assert!(hit.shading_normal.x < 0.0);
+    }
+
+    #[test]
+    fn test_matches_brute_force() {
+        let field = terrain(HeightMap::noise(17, 17, 3, 3));
+        let mut rng = StdRng::seed_from_u64(1);
+        for _ in 0..500 {
+            let source = Point3::new(
+                rng.gen_range(-2.0, 2.0),
+                rng.gen_range(0.0, 2.0),
+                rng.gen_range(-2.0, 2.0),
+            );
+            let target = Point3::new(rng.gen_range(-1.0, 1.0), 0.0, rng.gen_range(-1.0, 1.0));
+            let ray = Ray::new(source, target - source);
+
+            let expected = (0..16)
+                .flat_map(|z| (0..16).map(move |x| (x, z)))
+                .filter_map(|(x, z)| field.hit_cell(&ray, x, z))
+                .map(|hit| hit.t)
+                .fold(None, |best: Option<f32>, t| {
+                    Some(best.map_or(t, |b| b.min(t)))
+                });
+            let actual = field.intersect(&ray).map(|hit| hit.t);
+            match (expected, actual) {
+                (Some(e), Some(a)) => assert!((e - a).abs() < 1e-4, "{} != {}", e, a),
+                (None, None) => {}
+                _ => panic!("expected {:?}, got {:?} for {:?}", expected, actual, ray),
+            }
+        }
+    }
+}
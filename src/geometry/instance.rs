@@ -0,0 +1,211 @@
+use super::*;
+use nalgebra::*;
+use std::sync::Arc;
+
+/// Places a shape in the scene through an affine transform, so it can be moved, rotated, or
+/// stretched (e.g. scaling a sphere into an ellipsoid).  The shape itself is shared, so any
+/// number of instances can reuse a single copy of it.
+#[derive(Debug, Clone)]
+pub struct Instance {
+    pub(crate) shape: Arc<Geometry>,
+    pub(crate) transform: Affine3<f32>,
+    pub(crate) inverse: Affine3<f32>,
+    pub(crate) material: Option<MaterialId>,
+}
+
+impl Instance {
+    /// Places `shape` in the scene through `transform`, which maps the shape's own coordinates
+    /// into world space.
+    ///
+    /// # Panics
+    /// Panics if `transform` can't be inverted.
+    pub fn new<G>(shape: G, transform: Affine3<f32>) -> Instance
+    where
+        G: Into<Geometry>,
+    {
+        Instance::shared(Arc::new(shape.into()), transform)
+    }
+
+    /// Places a shape that may also be used by other instances.
+    ///
+    /// # Panics
+    /// Panics if `transform` can't be inverted.
+    pub fn shared(shape: Arc<Geometry>, transform: Affine3<f32>) -> Instance {
+        let inverse = transform
+            .try_inverse()
+            .expect("instance transforms must be invertible");
+        Instance {
+            shape,
+            transform,
+            inverse,
+            material: None,
+        }
+    }
+
+    /// Uses `material` for this instance instead of the shape's own material.
+    pub fn with_material(self, material: MaterialId) -> Instance {
+        Instance {
+            material: Some(material),
+            ..self
+        }
+    }
+
+    pub fn shape(&self) -> &Arc<Geometry> {
+        &self.shape
+    }
+
+    pub fn transform(&self) -> &Affine3<f32> {
+        &self.transform
+    }
+
+    /// Moves `ray` into the shape's coordinate system.  Also returns how much longer the ray's
+    /// direction became, since distances along it are scaled by the same amount.
+    fn to_object_space(&self, ray: &Ray) -> (Ray, f32) {
+        let source = self.inverse.transform_point(&ray.source);
+        let direction = self.inverse.transform_vector(&ray.direction);
+        let scale = direction.norm();
+        let local = Ray::new(source, direction).with_interval(ray.tmin * scale, ray.tmax * scale);
+        (local, scale)
+    }
+
+    /// Moves a normal out of the shape's coordinate system.  Normals have to be transformed by
+    /// the inverse transpose to stay perpendicular to the surface.
+    fn normal_to_world(&self, normal: &Vector3<f32>) -> Vector3<f32> {
+        let inverse = self.inverse.matrix().fixed_slice::<U3, U3>(0, 0);
+        (inverse.transpose() * normal).normalize()
+    }
+}
+
+impl Intersectable for Instance {
+    fn intersect(&self, ray: &Ray) -> Option<SurfaceInteraction> {
+        let (local, scale) = self.to_object_space(ray);
+        let hit = self.shape.intersect(&local)?;
+        // transforms don't change which side of the surface was hit, so `front_face` carries over
+        Some(SurfaceInteraction {
+            t: hit.t / scale,
+            point: self.transform.transform_point(&hit.point),
+            geometric_normal: self.normal_to_world(&hit.geometric_normal),
+            shading_normal: self.normal_to_world(&hit.shading_normal),
+            dpdu: self.transform.transform_vector(&hit.dpdu),
+            dpdv: self.transform.transform_vector(&hit.dpdv),
+            material: self.material.or(hit.material),
+            ..hit
+        })
+    }
+
+    fn occludes(&self, ray: &Ray) -> bool {
+        self.shape.occludes(&self.to_object_space(ray).0)
+    }
+}
+
+impl Shape for Instance {
+    fn bounds(&self) -> Bounds {
+        let bounds = self.shape.bounds();
+        if !bounds.is_finite() {
+            return Bounds::infinite();
+        }
+        let corners = (0..8)
+            .map(|i| {
+                Point3::new(
+                    if i & 1 == 0 {
+                        bounds.min.x
+                    } else {
+                        bounds.max.x
+                    },
+                    if i & 2 == 0 {
+                        bounds.min.y
+                    } else {
+                        bounds.max.y
+                    },
+                    if i & 4 == 0 {
+                        bounds.min.z
+                    } else {
+                        bounds.max.z
+                    },
+                )
+            })
+            .map(|p| self.transform.transform_point(&p))
+            .collect::<Vec<_>>();
+        Bounds::from_points(&corners)
+    }
+
+    fn material(&self) -> MaterialId {
+        self.material.unwrap_or_else(|| self.shape.material())
+    }
+}
+
+impl From<Instance> for Geometry {
+    fn from(instance: Instance) -> Self {
+        Geometry::Instance(instance)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ellipsoid() -> Instance {
+        let sphere = Sphere::new(Point3::new(0.0, 0.0, 0.0), 1.0, MaterialId(0));
+        let transform = Matrix4::new_translation(&Vector3::new(0.0, 0.0, 5.0))
+            * Matrix4::new_nonuniform_scaling(&Vector3::new(2.0, 1.0, 1.0));
+        Instance::new(sphere, Affine3::from_matrix_unchecked(transform))
+    }
+
+    #[test]
+    fn test_scaled_distance() {
+        let instance = ellipsoid();
+        let ray = Ray::new(Point3::new(-5.0, 0.0, 5.0), Vector3::new(1.0, 0.0, 0.0));
+        let hit = instance.intersect(&ray).unwrap();
+        assert!((hit.t - 3.0).abs() < 1e-5);
+        assert!((hit.point - Point3::new(-2.0, 0.0, 5.0)).norm() < 1e-5);
+        assert!((hit.geometric_normal - Vector3::new(-1.0, 0.0, 0.0)).norm() < 1e-5);
+
+        // the ray's interval is in world units
+        assert!(instance
+            .intersect(&ray.clone().with_interval(0.0, 2.5))
+            .is_none());
+        assert!(instance.occludes(&ray));
+    }
+
+    #[test]
+    fn test_normal_inverse_transpose() {
+        let instance = ellipsoid();
+        let x = 2.0_f32.sqrt();
+        let ray = Ray::new(Point3::new(x, 5.0, 5.0), Vector3::new(0.0, -1.0, 0.0));
+        let hit = instance.intersect(&ray).unwrap();
+
+        // the gradient of x²/4 + y² at the hit point
+        let expected = Vector3::new(x / 4.0, 0.5_f32.sqrt(), 0.0).normalize();
+        assert!((hit.geometric_normal - expected).norm() < 1e-4);
+        assert!(hit.front_face);
+    }
+
+    #[test]
+    fn test_shared_shape() {
+        let sphere = Arc::new(Geometry::from(Sphere::new(
+            Point3::new(0.0, 0.0, 0.0),
+            1.0,
+            MaterialId(0),
+        )));
+        let left = Instance::shared(
+            Arc::clone(&sphere),
+            convert(Isometry3::translation(-3.0, 0.0, 0.0)),
+        );
+        let right = Instance::shared(
+            Arc::clone(&sphere),
+            convert(Isometry3::translation(3.0, 0.0, 0.0)),
+        )
+        .with_material(MaterialId(1));
+
+        assert_eq!(left.material(), MaterialId(0));
+        assert_eq!(right.material(), MaterialId(1));
+        assert_eq!(
+            right.bounds(),
+            Bounds::new(Point3::new(2.0, -1.0, -1.0), Point3::new(4.0, 1.0, 1.0))
+        );
+
+        let ray = Ray::new(Point3::new(3.0, 0.0, -5.0), Vector3::new(0.0, 0.0, 1.0));
+        assert!(left.intersect(&ray).is_none());
+        assert!((right.intersect(&ray).unwrap().t - 4.0).abs() < 1e-5);
+    }
+}
@@ -0,0 +1,278 @@
+use super::bvh::Bvh;
+use super::triangle::intersect_triangle;
+use super::*;
+use nalgebra::*;
+
+/// A mesh of triangles, such as one loaded from a model file.  Vertices can carry normals, which
+/// are interpolated across each triangle for smooth shading, and texture coordinates.
+#[derive(Debug)]
+pub struct Mesh {
+    pub(crate) positions: Vec<Point3<f32>>,
+    pub(crate) normals: Option<Vec<Vector3<f32>>>,
+    pub(crate) uvs: Option<Vec<Point2<f32>>>,
+    pub(crate) colors: Option<Vec<Color>>,
+    /// Indices of each triangle's corners in the vertex lists
+    pub(crate) triangles: Vec<[usize; 3]>,
+    pub(crate) material: MaterialId,
+    bvh: Bvh,
+}
+
+impl Mesh {
+    /// Creates a mesh from a list of vertices and the triangles connecting them.
+    ///
+    /// # Panics
+    /// Panics if a triangle refers to a vertex that doesn't exist.
+    pub fn new(
+        positions: Vec<Point3<f32>>,
+        triangles: Vec<[usize; 3]>,
+        material: MaterialId,
+    ) -> Mesh {
+        let bounds = triangles
+            .iter()
+            .map(|triangle| {
+                assert!(
+                    triangle.iter().all(|&i| i < positions.len()),
+                    "triangle refers to a missing vertex"
+                );
+                Bounds::from_points(triangle.iter().map(|&i| &positions[i]))
+            })
+            .collect::<Vec<_>>();
+        Mesh {
+            bvh: Bvh::new(&bounds),
+            positions,
+            normals: None,
+            uvs: None,
+            colors: None,
+            triangles,
+            material,
+        }
+    }
+
+    /// Adds a normal to each vertex.
+    pub fn with_normals(self, normals: Vec<Vector3<f32>>) -> Mesh {
+        assert_eq!(normals.len(), self.positions.len(), "one normal per vertex");
+        Mesh {
+            normals: Some(normals),
+            ..self
+        }
+    }
+
+    /// Adds texture coordinates to each vertex.
+    pub fn with_uvs(self, uvs: Vec<Point2<f32>>) -> Mesh {
+        assert_eq!(uvs.len(), self.positions.len(), "one uv per vertex");
+        Mesh {
+            uvs: Some(uvs),
+            ..self
+        }
+    }
+
+    /// Adds a color to each vertex, which materials can use through `ColorSource::Vertex`.
+    pub fn with_colors(self, colors: Vec<Color>) -> Mesh {
+        assert_eq!(colors.len(), self.positions.len(), "one color per vertex");
+        Mesh {
+            colors: Some(colors),
+            ..self
+        }
+    }
+
+    pub fn vertex_count(&self) -> usize {
+        self.positions.len()
+    }
+
+    pub fn triangle_count(&self) -> usize {
+        self.triangles.len()
+    }
+
+    /// Finds the closest triangle hit by the ray, along with the barycentric coordinates of the
+    /// hit point.
+    fn hit(&self, ray: &Ray) -> Option<(f32, usize, Point2<f32>)> {
+        self.bvh
+            .closest(ray, |i, ray| {
+                let [a, b, c] = self.triangles[i];
+                let p = &self.positions;
+                intersect_triangle(ray, &p[a], &p[b], &p[c]).map(|(t, uv)| (t, (i, uv)))
+            })
+            .map(|(t, (i, barycentric))| (t, i, barycentric))
+    }
+
+    fn surface_interaction(
+        &self,
+        ray: &Ray,
+        t: f32,
+        triangle: usize,
+        barycentric: Point2<f32>,
+    ) -> SurfaceInteraction {
+        let [a, b, c] = self.triangles[triangle];
+        let (pa, pb, pc) = (self.positions[a], self.positions[b], self.positions[c]);
+        let weights = [
+            1.0 - barycentric.x - barycentric.y,
+            barycentric.x,
+            barycentric.y,
+        ];
+
+        // interpolated normals say which side is outside, since winding orders vary
+        let shading = self
+            .normals
+            .as_ref()
+            .map(|n| n[a] * weights[0] + n[b] * weights[1] + n[c] * weights[2])
+            .filter(|n| n.norm_squared() > 0.0);
+        let mut normal = (pb - pa).cross(&(pc - pa));
+        if shading.is_some_and(|s| s.dot(&normal) < 0.0) {
+            normal = -normal;
+        }
+
+        let (uv, dpdu, dpdv) = match &self.uvs {
+            Some(uvs) => {
+                let (ta, tb, tc) = (uvs[a], uvs[b], uvs[c]);
+                let uv = Point2::from(
+                    ta.coords * weights[0] + tb.coords * weights[1] + tc.coords * weights[2],
+                );
+                // solve for the tangents from how the uvs change along two of the edges
+                let (duv1, duv2) = (tb - ta, tc - ta);
+                let determinant = duv1.x * duv2.y - duv1.y * duv2.x;
+                if determinant.abs() > 1e-12 {
+                    let (dp1, dp2) = (pb - pa, pc - pa);
+                    let dpdu = (dp1 * duv2.y - dp2 * duv1.y) / determinant;
+                    let dpdv = (dp2 * duv1.x - dp1 * duv2.x) / determinant;
+                    (uv, dpdu, dpdv)
+                } else {
+                    let (dpdu, dpdv) = coordinate_system(&normal.normalize());
+                    (uv, dpdu, dpdv)
+                }
+            }
+            None => (barycentric, pb - pa, pc - pa),
+        };
+
+        let mut surface = SurfaceInteraction::new(ray, t, normal, uv, dpdu, dpdv);
+        surface.color = self
+            .colors
+            .as_ref()
+            .map(|colors| colors[a] * weights[0] + colors[b] * weights[1] + colors[c] * weights[2]);
+        match shading {
+            Some(shading) => surface.with_shading_normal(shading),
+            None => surface,
+        }
+    }
+}
+
+impl Intersectable for Mesh {
+    fn intersect(&self, ray: &Ray) -> Option<SurfaceInteraction> {
+        let (t, triangle, barycentric) = self.hit(ray)?;
+        Some(self.surface_interaction(ray, t, triangle, barycentric))
+    }
+
+    fn occludes(&self, ray: &Ray) -> bool {
+        self.bvh.any(ray, |i, ray| {
+            let [a, b, c] = self.triangles[i];
+            let p = &self.positions;
+            intersect_triangle(ray, &p[a], &p[b], &p[c]).is_some()
+        })
+    }
+}
+
+impl Shape for Mesh {
+    fn bounds(&self) -> Bounds {
+        // an empty mesh can't be hit, so any empty box will do
+        self.bvh.bounds().unwrap_or_else(|| {
+            let origin = Point3::new(0.0, 0.0, 0.0);
+            Bounds::new(origin, origin)
+        })
+    }
+
+    fn material(&self) -> MaterialId {
+        self.material
+    }
+}
+
+impl From<Mesh> for Geometry {
+    fn from(mesh: Mesh) -> Self {
+        Geometry::Mesh(mesh)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A unit square in the z = 5 plane, split along its diagonal.
+    fn square() -> Mesh {
+        let positions = vec![
+            Point3::new(0.0, 0.0, 5.0),
+            Point3::new(1.0, 0.0, 5.0),
+            Point3::new(1.0, 1.0, 5.0),
+            Point3::new(0.0, 1.0, 5.0),
+        ];
+        Mesh::new(positions, vec![[0, 1, 2], [0, 2, 3]], MaterialId(0))
+    }
+
+    #[test]
+    fn test_intersect() {
+        let mesh = square();
+        assert_eq!(mesh.triangle_count(), 2);
+        let ray = Ray::new(Point3::new(0.25, 0.75, 0.0), Vector3::new(0.0, 0.0, 1.0));
+        let hit = mesh.intersect(&ray).unwrap();
+        assert!((hit.t - 5.0).abs() < 1e-6);
+        // without normals, the winding order decides which side is outside
+        assert!((hit.geometric_normal - Vector3::new(0.0, 0.0, 1.0)).norm() < 1e-6);
+        assert!(!hit.front_face);
+
+        let miss = Ray::new(Point3::new(1.25, 0.75, 0.0), Vector3::new(0.0, 0.0, 1.0));
+        assert!(mesh.intersect(&miss).is_none());
+        assert!(!mesh.occludes(&miss));
+        assert!(mesh.occludes(&ray));
+    }
+
+    #[test]
+    fn test_interpolation() {
+        // normals tilted away from the center, like a curved surface
+        let normals = vec![
+            Vector3::new(-1.0, -1.0, -2.0),
+            Vector3::new(1.0, -1.0, -2.0),
+            Vector3::new(1.0, 1.0, -2.0),
+            Vector3::new(-1.0, 1.0, -2.0),
+        ];
+        let uvs = vec![
+            Point2::new(0.0, 1.0),
+            Point2::new(1.0, 1.0),
+            Point2::new(1.0, 0.0),
+            Point2::new(0.0, 0.0),
+        ];
+        let mesh = square().with_normals(normals).with_uvs(uvs);
+        let ray = Ray::new(Point3::new(0.5, 0.5, 0.0), Vector3::new(0.0, 0.0, 1.0));
+        let hit = mesh.intersect(&ray).unwrap();
+
+        // the normals flip the geometric normal to face the camera
+        assert!(hit.front_face);
+        assert!((hit.shading_normal - Vector3::new(0.0, 0.0, -1.0)).norm() < 1e-6);
+        assert!((hit.uv - Point2::new(0.5, 0.5)).norm() < 1e-6);
+        assert!((hit.dpdu - Vector3::new(1.0, 0.0, 0.0)).norm() < 1e-6);
+        assert!((hit.dpdv - Vector3::new(0.0, -1.0, 0.0)).norm() < 1e-6);
+
+        let ray = Ray::new(Point3::new(0.1, 0.1, 0.0), Vector3::new(0.0, 0.0, 1.0));
+        let hit = mesh.intersect(&ray).unwrap();
+        assert!(hit.shading_normal.x < 0.0 && hit.shading_normal.y < 0.0);
+    }
+
+    #[test]
+    fn test_many_triangles() {
+        // a strip of squares, one after another along x
+        let mut positions = Vec::new();
+        let mut triangles = Vec::new();
+        for i in 0..100 {
+            let x = i as f32;
+            let base = positions.len();
+            positions.extend_from_slice(&[
+                Point3::new(x, 0.0, x),
+                Point3::new(x + 1.0, 0.0, x),
+                Point3::new(x + 1.0, 1.0, x),
+                Point3::new(x, 1.0, x),
+            ]);
+            triangles.push([base, base + 1, base + 2]);
+            triangles.push([base, base + 2, base + 3]);
+        }
+        let mesh = Mesh::new(positions, triangles, MaterialId(0));
+        assert_eq!(mesh.bounds().max, Point3::new(100.0, 1.0, 99.0));
+        let ray = Ray::new(Point3::new(42.5, 0.5, -1.0), Vector3::new(0.0, 0.0, 1.0));
+        assert!((mesh.intersect(&ray).unwrap().t - 43.0).abs() < 1e-4);
+    }
+}
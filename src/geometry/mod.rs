@@ -1,39 +1,166 @@
-/// All various kinds of scene geometry: Spheres, Planes, etc.
+//! All various kinds of scene geometry: Spheres, Planes, etc.
 
+mod bounds;
+mod bvh;
+mod cone;
+mod csg;
+mod cuboid;
+mod cylinder;
+mod disk;
+mod heightfield;
+mod instance;
+mod mesh;
 mod plane;
+mod quad;
+mod roots;
+mod sdf;
 mod sphere;
+mod torus;
+mod triangle;
 
 use crate::prelude::*;
 use nalgebra::*;
+use std::fmt::Debug;
+
+pub use bounds::*;
+pub use cone::*;
+pub use csg::*;
+pub use cuboid::*;
+pub use cylinder::*;
+pub use disk::*;
+pub use heightfield::*;
+pub use instance::*;
+pub use mesh::*;
 pub use plane::*;
+pub use quad::*;
+pub use sdf::*;
 pub use sphere::*;
+pub use torus::*;
 
 pub trait Intersectable {
-    /// Determines whether the ray will intersect the given object
-    fn intersect(&self, ray: &Ray) -> Option<f32>;
-    fn surface_normal(&self, hit_point: &Point3<f32>) -> Vector3<f32>;
+    /// Determines whether the ray will intersect the given object, and if so, describes the
+    /// surface at the closest hit.
+    fn intersect(&self, ray: &Ray) -> Option<SurfaceInteraction>;
+
+    /// Determines whether the ray hits the object at all.  Shapes can override this when they
+    /// can answer more cheaply than building a full `SurfaceInteraction`.
+    fn occludes(&self, ray: &Ray) -> bool {
+        self.intersect(ray).is_some()
+    }
+}
+
+/// A complete primitive that can be placed in a scene.  Implement this to add new kinds of
+/// geometry from outside the crate, and wrap them with `Geometry::custom`.
+pub trait Shape: Intersectable + Debug {
+    /// A box containing the whole shape.  Rays that miss it are never tested against the shape.
+    fn bounds(&self) -> Bounds;
+
+    /// The material the shape's surface is made of.
+    fn material(&self) -> MaterialId;
+}
+
+/// Describes the surface at the point a ray hit it.
+#[derive(Debug, Clone)]
+pub struct SurfaceInteraction {
+    /// Distance along the ray to the hit point
+    pub t: f32,
+    pub point: Point3<f32>,
+    /// The normal of the underlying geometry, always pointing out of the surface
+    pub geometric_normal: Vector3<f32>,
+    /// The normal used for shading, flipped to face the side the ray came from
+    pub shading_normal: Vector3<f32>,
+    /// Surface parameterization at the hit point
+    pub uv: Point2<f32>,
+    /// Partial derivative of the hit point with respect to `u`
+    pub dpdu: Vector3<f32>,
+    /// Partial derivative of the hit point with respect to `v`
+    pub dpdv: Vector3<f32>,
+    /// Whether the ray hit the outside of the surface
+    pub front_face: bool,
+    /// The material at the hit point, for shapes made of several materials.  When this is
+    /// `None`, the shape's own material is used.
+    pub material: Option<MaterialId>,
+    /// The color interpolated from the vertices of a mesh, for meshes that have vertex colors
+    pub color: Option<Color>,
+}
+
+impl SurfaceInteraction {
+    /// Builds the hit record for a ray that hit a surface at distance `t`.  `normal` must point
+    /// out of the surface; the shading normal and `front_face` are derived from it.
+    pub fn new(
+        ray: &Ray,
+        t: f32,
+        normal: Vector3<f32>,
+        uv: Point2<f32>,
+        dpdu: Vector3<f32>,
+        dpdv: Vector3<f32>,
+    ) -> SurfaceInteraction {
+        let normal = normal.normalize();
+        let front_face = ray.direction.dot(&normal) < 0.0;
+        SurfaceInteraction {
+            t,
+            point: ray.source + t * ray.direction,
+            geometric_normal: normal,
+            shading_normal: if front_face { normal } else { -normal },
+            uv,
+            dpdu,
+            dpdv,
+            front_face,
+            material: None,
+            color: None,
+        }
+    }
+
+    /// Creates a ray leaving the surface in `direction`.  The origin is pushed off the surface
+    /// on the side the ray is heading, so that it can't hit the surface it starts on.
+    pub fn spawn_ray(&self, direction: Vector3<f32>) -> Ray {
+        Ray::new(self.offset_origin(&direction), direction)
+    }
+
+    /// Creates a ray from the surface to `target` that doesn't count hits at or past `target`.
+    pub fn spawn_ray_to(&self, target: Point3<f32>) -> Ray {
+        Ray::segment(self.offset_origin(&(target - self.point)), target)
+    }
+
+    /// Replaces the shading normal, for shapes that interpolate normals across their surface.
+    /// Like the geometric normal, `normal` must point out of the surface.
+    pub fn with_shading_normal(self, normal: Vector3<f32>) -> SurfaceInteraction {
+        let normal = normal.normalize();
+        SurfaceInteraction {
+            shading_normal: if self.front_face { normal } else { -normal },
+            ..self
+        }
+    }
+
+    fn offset_origin(&self, direction: &Vector3<f32>) -> Point3<f32> {
+        let magnitude = self.point.coords.amax().max(1.0);
+        let offset = self.geometric_normal * (Ray::ORIGIN_EPSILON * magnitude);
+        if direction.dot(&self.geometric_normal) < 0.0 {
+            self.point - offset
+        } else {
+            self.point + offset
+        }
+    }
 }
 
 #[derive(Debug)]
 pub struct Intersection<'a> {
-    pub(crate) dist: f32,
-    pub(crate) point: Point3<f32>,
+    pub(crate) surface: SurfaceInteraction,
     pub(crate) elem: &'a Geometry,
     pub(crate) incoming: &'a Ray,
 }
 
 impl<'a> Intersection<'a> {
-    pub fn new(dist: f32, ray: &'a Ray, elem: &'a Geometry) -> Intersection<'a> {
+    pub fn new(surface: SurfaceInteraction, ray: &'a Ray, elem: &'a Geometry) -> Intersection<'a> {
         Intersection {
-            dist,
-            point: ray.source + dist * ray.direction,
+            surface,
             elem,
             incoming: ray,
         }
     }
 
     pub fn surface_normal(&self) -> Vector3<f32> {
-        self.elem.surface_normal(&self.point)
+        self.surface.shading_normal
     }
 }
 
@@ -41,29 +168,178 @@ impl<'a> Intersection<'a> {
 pub enum Geometry {
     Sphere(Sphere),
     Plane(Plane),
+    Cuboid(Cuboid),
+    Cylinder(Cylinder),
+    Cone(Cone),
+    Disk(Disk),
+    Quad(Quad),
+    Torus(Torus),
+    Csg(Csg),
+    Sdf(SdfShape),
+    Heightfield(Heightfield),
+    Mesh(Mesh),
+    Instance(Instance),
+    /// A user-defined shape.
+    Custom(Box<dyn Shape + Send + Sync>),
 }
 
-impl Colorable for Geometry {
-    fn color(&self, scene: &Scene, i: &Intersection, tracing_depth: u32) -> Color {
+impl Geometry {
+    /// Wraps a user-defined shape so it can be added to a scene.
+    pub fn custom<S>(shape: S) -> Geometry
+    where
+        S: Shape + Send + Sync + 'static,
+    {
+        Geometry::Custom(Box::new(shape))
+    }
+}
+
+impl Shape for Geometry {
+    fn bounds(&self) -> Bounds {
+        match self {
+            Geometry::Sphere(s) => s.bounds(),
+            Geometry::Plane(p) => p.bounds(),
+            Geometry::Cuboid(c) => c.bounds(),
+            Geometry::Cylinder(c) => c.bounds(),
+            Geometry::Cone(c) => c.bounds(),
+            Geometry::Disk(d) => d.bounds(),
+            Geometry::Quad(q) => q.bounds(),
+            Geometry::Torus(t) => t.bounds(),
+            Geometry::Csg(c) => c.bounds(),
+            Geometry::Sdf(s) => s.bounds(),
+            Geometry::Heightfield(h) => h.bounds(),
+            Geometry::Mesh(m) => m.bounds(),
+            Geometry::Instance(i) => i.bounds(),
+            Geometry::Custom(c) => c.bounds(),
+        }
+    }
+
+    fn material(&self) -> MaterialId {
         match self {
-            Geometry::Sphere(s) => s.color(scene, i, tracing_depth),
-            Geometry::Plane(p) => p.color(scene, i, tracing_depth),
+            Geometry::Sphere(s) => s.material(),
+            Geometry::Plane(p) => p.material(),
+            Geometry::Cuboid(c) => c.material(),
+            Geometry::Cylinder(c) => c.material(),
+            Geometry::Cone(c) => c.material(),
+            Geometry::Disk(d) => d.material(),
+            Geometry::Quad(q) => q.material(),
+            Geometry::Torus(t) => t.material(),
+            Geometry::Csg(c) => c.material(),
+            Geometry::Sdf(s) => s.material(),
+            Geometry::Heightfield(h) => h.material(),
+            Geometry::Mesh(m) => m.material(),
+            Geometry::Instance(i) => i.material(),
+            Geometry::Custom(c) => c.material(),
         }
     }
 }
 
 impl Intersectable for Geometry {
-    fn intersect(&self, ray: &Ray) -> Option<f32> {
+    fn intersect(&self, ray: &Ray) -> Option<SurfaceInteraction> {
         match self {
             Geometry::Sphere(sphere) => sphere.intersect(ray),
             Geometry::Plane(plane) => plane.intersect(ray),
+            Geometry::Cuboid(cuboid) => cuboid.intersect(ray),
+            Geometry::Cylinder(cylinder) => cylinder.intersect(ray),
+            Geometry::Cone(cone) => cone.intersect(ray),
+            Geometry::Disk(disk) => disk.intersect(ray),
+            Geometry::Quad(quad) => quad.intersect(ray),
+            Geometry::Torus(torus) => torus.intersect(ray),
+            Geometry::Csg(csg) => csg.intersect(ray),
+            Geometry::Sdf(sdf) => sdf.intersect(ray),
+            Geometry::Heightfield(h) => h.intersect(ray),
+            Geometry::Mesh(mesh) => mesh.intersect(ray),
+            Geometry::Instance(instance) => instance.intersect(ray),
+            Geometry::Custom(shape) => {
+                shape.bounds().intersect(ray)?;
+                shape.intersect(ray)
+            }
         }
     }
 
-    fn surface_normal(&self, hit_point: &Point3<f32>) -> Vector3<f32> {
+    fn occludes(&self, ray: &Ray) -> bool {
         match self {
-            Geometry::Sphere(s) => s.surface_normal(hit_point),
-            Geometry::Plane(p) => p.surface_normal(hit_point),
+            Geometry::Sphere(sphere) => sphere.occludes(ray),
+            Geometry::Plane(plane) => plane.occludes(ray),
+            Geometry::Cuboid(cuboid) => cuboid.occludes(ray),
+            Geometry::Cylinder(cylinder) => cylinder.occludes(ray),
+            Geometry::Cone(cone) => cone.occludes(ray),
+            Geometry::Disk(disk) => disk.occludes(ray),
+            Geometry::Quad(quad) => quad.occludes(ray),
+            Geometry::Torus(torus) => torus.occludes(ray),
+            Geometry::Csg(csg) => csg.occludes(ray),
+            Geometry::Sdf(sdf) => sdf.occludes(ray),
+            Geometry::Heightfield(h) => h.occludes(ray),
+            Geometry::Mesh(mesh) => mesh.occludes(ray),
+            Geometry::Instance(instance) => instance.occludes(ray),
+            Geometry::Custom(shape) => {
+                shape.bounds().intersect(ray).is_some() && shape.occludes(ray)
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A square lying in the plane z = `z`, built only from the public API.
+    #[derive(Debug)]
+    struct Square {
+        z: f32,
+        half_size: f32,
+        material: MaterialId,
+    }
+
+    impl Intersectable for Square {
+        fn intersect(&self, ray: &Ray) -> Option<SurfaceInteraction> {
+            let t = (self.z - ray.source().z) / ray.direction().z;
+            let point = ray.source() + t * ray.direction();
+            if !ray.contains(t) || point.x.abs() > self.half_size || point.y.abs() > self.half_size
+            {
+                return None;
+            }
+            Some(SurfaceInteraction::new(
+                ray,
+                t,
+                Vector3::new(0.0, 0.0, -1.0),
+                Point2::new(point.x, point.y),
+                Vector3::new(1.0, 0.0, 0.0),
+                Vector3::new(0.0, 1.0, 0.0),
+            ))
+        }
+    }
+
+    impl Shape for Square {
+        fn bounds(&self) -> Bounds {
+            Bounds::new(
+                Point3::new(-self.half_size, -self.half_size, self.z),
+                Point3::new(self.half_size, self.half_size, self.z),
+            )
         }
+
+        fn material(&self) -> MaterialId {
+            self.material
+        }
+    }
+
+    #[test]
+    fn test_custom_shape() {
+        let mut scene = Scene::new(4, 3, 90.0, 1, Color::default());
+        let material = scene.add_material(Diffuse::new(Color::new(1.0, 1.0, 1.0), 1.0));
+        scene.add_geometry(Geometry::custom(Square {
+            z: 2.0,
+            half_size: 1.0,
+            material,
+        }));
+
+        let ray = Ray::new(Point3::new(0.5, 0.5, 0.0), Vector3::new(0.0, 0.0, 1.0));
+        let hit = scene.trace(&ray, 0).unwrap();
+        assert_eq!(hit.surface.t, 2.0);
+        assert!(hit.surface.front_face);
+        assert!(matches!(hit.elem, Geometry::Custom(_)));
+
+        let miss = Ray::new(Point3::new(2.0, 0.0, 0.0), Vector3::new(0.0, 0.0, 1.0));
+        assert!(scene.trace(&miss, 0).is_none());
+        assert!(!scene.occluded(&miss));
     }
 }
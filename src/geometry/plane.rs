@@ -6,12 +6,12 @@ use nalgebra::*;
 pub struct Plane {
     pub(crate) vertex: Point3<f32>,
     pub(crate) normal: Vector3<f32>,
-    pub(crate) material: Material,
+    pub(crate) material: MaterialId,
 }
 
 impl Plane {
-    pub fn new(vertex: Point3<f32>, normal: Vector3<f32>, material: Material) -> Plane {
-        let normal = normal.normalize();
+    pub fn new(vertex: Point3<f32>, normal: Vector3<f32>, material: MaterialId) -> Plane {
+        let normal = unit_vector(&normal);
         Plane {
             vertex,
             normal,
@@ -21,7 +21,7 @@ impl Plane {
 }
 
 impl Intersectable for Plane {
-    fn intersect(&self, ray: &Ray) -> Option<f32> {
+    fn intersect(&self, ray: &Ray) -> Option<SurfaceInteraction> {
         let normal = &self.normal;
         let dot = normal.dot(&ray.direction);
 
@@ -34,21 +34,27 @@ impl Intersectable for Plane {
         let v = self.vertex - ray.source;
         let distance = v.dot(normal) / dot;
 
-        if distance > 0.0 {
-            Some(distance)
+        if distance > 0.0 && ray.contains(distance) {
+            // parameterize the plane by projecting onto a pair of axes lying in it
+            let (u_axis, v_axis) = coordinate_system(normal);
+            let offset = (ray.source + distance * ray.direction) - self.vertex;
+            let uv = Point2::new(offset.dot(&u_axis), offset.dot(&v_axis));
+            Some(SurfaceInteraction::new(
+                ray, distance, *normal, uv, u_axis, v_axis,
+            ))
         } else {
             None
         }
     }
+}
 
-    fn surface_normal(&self, _: &Point3<f32>) -> Vector3<f32> {
-        self.normal
+impl Shape for Plane {
+    fn bounds(&self) -> Bounds {
+        Bounds::infinite()
     }
-}
 
-impl Colorable for Plane {
-    fn color(&self, scene: &Scene, i: &Intersection, depth: u32) -> Color {
-        self.material.color(scene, i, depth)
+    fn material(&self) -> MaterialId {
+        self.material
     }
 }
 
@@ -66,12 +72,9 @@ mod tests {
         let p = Plane::new(
             Point3::new(0.0, 0.0, 1.0),
             Vector3::new(1.0, 0.0, 0.0),
-            Diffuse::new(Color::default(), 1.0).into(),
+            MaterialId(0),
         );
-        let ray = Ray {
-            source: Point3::new(0.0, 0.0, 0.0),
-            direction: Vector3::new(0.0, 0.0, 1.0),
-        };
+        let ray = Ray::new(Point3::new(0.0, 0.0, 0.0), Vector3::new(0.0, 0.0, 1.0));
 
         assert!(p.intersect(&ray).is_none());
     }
@@ -81,12 +84,9 @@ mod tests {
         let p = Plane::new(
             Point3::new(1.0, 0.0, 0.0),
             Vector3::new(1.0, 0.0, 0.0),
-            Diffuse::new(Color::default(), 1.0).into(),
+            MaterialId(0),
         );
-        let ray = Ray {
-            source: Point3::new(0.0, 0.0, 0.0),
-            direction: Vector3::new(-1.0, 0.0, 0.0),
-        };
+        let ray = Ray::new(Point3::new(0.0, 0.0, 0.0), Vector3::new(-1.0, 0.0, 0.0));
 
         assert!(p.intersect(&ray).is_none());
     }
@@ -96,14 +96,16 @@ mod tests {
         let p = Plane::new(
             Point3::new(1.0, 0.0, 0.0),
             Vector3::new(-1.0, 0.0, 0.0),
-            Diffuse::new(Color::default(), 1.0).into(),
+            MaterialId(0),
         );
-        let ray = Ray {
-            source: Point3::new(0.0, 0.0, 0.0),
-            direction: Vector3::new(1.0, 0.0, 0.0),
-        };
-
-        assert_eq!(p.intersect(&ray).unwrap(), 1.0)
+        let ray = Ray::new(Point3::new(0.0, 0.0, 0.0), Vector3::new(1.0, 0.0, 0.0));
+
+        let hit = p.intersect(&ray).unwrap();
+        assert_eq!(hit.t, 1.0);
+        assert_eq!(hit.point, Point3::new(1.0, 0.0, 0.0));
+        assert!(hit.front_face);
+        assert_eq!(hit.uv, Point2::new(0.0, 0.0));
+        assert!((hit.dpdu.cross(&hit.dpdv) - hit.geometric_normal).norm() < 1e-6);
     }
 
     #[test]
@@ -111,12 +113,9 @@ mod tests {
         let p = Plane::new(
             Point3::new(0.0, -1.0, -10.0),
             Vector3::new(0.0, 1.0, 0.0),
-            Diffuse::new(Color::default(), 1.0).into(),
+            MaterialId(0),
         );
-        let ray = Ray {
-            source: Point3::new(0.0, 0.0, 0.0),
-            direction: Vector3::new(0.0, -1.0, 10.0).normalize(),
-        };
+        let ray = Ray::new(Point3::new(0.0, 0.0, 0.0), Vector3::new(0.0, -1.0, 10.0));
         let result = p.intersect(&ray);
 
         assert!(result.is_some());
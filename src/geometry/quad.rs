@@ -0,0 +1,132 @@
+use super::*;
+use nalgebra::*;
+
+/// A flat parallelogram, spanned by two edges leaving one of its corners.  Can be hit from
+/// either side.
+#[derive(Debug)]
+pub struct Quad {
+    pub(crate) corner: Point3<f32>,
+    pub(crate) u: Vector3<f32>,
+    pub(crate) v: Vector3<f32>,
+    pub(crate) material: MaterialId,
+}
+
+impl Quad {
+    pub fn new(
+        corner: Point3<f32>,
+        u: Vector3<f32>,
+        v: Vector3<f32>,
+        material: MaterialId,
+    ) -> Quad {
+        Quad {
+            corner,
+            u,
+            v,
+            material,
+        }
+    }
+
+    /// Finds the distance to the quad, along with the hit point's coordinates along each edge.
+    fn hit(&self, ray: &Ray) -> Option<(f32, Point2<f32>)> {
+        let normal = self.u.cross(&self.v);
+        let dot = normal.dot(&ray.direction);
+        if dot.abs() < 1e-9 {
+            return None;
+        }
+        let t = (self.corner - ray.source).dot(&normal) / dot;
+        if !ray.contains(t) {
+            return None;
+        }
+
+        // express the hit point in terms of the two edges
+        let offset = (ray.source + t * ray.direction) - self.corner;
+        let w = normal / normal.norm_squared();
+        let alpha = w.dot(&offset.cross(&self.v));
+        let beta = w.dot(&self.u.cross(&offset));
+        if (0.0..=1.0).contains(&alpha) && (0.0..=1.0).contains(&beta) {
+            Some((t, Point2::new(alpha, beta)))
+        } else {
+            None
+        }
+    }
+}
+
+impl Intersectable for Quad {
+    fn intersect(&self, ray: &Ray) -> Option<SurfaceInteraction> {
+        let (t, uv) = self.hit(ray)?;
+        Some(SurfaceInteraction::new(
+            ray,
+            t,
+            self.u.cross(&self.v),
+            uv,
+            self.u,
+            self.v,
+        ))
+    }
+
+    fn occludes(&self, ray: &Ray) -> bool {
+        self.hit(ray).is_some()
+    }
+}
+
+impl Shape for Quad {
+    fn bounds(&self) -> Bounds {
+        Bounds::from_points(&[
+            self.corner,
+            self.corner + self.u,
+            self.corner + self.v,
+            self.corner + self.u + self.v,
+        ])
+    }
+
+    fn material(&self) -> MaterialId {
+        self.material
+    }
+}
+
+impl From<Quad> for Geometry {
+    fn from(quad: Quad) -> Self {
+        Geometry::Quad(quad)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn quad() -> Quad {
+        Quad::new(
+            Point3::new(-1.0, -1.0, 5.0),
+            Vector3::new(4.0, 0.0, 0.0),
+            Vector3::new(0.0, 2.0, 0.0),
+            MaterialId(0),
+        )
+    }
+
+    #[test]
+    fn test_quad_hit() {
+        let ray = Ray::new(Point3::new(0.0, 0.0, 0.0), Vector3::new(0.0, 0.0, 1.0));
+        let hit = quad().intersect(&ray).unwrap();
+        assert!((hit.t - 5.0).abs() < 1e-6);
+        assert!((hit.uv - Point2::new(0.25, 0.5)).norm() < 1e-6);
+        // the edges wind so the normal faces away from the ray
+        assert!(!hit.front_face);
+        assert_eq!(hit.geometric_normal, Vector3::new(0.0, 0.0, 1.0));
+        assert_eq!(hit.shading_normal, Vector3::new(0.0, 0.0, -1.0));
+    }
+
+    #[test]
+    fn test_quad_miss() {
+        let ray = Ray::new(Point3::new(3.5, 0.0, 0.0), Vector3::new(0.0, 0.0, 1.0));
+        assert!(quad().intersect(&ray).is_none());
+        let ray = Ray::new(Point3::new(0.0, 1.5, 0.0), Vector3::new(0.0, 0.0, 1.0));
+        assert!(quad().intersect(&ray).is_none());
+    }
+
+    #[test]
+    fn test_quad_bounds() {
+        let bounds = quad().bounds();
+        assert_eq!(bounds.min, Point3::new(-1.0, -1.0, 5.0));
+        assert_eq!(bounds.max, Point3::new(3.0, 1.0, 5.0));
+    }
+}
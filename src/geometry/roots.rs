@@ -0,0 +1,149 @@
+//! Real roots of low order polynomials, for intersecting rays with implicit surfaces.  Work is
+//! done in `f64`, since the quartic in particular loses a lot of precision along the way.
+
+/// Finds the real roots of `a x² + b x + c`, in ascending order.
+pub(crate) fn solve_quadratic(a: f64, b: f64, c: f64) -> Vec<f64> {
+    if a.abs() < 1e-12 {
+        return if b.abs() < 1e-12 {
+            Vec::new()
+        } else {
+            vec![-c / b]
+        };
+    }
+    let discriminant = b * b - 4.0 * a * c;
+    if discriminant < 0.0 {
+        return Vec::new();
+    }
+    // avoid the cancellation in the textbook formula by never subtracting similar values
+    let q = -0.5 * (b + b.signum() * discriminant.sqrt());
+    let (x0, x1) = if q == 0.0 { (0.0, 0.0) } else { (q / a, c / q) };
+    if x0 < x1 {
+        vec![x0, x1]
+    } else {
+        vec![x1, x0]
+    }
+}
+
+/// Finds the real roots of `x³ + a x² + b x + c`, in ascending order.
+pub(crate) fn solve_cubic(a: f64, b: f64, c: f64) -> Vec<f64> {
+    use std::f64::consts::PI;
+
+    // substitute x = y - a/3 to get the depressed cubic y³ + p y + q
+    let shift = a / 3.0;
+    let p = b - a * a / 3.0;
+    let q = 2.0 * a * a * a / 27.0 - a * b / 3.0 + c;
+
+    let mut roots = if p.abs() < 1e-12 {
+        vec![(-q).cbrt()]
+    } else {
+        let discriminant = q * q / 4.0 + p * p * p / 27.0;
+        if discriminant > 0.0 {
+            // one real root (Cardano)
+            let sqrt = discriminant.sqrt();
+            vec![(-q / 2.0 + sqrt).cbrt() + (-q / 2.0 - sqrt).cbrt()]
+        } else {
+            // three real roots (trigonometric method)
+            let r = (-p / 3.0).sqrt();
+            let phi = (3.0 * q / (2.0 * p * r)).clamp(-1.0, 1.0).acos() / 3.0;
+            (0..3)
+                .map(|k| 2.0 * r * (phi - 2.0 * PI * k as f64 / 3.0).cos())
+                .collect()
+        }
+    };
+    for root in roots.iter_mut() {
+        *root -= shift;
+    }
+    roots.sort_by(|x, y| x.partial_cmp(y).unwrap());
+    roots
+}
+
+/// Finds the real roots of `x⁴ + a x³ + b x² + c x + d`, in ascending order, using Ferrari's
+/// method.
+pub(crate) fn solve_quartic(a: f64, b: f64, c: f64, d: f64) -> Vec<f64> {
+    // substitute x = y - a/4 to get the depressed quartic y⁴ + p y² + q y + r
+    let shift = a / 4.0;
+    let a2 = a * a;
+    let p = b - 3.0 * a2 / 8.0;
+    let q = c - a * b / 2.0 + a2 * a / 8.0;
+    let r = d - a * c / 4.0 + a2 * b / 16.0 - 3.0 * a2 * a2 / 256.0;
+
+    let mut roots = Vec::new();
+    if q.abs() < 1e-12 {
+        // biquadratic: solve for y² instead
+        for z in solve_quadratic(1.0, p, r) {
+            if z >= 0.0 {
+                roots.push(z.sqrt());
+                roots.push(-z.sqrt());
+            }
+        }
+    } else {
+        // pick m to make both sides of (y² + p/2 + m)² = 2m y² - q y + m² + p m + p²/4 - r
+        // perfect squares; the resolvent cubic always has a positive root when q isn't zero
+        let m = *solve_cubic(p, p * p / 4.0 - r, -q * q / 8.0)
+            .last()
+            .unwrap();
+        if m <= 0.0 {
+            return Vec::new();
+        }
+        let s = (2.0 * m).sqrt();
+        roots.extend(solve_quadratic(1.0, -s, p / 2.0 + m + q / (2.0 * s)));
+        roots.extend(solve_quadratic(1.0, s, p / 2.0 + m - q / (2.0 * s)));
+    }
+
+    for root in roots.iter_mut() {
+        *root -= shift;
+        // the closed form loses precision; a couple of Newton steps win it back
+        for _ in 0..2 {
+            let x = *root;
+            let f = (((x + a) * x + b) * x + c) * x + d;
+            let df = ((4.0 * x + 3.0 * a) * x + 2.0 * b) * x + c;
+            if df.abs() > 1e-12 {
+                *root = x - f / df;
+            }
+        }
+    }
+    roots.sort_by(|x, y| x.partial_cmp(y).unwrap());
+    roots
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn assert_roots(actual: Vec<f64>, expected: &[f64]) {
+        assert_eq!(actual.len(), expected.len(), "roots: {:?}", actual);
+        for (a, e) in actual.iter().zip(expected) {
+            assert!((a - e).abs() < 1e-6, "roots: {:?}", actual);
+        }
+    }
+
+    #[test]
+    fn test_quadratic() {
+        assert_roots(solve_quadratic(1.0, -3.0, 2.0), &[1.0, 2.0]);
+        assert_roots(solve_quadratic(1.0, 0.0, 1.0), &[]);
+        assert_roots(solve_quadratic(0.0, 2.0, -4.0), &[2.0]);
+    }
+
+    #[test]
+    fn test_cubic() {
+        // (x - 1)(x - 2)(x - 3)
+        assert_roots(solve_cubic(-6.0, 11.0, -6.0), &[1.0, 2.0, 3.0]);
+        // (x - 2)(x² + 1)
+        assert_roots(solve_cubic(-2.0, 1.0, -2.0), &[2.0]);
+    }
+
+    #[test]
+    fn test_quartic() {
+        // (x - 1)(x - 2)(x - 3)(x - 4)
+        assert_roots(
+            solve_quartic(-10.0, 35.0, -50.0, 24.0),
+            &[1.0, 2.0, 3.0, 4.0],
+        );
+        // (x² - 1)(x² - 4), which is biquadratic
+        assert_roots(solve_quartic(0.0, -5.0, 0.0, 4.0), &[-2.0, -1.0, 1.0, 2.0]);
+        // (x - 1)(x + 2)(x² + 1)
+        assert_roots(solve_quartic(1.0, -1.0, 1.0, -2.0), &[-2.0, 1.0]);
+        // x⁴ + 1 has no real roots
+        assert_roots(solve_quartic(0.0, 0.0, 0.0, 1.0), &[]);
+    }
+}
@@ -0,0 +1,392 @@
+use nalgebra::*;
+use std::fmt;
+use std::sync::Arc;
+
+use super::*;
+use crate::prelude::*;
+
+/// An arbitrary distance function, as wrapped by [`Sdf::function`].
+pub type DistanceFn = dyn Fn(&Point3<f32>) -> f32 + Send + Sync;
+
+/// A signed distance function: negative inside the shape, positive outside, and never more
+/// than the true distance to the surface.  Built up from primitives and operations, or from an
+/// arbitrary closure.
+#[derive(Clone)]
+pub enum Sdf {
+    Sphere {
+        radius: f32,
+    },
+    Cuboid {
+        half_extents: Vector3<f32>,
+    },
+    Torus {
+        major_radius: f32,
+        minor_radius: f32,
+    },
+    Union(Box<Sdf>, Box<Sdf>),
+    Intersection(Box<Sdf>, Box<Sdf>),
+    Difference(Box<Sdf>, Box<Sdf>),
+    /// A union that blends the two shapes together over a distance of `k`
+    SmoothUnion(Box<Sdf>, Box<Sdf>, f32),
+    Translate(Box<Sdf>, Vector3<f32>),
+    Scale(Box<Sdf>, f32),
+    /// Inflates the shape, rounding off its edges
+    Round(Box<Sdf>, f32),
+    /// Twists the shape around the y axis by the given number of radians per unit of height
+    Twist(Box<Sdf>, f32),
+    /// Repeats the shape forever with the given period along each axis.  A period of zero leaves
+    /// that axis alone.
+    Repeat(Box<Sdf>, Vector3<f32>),
+    Function(Arc<DistanceFn>),
+}
+
+impl Sdf {
+    pub fn sphere(radius: f32) -> Sdf {
+        Sdf::Sphere { radius }
+    }
+
+    /// A box centered on the origin.
+    pub fn cuboid(half_extents: Vector3<f32>) -> Sdf {
+        Sdf::Cuboid { half_extents }
+    }
+
+    /// A torus centered on the origin, with the y axis through its hole.
+    pub fn torus(major_radius: f32, minor_radius: f32) -> Sdf {
+        Sdf::Torus {
+            major_radius,
+            minor_radius,
+        }
+    }
+
+    /// Wraps an arbitrary distance function.
+    pub fn function<F>(f: F) -> Sdf
+    where
+        F: Fn(&Point3<f32>) -> f32 + Send + Sync + 'static,
+    {
+        Sdf::Function(Arc::new(f))
+    }
+
+    pub fn union(self, other: Sdf) -> Sdf {
+        Sdf::Union(Box::new(self), Box::new(other))
+    }
+
+    pub fn intersection(self, other: Sdf) -> Sdf {
+        Sdf::Intersection(Box::new(self), Box::new(other))
+    }
+
+    pub fn difference(self, other: Sdf) -> Sdf {
+        Sdf::Difference(Box::new(self), Box::new(other))
+    }
+
+    pub fn smooth_union(self, other: Sdf, k: f32) -> Sdf {
+        Sdf::SmoothUnion(Box::new(self), Box::new(other), k)
+    }
+
+    pub fn translate(self, offset: Vector3<f32>) -> Sdf {
+        Sdf::Translate(Box::new(self), offset)
+    }
+
+    pub fn scale(self, factor: f32) -> Sdf {
+        Sdf::Scale(Box::new(self), factor)
+    }
+
+    pub fn round(self, radius: f32) -> Sdf {
+        Sdf::Round(Box::new(self), radius)
+    }
+
+    pub fn twist(self, rate: f32) -> Sdf {
+        Sdf::Twist(Box::new(self), rate)
+    }
+
+    pub fn repeat(self, period: Vector3<f32>) -> Sdf {
+        Sdf::Repeat(Box::new(self), period)
+    }
+
+    /// Evaluates the distance from `p` to the surface.
+    pub fn distance(&self, p: &Point3<f32>) -> f32 {
+        match self {
+            Sdf::Sphere { radius } => p.coords.norm() - radius,
+            Sdf::Cuboid { half_extents } => {
+                let q = p.coords.abs() - half_extents;
+                q.map(|c| c.max(0.0)).norm() + q.max().min(0.0)
+            }
+            Sdf::Torus {
+                major_radius,
+                minor_radius,
+            } => {
+                let ring = (p.x * p.x + p.z * p.z).sqrt() - major_radius;
+                (ring * ring + p.y * p.y).sqrt() - minor_radius
+            }
+            Sdf::Union(a, b) => a.distance(p).min(b.distance(p)),
+            Sdf::Intersection(a, b) => a.distance(p).max(b.distance(p)),
+            Sdf::Difference(a, b) => a.distance(p).max(-b.distance(p)),
+            Sdf::SmoothUnion(a, b, k) => {
+                let (d1, d2) = (a.distance(p), b.distance(p));
+                let h = (0.5 + 0.5 * (d2 - d1) / k).clamp(0.0, 1.0);
+                d2 + (d1 - d2) * h - k * h * (1.0 - h)
+            }
+            Sdf::Translate(sdf, offset) => sdf.distance(&(p - offset)),
+            Sdf::Scale(sdf, factor) => sdf.distance(&(p / *factor)) * factor,
+            Sdf::Round(sdf, radius) => sdf.distance(p) - radius,
+            Sdf::Twist(sdf, rate) => {
+                let (sin, cos) = (rate * p.y).sin_cos();
+                let twisted = Point3::new(cos * p.x - sin * p.z, p.y, sin * p.x + cos * p.z);
+                sdf.distance(&twisted)
+            }
+            Sdf::Repeat(sdf, period) => {
+                let cell = p.coords.zip_map(period, |c, period| {
+                    if period > 0.0 {
+                        (c + 0.5 * period).rem_euclid(period) - 0.5 * period
+                    } else {
+                        c
+                    }
+                });
+                sdf.distance(&Point3::from(cell))
+            }
+            Sdf::Function(f) => f(p),
+        }
+    }
+}
+
+impl fmt::Debug for Sdf {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Sdf::Sphere { radius } => f.debug_struct("Sphere").field("radius", radius).finish(),
+            Sdf::Cuboid { half_extents } => f
+                .debug_struct("Cuboid")
+                .field("half_extents", half_extents)
+                .finish(),
+            Sdf::Torus {
+                major_radius,
+                minor_radius,
+            } => f
+                .debug_struct("Torus")
+                .field("major_radius", major_radius)
+                .field("minor_radius", minor_radius)
+                .finish(),
+            Sdf::Union(a, b) => f.debug_tuple("Union").field(a).field(b).finish(),
+            Sdf::Intersection(a, b) => f.debug_tuple("Intersection").field(a).field(b).finish(),
+            Sdf::Difference(a, b) => f.debug_tuple("Difference").field(a).field(b).finish(),
+            Sdf::SmoothUnion(a, b, k) => f
+                .debug_tuple("SmoothUnion")
+                .field(a)
+                .field(b)
+                .field(k)
+                .finish(),
+            Sdf::Translate(s, o) => f.debug_tuple("Translate").field(s).field(o).finish(),
+            Sdf::Scale(s, k) => f.debug_tuple("Scale").field(s).field(k).finish(),
+            Sdf::Round(s, r) => f.debug_tuple("Round").field(s).field(r).finish(),
+            Sdf::Twist(s, r) => f.debug_tuple("Twist").field(s).field(r).finish(),
+            Sdf::Repeat(s, p) => f.debug_tuple("Repeat").field(s).field(p).finish(),
+            Sdf::Function(_) => f.write_str("Function(..)"),
+        }
+    }
+}
+
+/// A shape described by a signed distance function, rendered by sphere tracing: stepping along
+/// the ray by the distance to the surface until it's close enough to count as a hit.
+#[derive(Debug, Clone)]
+pub struct SdfShape {
+    pub(crate) sdf: Sdf,
+    pub(crate) bounds: Bounds,
+    pub(crate) material: MaterialId,
+    /// How close a point has to be to the surface to count as a hit
+    pub(crate) epsilon: f32,
+    /// How many steps to take before giving up on finding the surface
+    pub(crate) max_steps: u32,
+    /// Fraction of the distance to take with each step.  Operations like twisting make the
+    /// distance overestimate, so they need smaller steps to avoid passing through the surface.
+    pub(crate) step_scale: f32,
+}
+
+impl SdfShape {
+    /// Creates a shape from `sdf`.  Since the distance function can be anything, the caller
+    /// has to say where the shape lives; rays are only marched inside of `bounds`.
+    pub fn new(sdf: Sdf, bounds: Bounds, material: MaterialId) -> SdfShape {
+        SdfShape {
+            sdf,
+            bounds,
+            material,
+            epsilon: 1e-4,
+            max_steps: 256,
+            step_scale: 1.0,
+        }
+    }
+
+    pub fn with_epsilon(self, epsilon: f32) -> SdfShape {
+        SdfShape { epsilon, ..self }
+    }
+
+    pub fn with_max_steps(self, max_steps: u32) -> SdfShape {
+        SdfShape { max_steps, ..self }
+    }
+
+    pub fn with_step_scale(self, step_scale: f32) -> SdfShape {
+        SdfShape { step_scale, ..self }
+    }
+
+    /// Marches along the ray until it reaches the surface.
+    fn hit_distance(&self, ray: &Ray) -> Option<f32> {
+        let (start, end) = self.bounds.intersect(ray)?;
+        let at = |t: f32| ray.source + t * ray.direction;
+        // march towards the surface from whichever side the ray starts on
+        let side = self.sdf.distance(&at(start)).signum();
+
+        let mut t = start;
+        for _ in 0..self.max_steps {
+            let distance = side * self.sdf.distance(&at(t));
+            if distance < self.epsilon {
+                return Some(t);
+            }
+            t += distance * self.step_scale;
+            if t > end {
+                return None;
+            }
+        }
+        None
+    }
+
+    /// Estimates the normal by taking the gradient of the distance function with central
+    /// differences.
+    fn normal(&self, p: &Point3<f32>) -> Vector3<f32> {
+        let h = self.epsilon;
+        let difference = |offset: Vector3<f32>| {
+            self.sdf.distance(&(p + offset)) - self.sdf.distance(&(p - offset))
+        };
+        Vector3::new(
+            difference(Vector3::new(h, 0.0, 0.0)),
+            difference(Vector3::new(0.0, h, 0.0)),
+            difference(Vector3::new(0.0, 0.0, h)),
+        )
+        .normalize()
+    }
+}
+
+impl Intersectable for SdfShape {
+    fn intersect(&self, ray: &Ray) -> Option<SurfaceInteraction> {
+        let t = self.hit_distance(ray)?;
+        let normal = self.normal(&(ray.source + t * ray.direction));
+        // distance fields have no natural parameterization, so just use any tangents
+        let (dpdu, dpdv) = coordinate_system(&normal);
+        Some(SurfaceInteraction::new(
+            ray,
+            t,
+            normal,
+            Point2::new(0.0, 0.0),
+            dpdu,
+            dpdv,
+        ))
+    }
+
+    fn occludes(&self, ray: &Ray) -> bool {
+        self.hit_distance(ray).is_some()
+    }
+}
+
+impl Shape for SdfShape {
+    fn bounds(&self) -> Bounds {
+        self.bounds
+    }
+
+    fn material(&self) -> MaterialId {
+        self.material
+    }
+}
+
+impl From<SdfShape> for Geometry {
+    fn from(shape: SdfShape) -> Self {
+        Geometry::Sdf(shape)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn cube_bounds(half_size: f32) -> Bounds {
+        let extent = Vector3::new(half_size, half_size, half_size);
+        Bounds::new(Point3::from(-extent), Point3::from(extent))
+    }
+
+    fn shape(sdf: Sdf) -> Instance {
+        let shape = SdfShape::new(sdf, cube_bounds(3.0), MaterialId(0));
+        Instance::new(shape, convert(Translation3::new(0.0, 0.0, 5.0)))
+    }
+
+    #[test]
+    fn test_sphere_matches_analytic() {
+        let ray = Ray::new(Point3::new(0.3, 0.2, 0.0), Vector3::new(0.0, 0.0, 1.0));
+        let hit = shape(Sdf::sphere(1.0)).intersect(&ray).unwrap();
+        let expected = Sphere::new(Point3::new(0.0, 0.0, 5.0), 1.0, MaterialId(0))
+            .intersect(&ray)
+            .unwrap();
+        assert!((hit.t - expected.t).abs() < 1e-3);
+        assert!((hit.geometric_normal - expected.geometric_normal).norm() < 1e-2);
+        assert!(hit.front_face);
+    }
+
+    #[test]
+    fn test_from_inside() {
+        let sdf = SdfShape::new(Sdf::sphere(1.0), cube_bounds(3.0), MaterialId(0));
+        let ray = Ray::new(Point3::new(0.0, 0.0, 0.0), Vector3::new(1.0, 0.0, 0.0));
+        let hit = sdf.intersect(&ray).unwrap();
+        assert!((hit.t - 1.0).abs() < 1e-3);
+        assert!(!hit.front_face);
+    }
+
+    #[test]
+    fn test_operations() {
+        let ray = Ray::new(Point3::new(0.0, 0.0, 0.0), Vector3::new(0.0, 0.0, 1.0));
+
+        // two blobs, blended together where they meet
+        let left = Sdf::sphere(1.0).translate(Vector3::new(-1.0, 0.0, 0.0));
+        let right = Sdf::sphere(1.0).translate(Vector3::new(1.0, 0.0, 0.0));
+        let blobs = shape(left.clone().union(right.clone()));
+        let blended = shape(left.smooth_union(right, 0.5));
+        // the plain union only just touches at the middle, but the blend fills it in
+        assert!(blended.intersect(&ray).unwrap().t < blobs.intersect(&ray).unwrap().t - 0.05);
+
+        // a hollowed out box
+        let hollow = shape(Sdf::cuboid(Vector3::new(1.0, 1.0, 1.0)).difference(Sdf::sphere(1.2)));
+        let ray = Ray::new(Point3::new(0.0, 0.0, 0.0), Vector3::new(0.0, 0.0, 1.0));
+        assert!(hollow.intersect(&ray).is_none());
+        let corner = Ray::new(Point3::new(0.9, 0.9, 0.0), Vector3::new(0.0, 0.0, 1.0));
+        assert!((hollow.intersect(&corner).unwrap().t - 4.0).abs() < 1e-3);
+    }
+
+    #[test]
+    fn test_closure_and_repeat() {
+        // a row of tiny spheres along x, defined as a closure
+        let sdf = Sdf::function(|p| p.coords.norm() - 0.25).repeat(Vector3::new(1.0, 0.0, 0.0));
+        let row = shape(sdf);
+        let on_row = Ray::new(Point3::new(2.0, 0.0, 0.0), Vector3::new(0.0, 0.0, 1.0));
+        assert!((row.intersect(&on_row).unwrap().t - 4.75).abs() < 1e-3);
+        let between = Ray::new(Point3::new(2.5, 0.0, 0.0), Vector3::new(0.0, 0.0, 1.0));
+        assert!(row.intersect(&between).is_none());
+    }
+
+    #[test]
+    fn test_max_steps() {
+        // grazing past the sphere takes many small steps
+        let sdf = SdfShape::new(Sdf::sphere(1.0), cube_bounds(3.0), MaterialId(0));
+        let ray = Ray::new(Point3::new(-3.0, 1.0001, 0.0), Vector3::new(1.0, 0.0, 0.0));
+        assert!(!sdf.occludes(&ray));
+        let ray = Ray::new(Point3::new(-3.0, 0.0, 0.0), Vector3::new(1.0, 0.0, 0.0));
+        assert!(sdf.occludes(&ray));
+        assert!(!sdf.with_max_steps(0).occludes(&ray));
+    }
+
+    #[test]
+    fn test_twist() {
+        // a box twisted by a quarter turn from its middle to its top is untouched in the
+        // middle, and turned by an eighth of a turn halfway up, so a corner faces the ray
+        let rate = std::f32::consts::FRAC_PI_2;
+        let sdf = Sdf::cuboid(Vector3::new(0.5, 1.0, 0.5)).twist(rate);
+        let shape = SdfShape::new(sdf, cube_bounds(3.0), MaterialId(0)).with_step_scale(0.5);
+        let ray = Ray::new(Point3::new(0.0, 0.0, -3.0), Vector3::new(0.0, 0.0, 1.0));
+        assert!((shape.intersect(&ray).unwrap().t - 2.5).abs() < 1e-3);
+        let ray = Ray::new(Point3::new(0.0, 0.5, -3.0), Vector3::new(0.0, 0.0, 1.0));
+        let corner = 0.5 * std::f32::consts::SQRT_2;
+        assert!((shape.intersect(&ray).unwrap().t - (3.0 - corner)).abs() < 1e-3);
+    }
+}
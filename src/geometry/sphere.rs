@@ -8,11 +8,11 @@ use crate::prelude::*;
 pub struct Sphere {
     pub(crate) center: Point3<f32>,
     pub(crate) radius: f32,
-    pub(crate) material: Material,
+    pub(crate) material: MaterialId,
 }
 
 impl Sphere {
-    pub fn new(center: Point3<f32>, radius: f32, material: Material) -> Sphere {
+    pub fn new(center: Point3<f32>, radius: f32, material: MaterialId) -> Sphere {
         Sphere {
             center,
             radius,
@@ -21,9 +21,14 @@ impl Sphere {
     }
 }
 
-impl Colorable for Sphere {
-    fn color(&self, scene: &Scene, i: &Intersection, tracing_depth: u32) -> Color {
-        self.material.color(scene, i, tracing_depth)
+impl Shape for Sphere {
+    fn bounds(&self) -> Bounds {
+        let extent = Vector3::new(self.radius, self.radius, self.radius);
+        Bounds::new(self.center - extent, self.center + extent)
+    }
+
+    fn material(&self) -> MaterialId {
+        self.material
     }
 }
 
@@ -31,14 +36,35 @@ impl Intersectable for Sphere {
     /// Determines whether the ray will intersect the sphere. See
     /// [here](https://bheisler.github.io/post/writing-raytracer-in-rust-part-2/)
     /// for more information on how this works.
-    fn intersect(&self, ray: &Ray) -> Option<f32> {
+    fn intersect(&self, ray: &Ray) -> Option<SurfaceInteraction> {
+        self.hit_distance(ray)
+            .map(|t| self.surface_interaction(ray, t))
+    }
+
+    fn occludes(&self, ray: &Ray) -> bool {
+        self.hit_distance(ray).is_some()
+    }
+}
+
+impl Sphere {
+    /// Finds the distance to the closest point on the sphere within the ray's interval.
+    fn hit_distance(&self, ray: &Ray) -> Option<f32> {
+        let (near, far) = self.span(ray)?;
+        if ray.contains(near) {
+            Some(near)
+        } else if ray.contains(far) {
+            Some(far)
+        } else {
+            None
+        }
+    }
+
+    /// Finds where the ray enters and leaves the sphere, ignoring the ray's interval.
+    pub(crate) fn span(&self, ray: &Ray) -> Option<(f32, f32)> {
         // length of leg a of the triangle
         let direct_distance = self.center - ray.source;
         // length of the hypotenuse
         let adjacent_leg = direct_distance.dot(&ray.direction);
-        if adjacent_leg.signum() < 0.0 {
-            return None;
-        }
 
         // length of the remaining side (squared)
         let d = direct_distance.dot(&direct_distance) - adjacent_leg.powi(2);
@@ -49,25 +75,37 @@ impl Intersectable for Sphere {
         }
 
         let thickness = (radius2 - d).sqrt();
-        let t0 = adjacent_leg + thickness;
-        let t1 = adjacent_leg - thickness;
+        Some((adjacent_leg - thickness, adjacent_leg + thickness))
+    }
 
-        if t0 < 0.0 && t1 < 0.0 {
-            None
-        } else {
-            let d = if t0 < 0.0 {
-                t1
-            } else if t1 < 0.0 {
-                t0
-            } else {
-                t0.min(t1)
-            };
-            Some(d)
+    /// Describes the surface of the sphere where `ray` hits it at distance `t`.  `u` runs around
+    /// the y axis and `v` runs from the top of the sphere to the bottom.
+    pub(crate) fn surface_interaction(&self, ray: &Ray, t: f32) -> SurfaceInteraction {
+        use std::f32::consts::PI;
+
+        let local = (ray.source + t * ray.direction) - self.center;
+        let mut phi = local.z.atan2(local.x);
+        if phi < 0.0 {
+            phi += 2.0 * PI;
         }
-    }
+        let theta = (local.y / self.radius).clamp(-1.0, 1.0).acos();
 
-    fn surface_normal(&self, hit_point: &Point3<f32>) -> Vector3<f32> {
-        (hit_point - self.center).normalize()
+        let dpdu = 2.0 * PI * Vector3::new(-local.z, 0.0, local.x);
+        let dpdv = PI
+            * Vector3::new(
+                local.y * phi.cos(),
+                -self.radius * theta.sin(),
+                local.y * phi.sin(),
+            );
+
+        SurfaceInteraction::new(
+            ray,
+            t,
+            local,
+            Point2::new(phi / (2.0 * PI), theta / PI),
+            dpdu,
+            dpdv,
+        )
     }
 }
 
@@ -85,30 +123,60 @@ mod tests {
 
     #[test]
     fn test_intersect() {
-        let sphere = Sphere::new(
-            Point3::new(1.0, 1.0, 1.0),
-            1.0,
-            Diffuse::new(Color::new(1.0, 1.0, 1.0), 1.0).into(),
-        );
-        let ray = Ray {
-            source: Point3::new(0.0, 0.0, 0.0),
-            direction: Vector3::new(1.0, 1.0, 1.0).normalize(),
-        };
+        let sphere = Sphere::new(Point3::new(1.0, 1.0, 1.0), 1.0, MaterialId(0));
+        let ray = Ray::new(Point3::new(0.0, 0.0, 0.0), Vector3::new(1.0, 1.0, 1.0));
         let result = sphere.intersect(&ray);
-        assert!(result.unwrap() - (3.0 as f32).sqrt() - 1.0 <= 1e-6);
+        assert!(result.unwrap().t - 3.0_f32.sqrt() - 1.0 <= 1e-6);
+    }
+
+    #[test]
+    fn test_hit_record() {
+        let sphere = Sphere::new(Point3::new(0.0, 0.0, 5.0), 1.0, MaterialId(0));
+        let ray = Ray::new(Point3::new(0.0, 0.0, 0.0), Vector3::new(0.0, 0.0, 1.0));
+        let hit = sphere.intersect(&ray).unwrap();
+        assert!((hit.t - 4.0).abs() < 1e-6);
+        assert!(hit.front_face);
+        assert!((hit.geometric_normal - Vector3::new(0.0, 0.0, -1.0)).norm() < 1e-6);
+        assert_eq!(hit.geometric_normal, hit.shading_normal);
+        assert!((hit.uv.y - 0.5).abs() < 1e-6);
+        // the tangents span the surface, so their cross product is along the normal
+        let n = hit.dpdu.cross(&hit.dpdv).normalize();
+        assert!(n.dot(&hit.geometric_normal).abs() > 1.0 - 1e-4);
     }
 
     #[test]
     fn test_near_miss() {
-        let sphere = Sphere::new(
-            Point3::new(1.0, 1.0, 1.0),
-            1.0,
-            Diffuse::new(Color::new(1.0, 1.0, 1.0), 1.0).into(),
-        );
-        let ray = Ray {
-            source: Point3::new(0.0, 0.0, 0.0),
-            direction: Vector3::new(0.0, 0.0, 1.0),
-        };
+        let sphere = Sphere::new(Point3::new(1.0, 1.0, 1.0), 1.0, MaterialId(0));
+        let ray = Ray::new(Point3::new(0.0, 0.0, 0.0), Vector3::new(0.0, 0.0, 1.0));
         assert!(sphere.intersect(&ray).is_none());
     }
+
+    #[test]
+    fn test_inside() {
+        let sphere = Sphere::new(Point3::new(0.0, 0.0, 0.0), 2.0, MaterialId(0));
+        let ray = Ray::new(Point3::new(1.0, 0.0, 0.0), Vector3::new(1.0, 0.0, 0.0));
+        let hit = sphere.intersect(&ray).unwrap();
+        assert!((hit.t - 1.0).abs() < 1e-6);
+        assert!(!hit.front_face);
+        assert!((hit.shading_normal - Vector3::new(-1.0, 0.0, 0.0)).norm() < 1e-6);
+    }
+
+    #[test]
+    fn test_interval() {
+        let sphere = Sphere::new(Point3::new(0.0, 0.0, 5.0), 1.0, MaterialId(0));
+        let ray = Ray::new(Point3::new(0.0, 0.0, 0.0), Vector3::new(0.0, 0.0, 1.0));
+
+        // the near side is skipped when it's before tmin
+        let hit = sphere.intersect(&ray.clone().with_interval(4.5, 10.0));
+        assert!((hit.unwrap().t - 6.0).abs() < 1e-6);
+        // the whole sphere lies past tmax
+        assert!(sphere
+            .intersect(&ray.clone().with_interval(0.0, 3.0))
+            .is_none());
+        assert!(!sphere.occludes(&Ray::segment(
+            Point3::new(0.0, 0.0, 0.0),
+            Point3::new(0.0, 0.0, 4.0)
+        )));
+        assert!(sphere.occludes(&ray));
+    }
 }
@@ -0,0 +1,169 @@
+use super::roots::*;
+use super::*;
+use nalgebra::*;
+use std::f32::consts::PI;
+
+/// A torus lying flat, with the y axis through its hole.
+#[derive(Debug)]
+pub struct Torus {
+    pub(crate) center: Point3<f32>,
+    /// Distance from the center to the middle of the tube
+    pub(crate) major_radius: f32,
+    /// Radius of the tube
+    pub(crate) minor_radius: f32,
+    pub(crate) material: MaterialId,
+}
+
+impl Torus {
+    pub fn new(
+        center: Point3<f32>,
+        major_radius: f32,
+        minor_radius: f32,
+        material: MaterialId,
+    ) -> Torus {
+        Torus {
+            center,
+            major_radius,
+            minor_radius,
+            material,
+        }
+    }
+
+    /// Finds the distance to the closest point on the torus within the ray's interval.  This
+    /// means solving the quartic `(|p|² + R² - r²)² = 4R²(x² + z²)` along the ray.
+    fn hit_distance(&self, ray: &Ray) -> Option<f32> {
+        // start from where the ray enters the bounding box; the quartic is much better behaved
+        // close to the torus than far away from it
+        let (start, _) = self.bounds().intersect(ray)?;
+        let o = (ray.source + start * ray.direction - self.center).map(f64::from);
+        let d = ray.direction.map(f64::from);
+        let major2 = f64::from(self.major_radius).powi(2);
+        let minor2 = f64::from(self.minor_radius).powi(2);
+
+        let n = o.dot(&d);
+        let k = o.dot(&o) + major2 - minor2;
+        let roots = solve_quartic(
+            4.0 * n,
+            4.0 * n * n + 2.0 * k - 4.0 * major2 * (d.x * d.x + d.z * d.z),
+            4.0 * n * k - 8.0 * major2 * (o.x * d.x + o.z * d.z),
+            k * k - 4.0 * major2 * (o.x * o.x + o.z * o.z),
+        );
+        roots
+            .into_iter()
+            .map(|t| start + t as f32)
+            .find(|t| ray.contains(*t))
+    }
+
+    /// Describes the surface of the torus where `ray` hits it at distance `t`.  `u` runs around
+    /// the y axis, and `v` runs around the tube.
+    fn surface_interaction(&self, ray: &Ray, t: f32) -> SurfaceInteraction {
+        let local = (ray.source + t * ray.direction) - self.center;
+        let mut phi = local.z.atan2(local.x);
+        if phi < 0.0 {
+            phi += 2.0 * PI;
+        }
+        // the point in the middle of the tube closest to the hit point
+        let ring = Vector3::new(phi.cos(), 0.0, phi.sin()) * self.major_radius;
+        let normal = local - ring;
+
+        let rho = (local.x * local.x + local.z * local.z).sqrt();
+        let mut theta = local.y.atan2(rho - self.major_radius);
+        if theta < 0.0 {
+            theta += 2.0 * PI;
+        }
+        let dpdu = 2.0 * PI * Vector3::new(-local.z, 0.0, local.x);
+        // runs backwards around the tube, so that dpdu × dpdv points outwards
+        let dpdv = -2.0
+            * PI
+            * self.minor_radius
+            * Vector3::new(
+                -theta.sin() * phi.cos(),
+                theta.cos(),
+                -theta.sin() * phi.sin(),
+            );
+
+        SurfaceInteraction::new(
+            ray,
+            t,
+            normal,
+            Point2::new(phi / (2.0 * PI), 1.0 - theta / (2.0 * PI)),
+            dpdu,
+            dpdv,
+        )
+    }
+}
+
+impl Intersectable for Torus {
+    fn intersect(&self, ray: &Ray) -> Option<SurfaceInteraction> {
+        self.hit_distance(ray)
+            .map(|t| self.surface_interaction(ray, t))
+    }
+
+    fn occludes(&self, ray: &Ray) -> bool {
+        self.hit_distance(ray).is_some()
+    }
+}
+
+impl Shape for Torus {
+    fn bounds(&self) -> Bounds {
+        let outer = self.major_radius + self.minor_radius;
+        let extent = Vector3::new(outer, self.minor_radius, outer);
+        Bounds::new(self.center - extent, self.center + extent)
+    }
+
+    fn material(&self) -> MaterialId {
+        self.material
+    }
+}
+
+impl From<Torus> for Geometry {
+    fn from(torus: Torus) -> Self {
+        Geometry::Torus(torus)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn torus() -> Torus {
+        Torus::new(Point3::new(0.0, 0.0, 10.0), 2.0, 0.5, MaterialId(0))
+    }
+
+    #[test]
+    fn test_torus_through_tube() {
+        // straight along z through the middle: crosses the tube twice on each side
+        let ray = Ray::new(Point3::new(0.0, 0.0, 0.0), Vector3::new(0.0, 0.0, 1.0));
+        let hit = torus().intersect(&ray).unwrap();
+        assert!((hit.t - 7.5).abs() < 1e-4);
+        assert!((hit.geometric_normal - Vector3::new(0.0, 0.0, -1.0)).norm() < 1e-4);
+        assert!(hit.front_face);
+        let n = hit.dpdu.cross(&hit.dpdv).normalize();
+        assert!((n - hit.geometric_normal).norm() < 1e-4);
+
+        // starting inside the hole, the inner wall of the tube is next
+        let ray = Ray::new(Point3::new(0.0, 0.0, 10.0), Vector3::new(0.0, 0.0, 1.0));
+        let hit = torus().intersect(&ray).unwrap();
+        assert!((hit.t - 1.5).abs() < 1e-4);
+        assert!((hit.geometric_normal - Vector3::new(0.0, 0.0, -1.0)).norm() < 1e-4);
+    }
+
+    #[test]
+    fn test_torus_from_above() {
+        let ray = Ray::new(Point3::new(2.0, 5.0, 10.0), Vector3::new(0.0, -1.0, 0.0));
+        let hit = torus().intersect(&ray).unwrap();
+        assert!((hit.t - 4.5).abs() < 1e-4);
+        assert!((hit.geometric_normal - Vector3::new(0.0, 1.0, 0.0)).norm() < 1e-4);
+        assert!((hit.uv - Point2::new(0.0, 0.75)).norm() < 1e-4);
+    }
+
+    #[test]
+    fn test_torus_miss() {
+        // straight down through the hole
+        let ray = Ray::new(Point3::new(0.0, 5.0, 10.0), Vector3::new(0.0, -1.0, 0.0));
+        assert!(torus().intersect(&ray).is_none());
+        // just above the tube
+        let ray = Ray::new(Point3::new(0.0, 0.6, 0.0), Vector3::new(0.0, 0.0, 1.0));
+        assert!(torus().intersect(&ray).is_none());
+    }
+}
@@ -0,0 +1,59 @@
+use crate::prelude::*;
+use nalgebra::*;
+
+/// Intersects a ray with the triangle `a`, `b`, `c` using the Möller–Trumbore algorithm.
+/// Returns the distance to the hit and its barycentric coordinates relative to `b` and `c`.
+pub(crate) fn intersect_triangle(
+    ray: &Ray,
+    a: &Point3<f32>,
+    b: &Point3<f32>,
+    c: &Point3<f32>,
+) -> Option<(f32, Point2<f32>)> {
+    let ab = b - a;
+    let ac = c - a;
+    let p = ray.direction.cross(&ac);
+    let determinant = ab.dot(&p);
+    if determinant.abs() < 1e-12 {
+        return None;
+    }
+
+    let inverse = 1.0 / determinant;
+    let offset = ray.source - a;
+    let u = offset.dot(&p) * inverse;
+    if !(0.0..=1.0).contains(&u) {
+        return None;
+    }
+    let q = offset.cross(&ab);
+    let v = ray.direction.dot(&q) * inverse;
+    if v < 0.0 || u + v > 1.0 {
+        return None;
+    }
+
+    let t = ac.dot(&q) * inverse;
+    if ray.contains(t) {
+        Some((t, Point2::new(u, v)))
+    } else {
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_intersect_triangle() {
+        let a = Point3::new(0.0, 0.0, 5.0);
+        let b = Point3::new(1.0, 0.0, 5.0);
+        let c = Point3::new(0.0, 1.0, 5.0);
+        let ray = Ray::new(Point3::new(0.25, 0.5, 0.0), Vector3::new(0.0, 0.0, 1.0));
+        let (t, uv) = intersect_triangle(&ray, &a, &b, &c).unwrap();
+        assert!((t - 5.0).abs() < 1e-6);
+        assert!((uv - Point2::new(0.25, 0.5)).norm() < 1e-6);
+
+        let outside = Ray::new(Point3::new(0.75, 0.5, 0.0), Vector3::new(0.0, 0.0, 1.0));
+        assert!(intersect_triangle(&outside, &a, &b, &c).is_none());
+        let behind = Ray::new(Point3::new(0.25, 0.5, 6.0), Vector3::new(0.0, 0.0, 1.0));
+        assert!(intersect_triangle(&behind, &a, &b, &c).is_none());
+    }
+}
@@ -0,0 +1,182 @@
+//! A hierarchy of named nodes for grouping geometry and lights, so a whole assembly can be moved
+//! by changing one transform.
+
+use crate::prelude::*;
+use nalgebra::*;
+use std::sync::Arc;
+
+/// A named node in the scene graph.  Everything attached to a node, including its children, is
+/// placed relative to the node's transform.
+#[derive(Debug, Clone)]
+pub struct Node {
+    pub(crate) name: String,
+    pub(crate) transform: Affine3<f32>,
+    pub(crate) children: Vec<Node>,
+    pub(crate) geometry: Vec<Arc<Geometry>>,
+    pub(crate) lights: Vec<Light>,
+}
+
+impl Node {
+    /// Creates an empty node with no transform.
+    pub fn new<S>(name: S) -> Node
+    where
+        S: Into<String>,
+    {
+        Node {
+            name: name.into(),
+            transform: Affine3::identity(),
+            children: Vec::new(),
+            geometry: Vec::new(),
+            lights: Vec::new(),
+        }
+    }
+
+    /// Places the node relative to its parent.
+    pub fn with_transform(self, transform: Affine3<f32>) -> Node {
+        Node { transform, ..self }
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn transform(&self) -> &Affine3<f32> {
+        &self.transform
+    }
+
+    pub fn set_transform(&mut self, transform: Affine3<f32>) {
+        self.transform = transform;
+    }
+
+    pub fn children(&self) -> &[Node] {
+        &self.children
+    }
+
+    /// Adds a child node, returning it so it can be filled in.
+    pub fn add_child(&mut self, child: Node) -> &mut Node {
+        self.children.push(child);
+        self.children.last_mut().unwrap()
+    }
+
+    /// Attaches geometry to the node.
+    pub fn add_geometry<G>(&mut self, object: G)
+    where
+        G: Into<Geometry>,
+    {
+        self.geometry.push(Arc::new(object.into()));
+    }
+
+    /// Attaches geometry that may also be attached to other nodes.
+    pub fn add_shared_geometry(&mut self, object: Arc<Geometry>) {
+        self.geometry.push(object);
+    }
+
+    /// Attaches a light to the node.
+    pub fn add_light<L>(&mut self, light: L)
+    where
+        L: Into<Light>,
+    {
+        self.lights.push(light.into());
+    }
+
+    /// Finds a descendant by its path of names relative to this node, such as `"table/cup"`.  An
+    /// empty path refers to this node.
+    pub fn find(&self, path: &str) -> Option<&Node> {
+        path.split('/')
+            .filter(|name| !name.is_empty())
+            .try_fold(self, |node, name| {
+                node.children.iter().find(|child| child.name == name)
+            })
+    }
+
+    /// Finds a descendant by its path of names relative to this node.
+    pub fn find_mut(&mut self, path: &str) -> Option<&mut Node> {
+        path.split('/')
+            .filter(|name| !name.is_empty())
+            .try_fold(self, |node, name| {
+                node.children.iter_mut().find(|child| child.name == name)
+            })
+    }
+
+    /// Collects everything in the subtree, placed in world space.  `parent` is the world
+    /// transform of this node's parent.
+    pub(crate) fn flatten(
+        &self,
+        parent: &Affine3<f32>,
+        geometry: &mut Vec<Geometry>,
+        lights: &mut Vec<Light>,
+    ) {
+        let world = parent * self.transform;
+        geometry.extend(
+            self.geometry
+                .iter()
+                .map(|g| Instance::shared(Arc::clone(g), world).into()),
+        );
+        lights.extend(self.lights.iter().map(|l| l.transformed(&world)));
+        for child in &self.children {
+            child.flatten(&world, geometry, lights);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn table() -> Node {
+        let mut table = Node::new("table");
+        table.add_child(Node::new("cup")).add_geometry(Sphere::new(
+            Point3::new(0.0, 0.0, 0.0),
+            1.0,
+            MaterialId(0),
+        ));
+        table.add_child(Node::new("plate"));
+        table
+    }
+
+    #[test]
+    fn test_find_by_path() {
+        let mut root = Node::new("root");
+        root.add_child(table());
+
+        assert_eq!(root.find("table/cup").unwrap().name(), "cup");
+        assert_eq!(root.find("/table/plate/").unwrap().name(), "plate");
+        assert_eq!(root.find("").unwrap().name(), "root");
+        assert!(root.find("table/fork").is_none());
+        assert!(root.find("cup").is_none());
+
+        root.find_mut("table/cup")
+            .unwrap()
+            .add_child(Node::new("spoon"));
+        assert!(root.find("table/cup/spoon").is_some());
+    }
+
+    #[test]
+    fn test_flatten_composes_transforms() {
+        let mut table = table();
+        table.set_transform(convert(Translation3::new(0.0, 0.0, 5.0)));
+        table
+            .find_mut("cup")
+            .unwrap()
+            .set_transform(convert(Translation3::new(2.0, 0.0, 0.0)));
+        table.add_light(SphericalLight::new(
+            Point3::new(0.0, 1.0, 0.0),
+            Color::new(1.0, 1.0, 1.0),
+            1.0,
+        ));
+
+        let mut geometry = Vec::new();
+        let mut lights = Vec::new();
+        table.flatten(&Affine3::identity(), &mut geometry, &mut lights);
+
+        assert_eq!(geometry.len(), 1);
+        assert_eq!(
+            geometry[0].bounds(),
+            Bounds::new(Point3::new(1.0, -1.0, 4.0), Point3::new(3.0, 1.0, 6.0))
+        );
+        match &lights[0] {
+            Light::Spherical(light) => assert_eq!(light.pos, Point3::new(0.0, 1.0, 5.0)),
+            _ => panic!("expected a spherical light"),
+        }
+    }
+}
@@ -0,0 +1,644 @@
+use nalgebra::*;
+use std::collections::HashMap;
+use std::f32::consts::PI;
+use std::path::Path;
+use std::sync::Arc;
+
+use ::gltf::buffer;
+use ::gltf::camera::Projection;
+use ::gltf::image::{self, Format};
+use ::gltf::khr_lights_punctual::Kind;
+use ::gltf::mesh::Mode;
+use ::gltf::Document;
+
+use super::ImportError;
+use crate::prelude::*;
+
+impl Scene {
+    /// Adds the default scene of a glTF 2.0 file (`.gltf` or `.glb`) to the scene graph, under a
+    /// node named after the file.  Meshes become `Mesh` geometry shared between the nodes that
+    /// use them, materials become `Pbr` materials, and punctual lights are added to their nodes.
+    /// If the file contains a perspective camera, the first one found is used to view the scene.
+    pub fn import_gltf<P>(&mut self, path: P) -> Result<(), ImportError>
+    where
+        P: AsRef<Path>,
+    {
+        let path = path.as_ref();
+        let (document, buffers, images) = ::gltf::import(path)?;
+        let name = path
+            .file_stem()
+            .map_or_else(|| "gltf".to_string(), |s| s.to_string_lossy().into_owned());
+        Importer::new(self, &buffers, &images).import(&document, name)
+    }
+
+    /// Like `import_gltf`, but reads the file from memory and adds it under the node `name`.
+    /// Buffers and images must be embedded, in a binary chunk or as data URIs; files that refer
+    /// to external ones are rejected, since there's no directory to look them up in.
+    pub fn import_gltf_slice<S>(&mut self, name: S, data: &[u8]) -> Result<(), ImportError>
+    where
+        S: Into<String>,
+    {
+        let (document, buffers, images) = ::gltf::import_slice(data)?;
+        Importer::new(self, &buffers, &images).import(&document, name.into())
+    }
+}
+
+/// Converts the contents of a glTF file, remembering what has already been converted so that
+/// shared meshes, materials and textures are only added once.
+struct Importer<'a> {
+    scene: &'a mut Scene,
+    buffers: &'a [buffer::Data],
+    images: &'a [image::Data],
+    /// Materials by their index in the file, with `None` for the default material
+    materials: HashMap<Option<usize>, MaterialId>,
+    /// Textures by image index and whether they hold sRGB colors
+    textures: HashMap<(usize, bool), Arc<Texture>>,
+    meshes: HashMap<usize, Vec<Arc<Geometry>>>,
+    /// The first perspective camera found, with its vertical field of view in degrees
+    camera: Option<(Camera, f32)>,
+}
+
+impl<'a> Importer<'a> {
+    fn new(
+        scene: &'a mut Scene,
+        buffers: &'a [buffer::Data],
+        images: &'a [image::Data],
+    ) -> Importer<'a> {
+        Importer {
+            scene,
+            buffers,
+            images,
+            materials: HashMap::new(),
+            textures: HashMap::new(),
+            meshes: HashMap::new(),
+            camera: None,
+        }
+    }
+
+    fn import(mut self, document: &Document, name: String) -> Result<(), ImportError> {
+        let gltf_scene = document
+            .default_scene()
+            .or_else(|| document.scenes().next())
+            .ok_or_else(|| ImportError::Invalid("the glTF file has no scenes".to_string()))?;
+
+        // glTF is right-handed with the camera looking down -z, so mirror z to match the crate
+        let flip = Matrix4::new_nonuniform_scaling(&Vector3::new(1.0, 1.0, -1.0));
+        let mut root = Node::new(name).with_transform(Affine3::from_matrix_unchecked(flip));
+        for node in gltf_scene.nodes() {
+            if let Some(child) = self.node(&node, root.transform())? {
+                root.add_child(child);
+            }
+        }
+
+        if let Some((camera, fov)) = self.camera.take() {
+            self.scene.set_camera(camera);
+            self.scene.set_fov(fov);
+        }
+        self.scene.add_node(root);
+        Ok(())
+    }
+
+    /// Converts a node and its children.  `parent` is the world transform of the node's parent.
+    /// Nodes scaled down to nothing can't be seen, and are left out.
+    fn node(
+        &mut self,
+        node: &::gltf::Node<'_>,
+        parent: &Affine3<f32>,
+    ) -> Result<Option<Node>, ImportError> {
+        let transform = Affine3::from_matrix_unchecked(Matrix4::from(node.transform().matrix()));
+        if transform.try_inverse().is_none() {
+            return Ok(None);
+        }
+        let world = parent * transform;
+        let name = node
+            .name()
+            .map_or_else(|| format!("node{}", node.index()), String::from);
+        let mut result = Node::new(name).with_transform(transform);
+
+        if let Some(mesh) = node.mesh() {
+            for geometry in self.mesh(&mesh)? {
+                result.add_shared_geometry(geometry);
+            }
+        }
+
+        // orthographic cameras aren't supported, so only perspective cameras are considered
+        if let (None, Some(camera)) = (&self.camera, node.camera()) {
+            if let Projection::Perspective(perspective) = camera.projection() {
+                let eye = world.transform_point(&Point3::origin());
+                let forward = world.transform_vector(&-Vector3::z());
+                let up = world.transform_vector(&Vector3::y());
+                self.camera = Some((
+                    Camera::look_at(eye, eye + forward, up),
+                    perspective.yfov().to_degrees(),
+                ));
+            }
+        }
+
+        if let Some(light) = node.light() {
+            let [red, green, blue] = light.color();
+            let color = Color::new(red, green, blue);
+            // point and spot intensities are per steradian, while the crate's lights give the
+            // total over the whole sphere
+            let intensity = light.intensity();
+            let forward = -Vector3::z();
+            match light.kind() {
+                Kind::Directional => result.add_light(GlobalLight::new(forward, color, intensity)),
+                Kind::Point => result.add_light(SphericalLight::new(
+                    Point3::origin(),
+                    color,
+                    4.0 * PI * intensity,
+                )),
+                Kind::Spot {
+                    inner_cone_angle,
+                    outer_cone_angle,
+                } => result.add_light(SpotLight::new(
+                    Point3::origin(),
+                    forward,
+                    color,
+                    4.0 * PI * intensity,
+                    inner_cone_angle,
+                    outer_cone_angle,
+                )),
+            }
+        }
+
+        for child in node.children() {
+            if let Some(child) = self.node(&child, &world)? {
+                result.add_child(child);
+            }
+        }
+        Ok(Some(result))
+    }
+
+    /// Converts each primitive of a mesh into a `Mesh`.  Points and lines have no surface to
+    /// render, so they are skipped.
+    fn mesh(&mut self, mesh: &::gltf::Mesh<'_>) -> Result<Vec<Arc<Geometry>>, ImportError> {
+        if let Some(geometry) = self.meshes.get(&mesh.index()) {
+            return Ok(geometry.clone());
+        }
+
+        let buffers = self.buffers;
+        let mut geometry = Vec::new();
+        for primitive in mesh.primitives() {
+            let invalid = |what: &str| {
+                ImportError::Invalid(format!(
+                    "primitive {} of mesh {} {}",
+                    primitive.index(),
+                    mesh.index(),
+                    what
+                ))
+            };
+            let reader = primitive.reader(|buffer| buffers.get(buffer.index()).map(|d| &d.0[..]));
+            let positions = reader
+                .read_positions()
+                .ok_or_else(|| invalid("has no positions"))?
+                .map(|[x, y, z]| Point3::new(x, y, z))
+                .collect::<Vec<_>>();
+            let indices = match reader.read_indices() {
+                Some(indices) => indices.into_u32().map(|i| i as usize).collect(),
+                None => (0..positions.len()).collect::<Vec<_>>(),
+            };
+            if indices.iter().any(|&i| i >= positions.len()) {
+                return Err(invalid("refers to a missing vertex"));
+            }
+
+            let triangles = match primitive.mode() {
+                Mode::Triangles => indices
+                    .chunks_exact(3)
+                    .map(|t| [t[0], t[1], t[2]])
+                    .collect::<Vec<_>>(),
+                // every other triangle of a strip is wound the other way
+                Mode::TriangleStrip => (0..indices.len().saturating_sub(2))
+                    .map(|i| {
+                        if i % 2 == 0 {
+                            [indices[i], indices[i + 1], indices[i + 2]]
+                        } else {
+                            [indices[i + 1], indices[i], indices[i + 2]]
+                        }
+                    })
+                    .collect(),
+                Mode::TriangleFan => (1..indices.len().saturating_sub(1))
+                    .map(|i| [indices[0], indices[i], indices[i + 1]])
+                    .collect(),
+                Mode::Points | Mode::Lines | Mode::LineLoop | Mode::LineStrip => continue,
+            };
+            if triangles.is_empty() {
+                continue;
+            }
+
+            let vertices = positions.len();
+            let uv_set = uv_set(&primitive.material())?;
+            let material = self.material(&primitive.material())?;
+            let mut result = Mesh::new(positions, triangles, material);
+            if let Some(normals) = reader.read_normals() {
+                let normals = normals
+                    .map(|[x, y, z]| Vector3::new(x, y, z))
+                    .collect::<Vec<_>>();
+                if normals.len() != vertices {
+                    return Err(invalid("has the wrong number of normals"));
+                }
+                result = result.with_normals(normals);
+            }
+            if let Some(uvs) = reader.read_tex_coords(uv_set) {
+                let uvs = uvs
+                    .into_f32()
+                    .map(|[u, v]| Point2::new(u, v))
+                    .collect::<Vec<_>>();
+                if uvs.len() != vertices {
+                    return Err(invalid("has the wrong number of texture coordinates"));
+                }
+                result = result.with_uvs(uvs);
+            }
+            geometry.push(Arc::new(result.into()));
+        }
+
+        self.meshes.insert(mesh.index(), geometry.clone());
+        Ok(geometry)
+    }
+
+    /// Converts a metallic-roughness material into a `Pbr` material.
+    fn material(&mut self, material: &::gltf::Material<'_>) -> Result<MaterialId, ImportError> {
+        if let Some(&id) = self.materials.get(&material.index()) {
+            return Ok(id);
+        }
+
+        let pbr = material.pbr_metallic_roughness();
+        let [red, green, blue, _] = pbr.base_color_factor();
+        let base_factor = Color::new(red, green, blue);
+        let base_color = match pbr.base_color_texture() {
+            Some(info) => ColorSource::Texture(self.texture(&info, true)?, base_factor),
+            None => base_factor.into(),
+        };
+        let [red, green, blue] = material.emissive_factor();
+        let emissive_factor = Color::new(red, green, blue);
+        let emission = match material.emissive_texture() {
+            Some(info) => ColorSource::Texture(self.texture(&info, true)?, emissive_factor),
+            None => emissive_factor.into(),
+        };
+
+        let mut result = Pbr::new(base_color, pbr.metallic_factor(), pbr.roughness_factor())
+            .with_emission(emission);
+        if let Some(info) = pbr.metallic_roughness_texture() {
+            result = result.with_metallic_roughness(self.texture(&info, false)?);
+        }
+        let id = self.scene.add_material(result);
+        self.materials.insert(material.index(), id);
+        Ok(id)
+    }
+
+    /// Converts the image used by a texture.  Color textures are stored in sRGB and have to be
+    /// converted to linear colors, while data textures like metallic-roughness are already
+    /// linear.
+    fn texture(
+        &mut self,
+        info: &::gltf::texture::Info<'_>,
+        srgb: bool,
+    ) -> Result<Arc<Texture>, ImportError> {
+        let index = info.texture().source().index();
+        if let Some(texture) = self.textures.get(&(index, srgb)) {
+            return Ok(Arc::clone(texture));
+        }
+        let image = self
+            .images
+            .get(index)
+            .ok_or_else(|| ImportError::Invalid(format!("image {} is missing", index)))?;
+        let texture = Arc::new(image_texture(image, srgb).ok_or_else(|| {
+            ImportError::Invalid(format!("image {} has the wrong number of pixels", index))
+        })?);
+        self.textures.insert((index, srgb), Arc::clone(&texture));
+        Ok(texture)
+    }
+}
+
+/// The set of texture coordinates a material's textures are looked up with.  Meshes carry a
+/// single set, so every texture of the material has to use the same one.
+fn uv_set(material: &::gltf::Material<'_>) -> Result<u32, ImportError> {
+    let pbr = material.pbr_metallic_roughness();
+    let mut sets = [
+        pbr.base_color_texture(),
+        pbr.metallic_roughness_texture(),
+        material.emissive_texture(),
+    ]
+    .iter()
+    .flatten()
+    .map(|info| info.tex_coord())
+    .collect::<Vec<_>>();
+    sets.dedup();
+    match sets[..] {
+        [] => Ok(0),
+        [set] => Ok(set),
+        _ => Err(ImportError::Invalid(format!(
+            "the textures of material {} use different texture coordinates",
+            material
+                .index()
+                .map_or_else(|| "default".to_string(), |i| i.to_string())
+        ))),
+    }
+}
+
+/// Converts decoded image data to a texture, ignoring any alpha channel.  Gray images are
+/// copied into all three channels.  Returns `None` if the image is empty or its size doesn't
+/// match its pixels.
+fn image_texture(image: &image::Data, srgb: bool) -> Option<Texture> {
+    let (channels, bytes) = match image.format {
+        Format::R8 => (1, 1),
+        Format::R8G8 => (2, 1),
+        Format::R8G8B8 => (3, 1),
+        Format::R8G8B8A8 => (4, 1),
+        Format::R16 => (1, 2),
+        Format::R16G16 => (2, 2),
+        Format::R16G16B16 => (3, 2),
+        Format::R16G16B16A16 => (4, 2),
+        Format::R32G32B32FLOAT => (3, 4),
+        Format::R32G32B32A32FLOAT => (4, 4),
+    };
+    let (width, height) = (image.width as usize, image.height as usize);
+    if width == 0 || height == 0 || image.pixels.len() != width * height * channels * bytes {
+        return None;
+    }
+
+    // the decoder stores samples wider than a byte in native byte order
+    let sample = |b: &[u8]| match bytes {
+        1 => f32::from(b[0]) / 255.0,
+        2 => f32::from(u16::from_ne_bytes([b[0], b[1]])) / 65535.0,
+        _ => f32::from_ne_bytes([b[0], b[1], b[2], b[3]]),
+    };
+    let pixels = image
+        .pixels
+        .chunks_exact(channels * bytes)
+        .map(|pixel| {
+            // two channel images are gray with alpha
+            let channel = |c: usize| {
+                let c = if channels < 3 { 0 } else { c };
+                sample(&pixel[c * bytes..])
+            };
+            let color = Color::new(channel(0), channel(1), channel(2));
+            // float images are already linear
+            if srgb && bytes < 4 {
+                color.from_srgb()
+            } else {
+                color
+            }
+        })
+        .collect();
+    Some(Texture::new(width, height, pixels))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Packs a glTF document and its binary buffer into a `.glb` file.
+    fn glb(json: &str, bin: &[u8]) -> Vec<u8> {
+        let mut json = json.as_bytes().to_vec();
+        while !json.len().is_multiple_of(4) {
+            json.push(b' ');
+        }
+        let mut bin = bin.to_vec();
+        while !bin.len().is_multiple_of(4) {
+            bin.push(0);
+        }
+        let length = 12 + 8 + json.len() + 8 + bin.len();
+        let mut data = Vec::new();
+        data.extend_from_slice(b"glTF");
+        data.extend_from_slice(&2u32.to_le_bytes());
+        data.extend_from_slice(&(length as u32).to_le_bytes());
+        data.extend_from_slice(&(json.len() as u32).to_le_bytes());
+        data.extend_from_slice(b"JSON");
+        data.extend_from_slice(&json);
+        data.extend_from_slice(&(bin.len() as u32).to_le_bytes());
+        data.extend_from_slice(b"BIN\0");
+        data.extend_from_slice(&bin);
+        data
+    }
+
+    /// A square facing +z, as positions, normals and indices.
+    fn square(indices: &[u16]) -> Vec<u8> {
+        let positions = [
+            [-1.0f32, -1.0, 0.0],
+            [1.0, -1.0, 0.0],
+            [1.0, 1.0, 0.0],
+            [-1.0, 1.0, 0.0],
+        ];
+        let normals = [[0.0f32, 0.0, 1.0]; 4];
+        let mut bin = Vec::new();
+        for x in positions.iter().chain(&normals).flatten() {
+            bin.extend_from_slice(&x.to_le_bytes());
+        }
+        for i in indices {
+            bin.extend_from_slice(&i.to_le_bytes());
+        }
+        bin
+    }
+
+    const DOCUMENT: &str = r#"{
+        "asset": {"version": "2.0"},
+        "extensionsUsed": ["KHR_lights_punctual"],
+        "extensions": {"KHR_lights_punctual": {"lights": [
+            {"type": "point", "color": [1.0, 0.5, 0.25], "intensity": 2.0},
+            {"type": "spot", "spot": {"innerConeAngle": 0.1, "outerConeAngle": 0.5}}
+        ]}},
+        "scene": 0,
+        "scenes": [{"nodes": [0, 1, 2, 3, 4]}],
+        "nodes": [
+            {"name": "square", "mesh": 0, "translation": [0.0, 0.0, -5.0]},
+            {"name": "copy", "mesh": 0, "translation": [10.0, 0.0, -5.0]},
+            {"name": "camera", "camera": 0, "translation": [0.0, 0.0, 1.0]},
+            {"name": "lamp", "translation": [0.0, 2.0, 0.0],
+             "extensions": {"KHR_lights_punctual": {"light": 0}}},
+            {"name": "spot", "extensions": {"KHR_lights_punctual": {"light": 1}}}
+        ],
+        "cameras": [{"type": "perspective", "perspective": {"yfov": 0.8, "znear": 0.1}}],
+        "materials": [{"pbrMetallicRoughness": {
+            "baseColorFactor": [0.5, 0.25, 1.0, 1.0], "metallicFactor": 0.0, "roughnessFactor": 0.5
+        }}],
+        "meshes": [{"primitives": [
+            {"attributes": {"POSITION": 0, "NORMAL": 1}, "indices": 2, "material": 0}
+        ]}],
+        "buffers": [{"byteLength": 108}],
+        "bufferViews": [
+            {"buffer": 0, "byteOffset": 0, "byteLength": 48},
+            {"buffer": 0, "byteOffset": 48, "byteLength": 48},
+            {"buffer": 0, "byteOffset": 96, "byteLength": 12}
+        ],
+        "accessors": [
+            {"bufferView": 0, "componentType": 5126, "count": 4, "type": "VEC3",
+             "min": [-1.0, -1.0, 0.0], "max": [1.0, 1.0, 0.0]},
+            {"bufferView": 1, "componentType": 5126, "count": 4, "type": "VEC3"},
+            {"bufferView": 2, "componentType": 5123, "count": 6, "type": "SCALAR"}
+        ]
+    }"#;
+
+    #[test]
+    fn test_import() {
+        let mut scene = Scene::new(4, 3, 90.0, 1, Color::default());
+        let data = glb(DOCUMENT, &square(&[0, 1, 2, 0, 2, 3]));
+        scene.import_gltf_slice("model", &data).unwrap();
+
+        // the camera sits at z = 1 looking down -z, which mirrors to z = -1 looking down +z
+        assert!((scene.fov - 0.8f32.to_degrees()).abs() < 1e-4);
+        assert!((scene.camera().eye() - Point3::new(0.0, 0.0, -1.0)).norm() < 1e-6);
+        assert!((scene.camera().target() - Point3::new(0.0, 0.0, 0.0)).norm() < 1e-6);
+
+        let ray = Ray::new(Point3::new(0.5, 0.5, -1.0), Vector3::new(0.0, 0.0, 1.0));
+        let hit = scene.trace(&ray, 0).unwrap();
+        assert!((hit.surface.t - 6.0).abs() < 1e-5);
+        assert!(hit.surface.front_face);
+        match scene.material(hit.elem.material()) {
+            Material::Pbr(pbr) => {
+                assert_eq!(pbr.roughness, 0.5);
+                assert_eq!(pbr.base_color.at(&hit.surface), Color::new(0.5, 0.25, 1.0));
+            }
+            material => panic!("expected a Pbr material, got {:?}", material),
+        }
+
+        // both nodes use the same mesh
+        let square = &scene.node("model/square").unwrap().geometry[0];
+        let copy = &scene.node("model/copy").unwrap().geometry[0];
+        assert!(Arc::ptr_eq(square, copy));
+        assert_eq!(scene.materials.len(), 1);
+
+        let lights = scene.all_lights().collect::<Vec<_>>();
+        match lights[0] {
+            Light::Spherical(light) => {
+                assert!((light.pos - Point3::new(0.0, 2.0, 0.0)).norm() < 1e-6);
+                assert!((light.intensity - 8.0 * PI).abs() < 1e-5);
+                assert_eq!(light.color, Color::new(1.0, 0.5, 0.25));
+            }
+            light => panic!("expected a spherical light, got {:?}", light),
+        }
+        match lights[1] {
+            Light::Spot(light) => {
+                assert!((light.direction - Vector3::new(0.0, 0.0, 1.0)).norm() < 1e-6);
+                assert_eq!((light.inner_angle, light.outer_angle), (0.1, 0.5));
+            }
+            light => panic!("expected a spot light, got {:?}", light),
+        }
+    }
+
+    #[test]
+    fn test_errors() {
+        let mut scene = Scene::new(4, 3, 90.0, 1, Color::default());
+        let data = glb(DOCUMENT, &square(&[0, 1, 2, 0, 2, 7]));
+        match scene.import_gltf_slice("model", &data) {
+            Err(ImportError::Invalid(message)) => {
+                assert_eq!(message, "primitive 0 of mesh 0 refers to a missing vertex")
+            }
+            result => panic!("expected an invalid file, got {:?}", result),
+        }
+
+        assert!(matches!(
+            scene.import_gltf_slice("model", b"not a glTF file"),
+            Err(ImportError::Gltf(_))
+        ));
+        assert!(matches!(
+            scene.import_gltf("/nonexistent/model.gltf"),
+            Err(ImportError::Gltf(_))
+        ));
+    }
+
+    #[test]
+    fn test_texture_coordinates() {
+        // a red and green image, with the first set of coordinates on red and the second on green
+        let mut bin = square(&[0, 1, 2, 0, 2, 3]);
+        for u in &[0.25f32, 0.75] {
+            for x in [*u, 0.5].iter().cycle().take(8) {
+                bin.extend_from_slice(&x.to_le_bytes());
+            }
+        }
+        let mut encoder = png::Encoder::new(&mut bin, 2, 1);
+        encoder.set_color(png::ColorType::Rgb);
+        let mut writer = encoder.write_header().unwrap();
+        writer.write_image_data(&[255, 0, 0, 0, 255, 0]).unwrap();
+        writer.finish().unwrap();
+        let png_length = bin.len() - 172;
+
+        let document = |base: u32, metallic: u32| {
+            format!(
+                r#"{{
+                "asset": {{"version": "2.0"}},
+                "scene": 0,
+                "scenes": [{{"nodes": [0]}}],
+                "nodes": [{{"mesh": 0, "translation": [0.0, 0.0, -5.0]}}],
+                "materials": [{{"pbrMetallicRoughness": {{
+                    "baseColorTexture": {{"index": 0, "texCoord": {}}},
+                    "metallicRoughnessTexture": {{"index": 0, "texCoord": {}}}
+                }}}}],
+                "textures": [{{"source": 0}}],
+                "images": [{{"bufferView": 5, "mimeType": "image/png"}}],
+                "meshes": [{{"primitives": [{{"attributes":
+                    {{"POSITION": 0, "NORMAL": 1, "TEXCOORD_0": 3, "TEXCOORD_1": 4}},
+                    "indices": 2, "material": 0}}]}}],
+                "buffers": [{{"byteLength": {}}}],
+                "bufferViews": [
+                    {{"buffer": 0, "byteOffset": 0, "byteLength": 48}},
+                    {{"buffer": 0, "byteOffset": 48, "byteLength": 48}},
+                    {{"buffer": 0, "byteOffset": 96, "byteLength": 12}},
+                    {{"buffer": 0, "byteOffset": 108, "byteLength": 32}},
+                    {{"buffer": 0, "byteOffset": 140, "byteLength": 32}},
+                    {{"buffer": 0, "byteOffset": 172, "byteLength": {}}}
+                ],
+                "accessors": [
+                    {{"bufferView": 0, "componentType": 5126, "count": 4, "type": "VEC3",
+                     "min": [-1.0, -1.0, 0.0], "max": [1.0, 1.0, 0.0]}},
+                    {{"bufferView": 1, "componentType": 5126, "count": 4, "type": "VEC3"}},
+                    {{"bufferView": 2, "componentType": 5123, "count": 6, "type": "SCALAR"}},
+                    {{"bufferView": 3, "componentType": 5126, "count": 4, "type": "VEC2"}},
+                    {{"bufferView": 4, "componentType": 5126, "count": 4, "type": "VEC2"}}
+                ]
+            }}"#,
+                base,
+                metallic,
+                bin.len(),
+                png_length
+            )
+        };
+
+        let mut scene = Scene::new(4, 3, 90.0, 1, Color::default());
+        scene
+            .import_gltf_slice("model", &glb(&document(1, 1), &bin))
+            .unwrap();
+        let ray = Ray::new(Point3::new(0.5, 0.5, -1.0), Vector3::new(0.0, 0.0, 1.0));
+        let hit = scene.trace(&ray, 0).unwrap();
+        match scene.material(hit.elem.material()) {
+            Material::Pbr(pbr) => {
+                assert_eq!(pbr.base_color.at(&hit.surface), Color::new(0.0, 1.0, 0.0))
+            }
+            material => panic!("expected a Pbr material, got {:?}", material),
+        }
+
+        let mut scene = Scene::new(4, 3, 90.0, 1, Color::default());
+        match scene.import_gltf_slice("model", &glb(&document(1, 0), &bin)) {
+            Err(ImportError::Invalid(message)) => assert_eq!(
+                message,
+                "the textures of material 0 use different texture coordinates"
+            ),
+            result => panic!("expected an invalid file, got {:?}", result),
+        }
+    }
+
+    #[test]
+    fn test_image_texture() {
+        let image = image::Data {
+            pixels: vec![255, 0, 0, 128, 0, 255, 0, 128],
+            format: Format::R8G8B8A8,
+            width: 2,
+            height: 1,
+        };
+        let texture = image_texture(&image, false).unwrap();
+        assert_eq!(
+            texture.pixels,
+            vec![Color::new(1.0, 0.0, 0.0), Color::new(0.0, 1.0, 0.0)]
+        );
+
+        let image = image::Data {
+            pixels: vec![0, 0, 255, 255],
+            format: Format::R16,
+            width: 2,
+            height: 1,
+        };
+        let texture = image_texture(&image, false).unwrap();
+        assert_eq!(texture.pixels[1], Color::new(1.0, 1.0, 1.0));
+        assert!(image_texture(&image::Data { width: 3, ..image }, false).is_none());
+    }
+}
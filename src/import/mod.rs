@@ -0,0 +1,54 @@
+//! Loading models and scenes made with other tools.
+
+mod gltf;
+mod ply;
+mod stl;
+
+use std::error::Error;
+use std::fmt;
+use std::io;
+
+/// Errors that can occur while importing a file.
+#[derive(Debug)]
+pub enum ImportError {
+    Io(io::Error),
+    /// The glTF file couldn't be read or parsed
+    Gltf(::gltf::Error),
+    /// The file stops early, in the middle of the thing described
+    Truncated(String),
+    /// The file was read, but describes something the crate can't use
+    Invalid(String),
+}
+
+impl fmt::Display for ImportError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ImportError::Io(e) => write!(f, "couldn't read file: {}", e),
+            ImportError::Gltf(e) => write!(f, "couldn't read glTF: {}", e),
+            ImportError::Truncated(what) => write!(f, "the file ends in the middle of {}", what),
+            ImportError::Invalid(message) => f.write_str(message),
+        }
+    }
+}
+
+impl Error for ImportError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            ImportError::Io(e) => Some(e),
+            ImportError::Gltf(e) => Some(e),
+            ImportError::Truncated(_) | ImportError::Invalid(_) => None,
+        }
+    }
+}
+
+impl From<io::Error> for ImportError {
+    fn from(e: io::Error) -> Self {
+        ImportError::Io(e)
+    }
+}
+
+impl From<::gltf::Error> for ImportError {
+    fn from(e: ::gltf::Error) -> Self {
+        ImportError::Gltf(e)
+    }
+}
@@ -0,0 +1,511 @@
+use nalgebra::*;
+use std::fs::File;
+use std::io::{self, BufRead, BufReader, Read};
+use std::path::Path;
+use std::str::SplitWhitespace;
+
+use super::ImportError;
+use crate::prelude::*;
+
+impl Mesh {
+    /// Reads a mesh from a PLY file.  See `from_ply`.
+    pub fn load_ply<P>(path: P, material: MaterialId) -> Result<Mesh, ImportError>
+    where
+        P: AsRef<Path>,
+    {
+        Mesh::from_ply(BufReader::new(File::open(path)?), material)
+    }
+
+    /// Reads a mesh from a PLY file, in ASCII or either binary byte order.  Besides `x`, `y` and
+    /// `z`, vertices can have normals (`nx`, `ny`, `nz`), texture coordinates (`u`, `v` or `s`,
+    /// `t`) and colors (`red`, `green`, `blue`), which are kept for `ColorSource::Vertex`.
+    /// Faces with more than three corners are split into triangles, and any other elements are
+    /// skipped.
+    pub fn from_ply<R: BufRead>(mut reader: R, material: MaterialId) -> Result<Mesh, ImportError> {
+        let (format, elements) = read_header(&mut reader)?;
+        let mut ply = PlyMesh::default();
+        match format {
+            PlyFormat::Ascii => {
+                let mut text = String::new();
+                reader
+                    .read_to_string(&mut text)
+                    .map_err(|_| ImportError::Invalid("the PLY data isn't text".to_string()))?;
+                ply.read(&elements, &mut AsciiValues(text.split_whitespace()))?;
+            }
+            PlyFormat::BinaryLittleEndian | PlyFormat::BinaryBigEndian => {
+                let mut values = BinaryValues {
+                    reader,
+                    big_endian: format == PlyFormat::BinaryBigEndian,
+                };
+                ply.read(&elements, &mut values)?;
+            }
+        }
+        ply.into_mesh(material)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum PlyFormat {
+    Ascii,
+    BinaryLittleEndian,
+    BinaryBigEndian,
+}
+
+/// The type of a single value in a PLY file.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Scalar {
+    I8,
+    U8,
+    I16,
+    U16,
+    I32,
+    U32,
+    F32,
+    F64,
+}
+
+impl Scalar {
+    fn parse(name: &str) -> Option<Scalar> {
+        Some(match name {
+            "char" | "int8" => Scalar::I8,
+            "uchar" | "uint8" => Scalar::U8,
+            "short" | "int16" => Scalar::I16,
+            "ushort" | "uint16" => Scalar::U16,
+            "int" | "int32" => Scalar::I32,
+            "uint" | "uint32" => Scalar::U32,
+            "float" | "float32" => Scalar::F32,
+            "double" | "float64" => Scalar::F64,
+            _ => return None,
+        })
+    }
+
+    fn size(self) -> usize {
+        match self {
+            Scalar::I8 | Scalar::U8 => 1,
+            Scalar::I16 | Scalar::U16 => 2,
+            Scalar::I32 | Scalar::U32 | Scalar::F32 => 4,
+            Scalar::F64 => 8,
+        }
+    }
+
+    /// The largest value of an unsigned integer type, which colors stored in that type are
+    /// scaled by.  Colors stored as floats are already between 0 and 1.
+    fn color_scale(self) -> Option<f64> {
+        match self {
+            Scalar::U8 => Some(255.0),
+            Scalar::U16 => Some(65535.0),
+            Scalar::U32 => Some(4_294_967_295.0),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+enum Property {
+    Scalar(String, Scalar),
+    /// A list of values, preceded by how many there are
+    List(String, Scalar, Scalar),
+}
+
+impl Property {
+    fn name(&self) -> &str {
+        match self {
+            Property::Scalar(name, _) | Property::List(name, ..) => name,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+struct Element {
+    name: String,
+    count: usize,
+    properties: Vec<Property>,
+}
+
+impl Element {
+    fn property(&self, name: &str) -> Option<usize> {
+        self.properties.iter().position(|p| p.name() == name)
+    }
+}
+
+fn read_header<R: BufRead>(reader: &mut R) -> Result<(PlyFormat, Vec<Element>), ImportError> {
+    let invalid = |message: String| ImportError::Invalid(message);
+    let mut line = Vec::new();
+    let mut next_line = |reader: &mut R| -> Result<String, ImportError> {
+        line.clear();
+        if reader.read_until(b'\n', &mut line)? == 0 {
+            return Err(ImportError::Truncated("the header".to_string()));
+        }
+        Ok(String::from_utf8_lossy(&line).trim().to_string())
+    };
+
+    if next_line(reader)? != "ply" {
+        return Err(invalid("not a PLY file".to_string()));
+    }
+    let mut format = None;
+    let mut elements: Vec<Element> = Vec::new();
+    loop {
+        let line = next_line(reader)?;
+        let words = line.split_whitespace().collect::<Vec<_>>();
+        let scalar = |name: &str| {
+            Scalar::parse(name).ok_or_else(|| invalid(format!("unknown PLY type `{}`", name)))
+        };
+        match words.as_slice() {
+            [] | ["comment", ..] | ["obj_info", ..] => {}
+            ["end_header"] => break,
+            ["format", name, _version] => {
+                format = Some(match *name {
+                    "ascii" => PlyFormat::Ascii,
+                    "binary_little_endian" => PlyFormat::BinaryLittleEndian,
+                    "binary_big_endian" => PlyFormat::BinaryBigEndian,
+                    _ => return Err(invalid(format!("unknown PLY format `{}`", name))),
+                })
+            }
+            ["element", name, count] => elements.push(Element {
+                name: name.to_string(),
+                count: count
+                    .parse()
+                    .map_err(|_| invalid(format!("`{}` isn't a number of elements", count)))?,
+                properties: Vec::new(),
+            }),
+            ["property", "list", count, item, name] => elements
+                .last_mut()
+                .ok_or_else(|| invalid("a property comes before any element".to_string()))?
+                .properties
+                .push(Property::List(
+                    name.to_string(),
+                    scalar(count)?,
+                    scalar(item)?,
+                )),
+            ["property", ty, name] => elements
+                .last_mut()
+                .ok_or_else(|| invalid("a property comes before any element".to_string()))?
+                .properties
+                .push(Property::Scalar(name.to_string(), scalar(ty)?)),
+            _ => return Err(invalid(format!("unknown PLY header line `{}`", line))),
+        }
+    }
+    let format = format.ok_or_else(|| invalid("the PLY header has no format".to_string()))?;
+    Ok((format, elements))
+}
+
+/// Reads the values in the body of a PLY file one at a time.
+trait Values {
+    /// Reads the next value, or returns `None` at the end of the file.
+    fn next(&mut self, scalar: Scalar) -> Result<Option<f64>, ImportError>;
+}
+
+struct AsciiValues<'a>(SplitWhitespace<'a>);
+
+impl Values for AsciiValues<'_> {
+    fn next(&mut self, _: Scalar) -> Result<Option<f64>, ImportError> {
+        self.0
+            .next()
+            .map(|word| {
+                word.parse()
+                    .map_err(|_| ImportError::Invalid(format!("`{}` isn't a number", word)))
+            })
+            .transpose()
+    }
+}
+
+struct BinaryValues<R> {
+    reader: R,
+    big_endian: bool,
+}
+
+impl<R: Read> Values for BinaryValues<R> {
+    fn next(&mut self, scalar: Scalar) -> Result<Option<f64>, ImportError> {
+        let mut bytes = [0; 8];
+        let bytes = &mut bytes[..scalar.size()];
+        match self.reader.read_exact(bytes) {
+            Ok(()) => {}
+            Err(e) if e.kind() == io::ErrorKind::UnexpectedEof => return Ok(None),
+            Err(e) => return Err(e.into()),
+        }
+        if self.big_endian {
+            bytes.reverse();
+        }
+        // the bytes are now little endian
+        let b = &*bytes;
+        Ok(Some(match scalar {
+            Scalar::I8 => f64::from(b[0] as i8),
+            Scalar::U8 => f64::from(b[0]),
+            Scalar::I16 => f64::from(i16::from_le_bytes([b[0], b[1]])),
+            Scalar::U16 => f64::from(u16::from_le_bytes([b[0], b[1]])),
+            Scalar::I32 => f64::from(i32::from_le_bytes([b[0], b[1], b[2], b[3]])),
+            Scalar::U32 => f64::from(u32::from_le_bytes([b[0], b[1], b[2], b[3]])),
+            Scalar::F32 => f64::from(f32::from_le_bytes([b[0], b[1], b[2], b[3]])),
+            Scalar::F64 => f64::from_le_bytes([b[0], b[1], b[2], b[3], b[4], b[5], b[6], b[7]]),
+        }))
+    }
+}
+
+/// The parts of a PLY file that make up a mesh, gathered while reading it.
+#[derive(Debug, Default)]
+struct PlyMesh {
+    positions: Vec<Point3<f32>>,
+    normals: Vec<Vector3<f32>>,
+    uvs: Vec<Point2<f32>>,
+    colors: Vec<Color>,
+    /// The corners of each face, as they appear in the file
+    faces: Vec<Vec<f64>>,
+}
+
+/// Where to find each part of a vertex among the properties of the vertex element.
+struct VertexLayout {
+    position: [usize; 3],
+    normal: Option<[usize; 3]>,
+    uv: Option<[usize; 2]>,
+    /// Also the type to scale integer colors by
+    color: Option<([usize; 3], Scalar)>,
+}
+
+impl VertexLayout {
+    fn new(element: &Element) -> Result<VertexLayout, ImportError> {
+        let find = |names: &[&str]| -> Option<Vec<usize>> {
+            names.iter().map(|name| element.property(name)).collect()
+        };
+        let three = |names: &[&str]| find(names).map(|i| [i[0], i[1], i[2]]);
+        let two = |names: &[&str]| find(names).map(|i| [i[0], i[1]]);
+        let position = three(&["x", "y", "z"])
+            .ok_or_else(|| ImportError::Invalid("PLY vertices need x, y and z".to_string()))?;
+        let color = three(&["red", "green", "blue"]).map(|i| match element.properties[i[0]] {
+            Property::Scalar(_, scalar) => (i, scalar),
+            Property::List(_, _, scalar) => (i, scalar),
+        });
+        Ok(VertexLayout {
+            position,
+            normal: three(&["nx", "ny", "nz"]),
+            uv: two(&["u", "v"])
+                .or_else(|| two(&["s", "t"]))
+                .or_else(|| two(&["texture_u", "texture_v"])),
+            color,
+        })
+    }
+}
+
+impl PlyMesh {
+    fn read<V: Values>(&mut self, elements: &[Element], values: &mut V) -> Result<(), ImportError> {
+        for element in elements {
+            let layout = match element.name.as_str() {
+                "vertex" => Some(VertexLayout::new(element)?),
+                _ => None,
+            };
+            let face_indices = match element.name.as_str() {
+                "face" => element
+                    .property("vertex_indices")
+                    .or_else(|| element.property("vertex_index")),
+                _ => None,
+            };
+
+            let mut scalars = vec![0.0; element.properties.len()];
+            for i in 0..element.count {
+                let truncated = || ImportError::Truncated(format!("{} {}", element.name, i));
+                for (p, property) in element.properties.iter().enumerate() {
+                    match *property {
+                        Property::Scalar(_, scalar) => {
+                            scalars[p] = values.next(scalar)?.ok_or_else(truncated)?;
+                        }
+                        Property::List(_, count, item) => {
+                            let count = values.next(count)?.ok_or_else(truncated)?;
+                            if count < 0.0 || count.fract() != 0.0 {
+                                return Err(ImportError::Invalid(format!(
+                                    "{} {} has a list of length {}",
+                                    element.name, i, count
+                                )));
+                            }
+                            let list = (0..count as usize)
+                                .map(|_| values.next(item)?.ok_or_else(truncated))
+                                .collect::<Result<Vec<_>, _>>()?;
+                            if face_indices == Some(p) {
+                                self.faces.push(list);
+                            }
+                        }
+                    }
+                }
+                if let Some(layout) = &layout {
+                    self.add_vertex(layout, &scalars);
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn add_vertex(&mut self, layout: &VertexLayout, values: &[f64]) {
+        let get = |i: usize| values[i] as f32;
+        let [x, y, z] = layout.position;
+        self.positions.push(Point3::new(get(x), get(y), get(z)));
+        if let Some([x, y, z]) = layout.normal {
+            self.normals.push(Vector3::new(get(x), get(y), get(z)));
+        }
+        if let Some([u, v]) = layout.uv {
+            // PLY texture coordinates start at the bottom left
+            self.uvs.push(Point2::new(get(u), 1.0 - get(v)));
+        }
+        if let Some(([r, g, b], scalar)) = layout.color {
+            let color = match scalar.color_scale() {
+                // integer colors are the 8 bit sRGB values from scanners and paint programs
+                Some(scale) => Color::new(
+                    (values[r] / scale) as f32,
+                    (values[g] / scale) as f32,
+                    (values[b] / scale) as f32,
+                )
+                .from_srgb(),
+                None => Color::new(get(r), get(g), get(b)),
+            };
+            self.colors.push(color);
+        }
+    }
+
+    fn into_mesh(self, material: MaterialId) -> Result<Mesh, ImportError> {
+        let count = self.positions.len();
+        let mut triangles = Vec::new();
+        for (i, face) in self.faces.iter().enumerate() {
+            let corners = face
+                .iter()
+                .map(|&v| {
+                    if v >= 0.0 && v.fract() == 0.0 && (v as usize) < count {
+                        Ok(v as usize)
+                    } else {
+                        Err(ImportError::Invalid(format!(
+                            "face {} refers to a missing vertex {}",
+                            i, v
+                        )))
+                    }
+                })
+                .collect::<Result<Vec<_>, _>>()?;
+            // split polygons into a fan of triangles around their first corner
+            for j in 1..corners.len().saturating_sub(1) {
+                triangles.push([corners[0], corners[j], corners[j + 1]]);
+            }
+        }
+
+        let mut mesh = Mesh::new(self.positions, triangles, material);
+        if !self.normals.is_empty() {
+            mesh = mesh.with_normals(self.normals);
+        }
+        if !self.uvs.is_empty() {
+            mesh = mesh.with_uvs(self.uvs);
+        }
+        if !self.colors.is_empty() {
+            mesh = mesh.with_colors(self.colors);
+        }
+        Ok(mesh)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Cursor;
+
+    const HEADER: &str = "element vertex 4
+property float x
+property float y
+property float z
+property uchar red
+property uchar green
+property uchar blue
+element face 1
+property list uchar int vertex_indices
+end_header
+";
+
+    /// A square in the z = 5 plane, with a red, a green, a blue and a white corner.
+    const VERTICES: [([f32; 3], [u8; 3]); 4] = [
+        ([0.0, 0.0, 5.0], [255, 0, 0]),
+        ([1.0, 0.0, 5.0], [0, 255, 0]),
+        ([1.0, 1.0, 5.0], [0, 0, 255]),
+        ([0.0, 1.0, 5.0], [255, 255, 255]),
+    ];
+
+    fn binary(big_endian: bool) -> Vec<u8> {
+        let format = if big_endian { "big" } else { "little" };
+        let mut data = format!("ply\nformat binary_{}_endian 1.0\n{}", format, HEADER).into_bytes();
+        for (position, color) in &VERTICES {
+            for x in position {
+                if big_endian {
+                    data.extend_from_slice(&x.to_be_bytes());
+                } else {
+                    data.extend_from_slice(&x.to_le_bytes());
+                }
+            }
+            data.extend_from_slice(color);
+        }
+        data.push(4);
+        for i in 0..4i32 {
+            if big_endian {
+                data.extend_from_slice(&i.to_be_bytes());
+            } else {
+                data.extend_from_slice(&i.to_le_bytes());
+            }
+        }
+        data
+    }
+
+    fn check(mesh: &Mesh) {
+        assert_eq!(mesh.vertex_count(), 4);
+        assert_eq!(mesh.triangles, vec![[0, 1, 2], [0, 2, 3]]);
+        assert_eq!(mesh.positions[2], Point3::new(1.0, 1.0, 5.0));
+        let colors = mesh.colors.as_ref().unwrap();
+        assert_eq!(colors[1], Color::new(0.0, 1.0, 0.0));
+
+        // a blend of the corners of the first triangle
+        let ray = Ray::new(Point3::new(0.6, 0.4, 0.0), Vector3::new(0.0, 0.0, 1.0));
+        let hit = mesh.intersect(&ray).unwrap();
+        let color = hit.color.unwrap();
+        assert!((color.red - 0.4).abs() < 1e-5);
+        assert!((color.green - 0.2).abs() < 1e-5 && (color.blue - 0.4).abs() < 1e-5);
+    }
+
+    #[test]
+    fn test_ascii() {
+        let data = format!(
+            "ply\nformat ascii 1.0\ncomment made by hand\n{}\
+             0 0 5 255 0 0\n1 0 5 0 255 0\n1 1 5 0 0 255\n0 1 5 255 255 255\n4 0 1 2 3\n",
+            HEADER
+        );
+        check(&Mesh::from_ply(Cursor::new(data), MaterialId(0)).unwrap());
+    }
+
+    #[test]
+    fn test_binary() {
+        for &big_endian in &[false, true] {
+            let data = binary(big_endian);
+            check(&Mesh::from_ply(Cursor::new(data), MaterialId(0)).unwrap());
+        }
+    }
+
+    #[test]
+    fn test_errors() {
+        let error = |data: Vec<u8>| Mesh::from_ply(Cursor::new(data), MaterialId(0)).unwrap_err();
+
+        let mut data = binary(false);
+        data.truncate(data.len() - 6);
+        match error(data) {
+            ImportError::Truncated(what) => assert_eq!(what, "face 0"),
+            e => panic!("expected a truncated file, got {:?}", e),
+        }
+        match error(b"ply\nformat ascii 1.0\nelement vertex 3\n".to_vec()) {
+            ImportError::Truncated(what) => assert_eq!(what, "the header"),
+            e => panic!("expected a truncated file, got {:?}", e),
+        }
+        let data = format!("ply\nformat ascii 1.0\n{}0 0 5 255 0 0\n1 0", HEADER);
+        match error(data.into_bytes()) {
+            ImportError::Truncated(what) => assert_eq!(what, "vertex 1"),
+            e => panic!("expected a truncated file, got {:?}", e),
+        }
+
+        let data = format!(
+            "ply\nformat ascii 1.0\n{}{}3 0 1 7\n",
+            HEADER,
+            "0 0 0 0 0 0\n".repeat(4)
+        );
+        assert!(matches!(error(data.into_bytes()), ImportError::Invalid(_)));
+        assert!(matches!(
+            error(b"solid cube\n".to_vec()),
+            ImportError::Invalid(_)
+        ));
+    }
+}
@@ -0,0 +1,213 @@
+use nalgebra::*;
+use std::fs::File;
+use std::io::{BufReader, Read};
+use std::path::Path;
+
+use super::ImportError;
+use crate::prelude::*;
+
+/// Bytes taken up by the header and triangle count of a binary STL file
+const BINARY_HEADER: usize = 84;
+/// Bytes taken up by each triangle of a binary STL file
+const BINARY_TRIANGLE: usize = 50;
+
+impl Mesh {
+    /// Reads a mesh from an STL file.  See `from_stl`.
+    pub fn load_stl<P>(path: P, material: MaterialId) -> Result<Mesh, ImportError>
+    where
+        P: AsRef<Path>,
+    {
+        Mesh::from_stl(BufReader::new(File::open(path)?), material)
+    }
+
+    /// Reads a mesh from an STL file, in either its ASCII or binary form.  STL files don't share
+    /// vertices between triangles, so every triangle gets three vertices of its own.  The
+    /// stored facet normals are ignored in favor of the winding order, since many exporters
+    /// leave them zeroed.
+    pub fn from_stl<R: Read>(mut reader: R, material: MaterialId) -> Result<Mesh, ImportError> {
+        let mut data = Vec::new();
+        reader.read_to_end(&mut data)?;
+        let positions = if is_ascii(&data) {
+            read_ascii(&String::from_utf8_lossy(&data))?
+        } else {
+            read_binary(&data)?
+        };
+        let triangles = (0..positions.len() / 3)
+            .map(|i| [3 * i, 3 * i + 1, 3 * i + 2])
+            .collect();
+        Ok(Mesh::new(positions, triangles, material))
+    }
+}
+
+/// ASCII files start with `solid`, but so do some binary ones.  A file that is exactly the size
+/// its triangle count calls for, or that has bytes which can't be text, is taken to be binary.
+fn is_ascii(data: &[u8]) -> bool {
+    if data.len() >= BINARY_HEADER {
+        let count = u32::from_le_bytes([data[80], data[81], data[82], data[83]]) as usize;
+        if BINARY_HEADER + count * BINARY_TRIANGLE == data.len() {
+            return false;
+        }
+    }
+    let start = &data[..data.len().min(512)];
+    let text = start
+        .iter()
+        .all(|&b| b.is_ascii_graphic() || b.is_ascii_whitespace());
+    text && String::from_utf8_lossy(start)
+        .trim_start()
+        .starts_with("solid")
+}
+
+fn read_binary(data: &[u8]) -> Result<Vec<Point3<f32>>, ImportError> {
+    if data.len() < BINARY_HEADER {
+        return Err(ImportError::Truncated("the header".to_string()));
+    }
+    let count = u32::from_le_bytes([data[80], data[81], data[82], data[83]]) as usize;
+    let mut positions = Vec::new();
+    for i in 0..count {
+        let start = BINARY_HEADER + i * BINARY_TRIANGLE;
+        let triangle = data
+            .get(start..start + BINARY_TRIANGLE)
+            .ok_or_else(|| ImportError::Truncated(format!("triangle {}", i)))?;
+        // the facet normal comes first, and a two byte attribute last
+        let float = |offset: usize| {
+            let b = &triangle[offset..offset + 4];
+            f32::from_le_bytes([b[0], b[1], b[2], b[3]])
+        };
+        for corner in 0..3 {
+            let offset = 12 + 12 * corner;
+            positions.push(Point3::new(
+                float(offset),
+                float(offset + 4),
+                float(offset + 8),
+            ));
+        }
+    }
+    Ok(positions)
+}
+
+fn read_ascii(text: &str) -> Result<Vec<Point3<f32>>, ImportError> {
+    let mut words = text.split_whitespace();
+    let mut positions = Vec::new();
+    let mut facet = 0;
+    loop {
+        match words.next() {
+            None => return Err(ImportError::Truncated("the solid".to_string())),
+            Some("endsolid") => break,
+            Some("facet") => {
+                let start = positions.len();
+                let truncated = || ImportError::Truncated(format!("facet {}", facet));
+                loop {
+                    match words.next().ok_or_else(truncated)? {
+                        "endfacet" => break,
+                        "vertex" => {
+                            let mut number = || -> Result<f32, ImportError> {
+                                let word = words.next().ok_or_else(truncated)?;
+                                word.parse().map_err(|_| {
+                                    ImportError::Invalid(format!("`{}` isn't a number", word))
+                                })
+                            };
+                            positions.push(Point3::new(number()?, number()?, number()?));
+                        }
+                        // the normal and its numbers, and the `outer loop` around the vertices
+                        _ => {}
+                    }
+                }
+                if positions.len() - start != 3 {
+                    return Err(ImportError::Invalid(format!(
+                        "facet {} has {} vertices",
+                        facet,
+                        positions.len() - start
+                    )));
+                }
+                facet += 1;
+            }
+            // `solid` and the solid's name
+            Some(_) => {}
+        }
+    }
+    Ok(positions)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Cursor;
+
+    const ASCII: &str = "solid square
+  facet normal 0 0 -1
+    outer loop
+      vertex 0 0 5
+      vertex 1 1 5
+      vertex 1 0 5
+    endloop
+  endfacet
+  facet normal 0 0 0
+    outer loop
+      vertex 0 0 5
+      vertex 0 1 5
+      vertex 1 1 5
+    endloop
+  endfacet
+endsolid square
+";
+
+    /// The same square as `ASCII`, in binary, with a header that starts with `solid`.
+    fn binary() -> Vec<u8> {
+        let mut data = b"solid square".to_vec();
+        data.resize(80, b' ');
+        data.extend_from_slice(&2u32.to_le_bytes());
+        let corners = [
+            [[0.0f32, 0.0, 5.0], [1.0, 1.0, 5.0], [1.0, 0.0, 5.0]],
+            [[0.0, 0.0, 5.0], [0.0, 1.0, 5.0], [1.0, 1.0, 5.0]],
+        ];
+        for triangle in &corners {
+            data.extend_from_slice(&[0; 12]);
+            for x in triangle.iter().flatten() {
+                data.extend_from_slice(&x.to_le_bytes());
+            }
+            data.extend_from_slice(&[0; 2]);
+        }
+        data
+    }
+
+    fn check(mesh: &Mesh) {
+        assert_eq!(mesh.vertex_count(), 6);
+        assert_eq!(mesh.triangle_count(), 2);
+        let ray = Ray::new(Point3::new(0.25, 0.75, 0.0), Vector3::new(0.0, 0.0, 1.0));
+        let hit = mesh.intersect(&ray).unwrap();
+        assert!((hit.t - 5.0).abs() < 1e-6);
+        // both triangles are wound to face the ray
+        assert!(hit.front_face);
+    }
+
+    #[test]
+    fn test_ascii() {
+        check(&Mesh::from_stl(Cursor::new(ASCII), MaterialId(0)).unwrap());
+    }
+
+    #[test]
+    fn test_binary() {
+        check(&Mesh::from_stl(Cursor::new(binary()), MaterialId(0)).unwrap());
+    }
+
+    #[test]
+    fn test_errors() {
+        let error = |data: &[u8]| Mesh::from_stl(Cursor::new(data), MaterialId(0)).unwrap_err();
+
+        let data = binary();
+        match error(&data[..data.len() - 10]) {
+            ImportError::Truncated(what) => assert_eq!(what, "triangle 1"),
+            e => panic!("expected a truncated file, got {:?}", e),
+        }
+        match error(&ASCII.as_bytes()[..150]) {
+            ImportError::Truncated(what) => assert_eq!(what, "facet 1"),
+            e => panic!("expected a truncated file, got {:?}", e),
+        }
+        match error(b"\x00\x01") {
+            ImportError::Truncated(what) => assert_eq!(what, "the header"),
+            e => panic!("expected a truncated file, got {:?}", e),
+        }
+        let bad = ASCII.replace("vertex 0 1 5", "vertex 0 one 5");
+        assert!(matches!(error(bad.as_bytes()), ImportError::Invalid(_)));
+    }
+}
@@ -1,7 +1,17 @@
+pub mod aov;
+pub mod colorspace;
+pub mod denoise;
+pub mod description;
+pub mod framebuffer;
 pub mod geometry;
+pub mod graph;
+pub mod import;
 pub mod lighting;
 pub mod materials;
 pub mod prelude;
+pub mod progressive;
+pub mod texture;
+pub mod tonemap;
 pub mod util;
 
 use crate::prelude::*;
@@ -1,21 +1,57 @@
 use crate::prelude::*;
 use nalgebra::*;
 
+/// Light arriving at a surface from a single light source.
+#[derive(Debug, Clone)]
+pub struct LightSample {
+    /// Direction the light arrives from, pointing away from the surface
+    pub wi: Vector3<f32>,
+    /// Light arriving along `wi`
+    pub radiance: Color,
+}
+
+#[derive(Debug, Clone)]
 pub enum Light {
     Global(GlobalLight),
     Spherical(SphericalLight),
+    Spot(SpotLight),
 }
 
-impl Colorable for Light {
-    fn color(&self, scene: &Scene, i: &Intersection, depth: u32) -> Color {
+impl Light {
+    /// Determines how much light reaches the surface from this light.  Returns `None` if the
+    /// light is blocked.
+    pub fn illuminate(&self, scene: &Scene, surface: &SurfaceInteraction) -> Option<LightSample> {
         match &self {
-            Light::Global(gl) => gl.color(scene, i, depth),
-            Light::Spherical(sl) => sl.color(scene, i, depth),
+            Light::Global(gl) => gl.illuminate(scene, surface),
+            Light::Spherical(sl) => sl.illuminate(scene, surface),
+            Light::Spot(sl) => sl.illuminate(scene, surface),
+        }
+    }
+
+    /// Moves the light by `transform`.
+    pub fn transformed(&self, transform: &Affine3<f32>) -> Light {
+        match &self {
+            Light::Global(gl) => GlobalLight {
+                direction: transform.transform_vector(&gl.direction).normalize(),
+                ..gl.clone()
+            }
+            .into(),
+            Light::Spherical(sl) => SphericalLight {
+                pos: transform.transform_point(&sl.pos),
+                ..sl.clone()
+            }
+            .into(),
+            Light::Spot(sl) => SpotLight {
+                pos: transform.transform_point(&sl.pos),
+                direction: transform.transform_vector(&sl.direction).normalize(),
+                ..sl.clone()
+            }
+            .into(),
         }
     }
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct GlobalLight {
     pub(crate) direction: Vector3<f32>,
     pub(crate) color: Color,
@@ -25,35 +61,37 @@ pub struct GlobalLight {
 impl GlobalLight {
     pub fn new(direction: Vector3<f32>, color: Color, intensity: f32) -> GlobalLight {
         GlobalLight {
-            direction: direction.normalize(),
+            direction: unit_vector(&direction),
             color,
             intensity,
         }
     }
 }
 
-impl Colorable for GlobalLight {
-    fn color(&self, scene: &Scene, i: &Intersection, depth: u32) -> Color {
-        // basic lambertian lighting
+impl GlobalLight {
+    pub fn illuminate(&self, scene: &Scene, surface: &SurfaceInteraction) -> Option<LightSample> {
         let light_direction = -self.direction;
-        let shadow_ray = Ray::new(i.point, light_direction);
-        let visible = scene.trace(&shadow_ray, depth + 1).is_none();
-        let intensity = if visible { self.intensity } else { 0.0 };
-        let power = i.surface_normal().dot(&light_direction).max(0.0) * intensity;
-        self.color * power
+        if scene.occluded(&surface.spawn_ray(light_direction)) {
+            return None;
+        }
+        Some(LightSample {
+            wi: light_direction,
+            radiance: self.color * self.intensity,
+        })
     }
 }
 
-impl Into<Light> for GlobalLight {
-    fn into(self) -> Light {
-        Light::Global(self)
+impl From<GlobalLight> for Light {
+    fn from(light: GlobalLight) -> Self {
+        Light::Global(light)
     }
 }
 
+#[derive(Debug, Clone)]
 pub struct SphericalLight {
-    pos: Point3<f32>,
-    color: Color,
-    intensity: f32,
+    pub(crate) pos: Point3<f32>,
+    pub(crate) color: Color,
+    pub(crate) intensity: f32,
 }
 
 impl SphericalLight {
@@ -66,26 +104,89 @@ impl SphericalLight {
     }
 }
 
-impl Colorable for SphericalLight {
-    fn color(&self, scene: &Scene, i: &Intersection, depth: u32) -> Color {
-        let light_direction = self.pos - i.point;
+impl SphericalLight {
+    pub fn illuminate(&self, scene: &Scene, surface: &SurfaceInteraction) -> Option<LightSample> {
+        let light_direction = self.pos - surface.point;
+        let norm = light_direction.norm();
+        if scene.occluded(&surface.spawn_ray_to(self.pos)) {
+            return None;
+        }
+        Some(LightSample {
+            wi: light_direction / norm,
+            radiance: self.color * (self.intensity / (4.0 * std::f32::consts::PI * norm)),
+        })
+    }
+}
+
+impl From<SphericalLight> for Light {
+    fn from(light: SphericalLight) -> Self {
+        Light::Spherical(light)
+    }
+}
+
+/// A point light that only shines into a cone around `direction`.  Light is at full strength
+/// within `inner_angle` of the axis and fades out smoothly towards `outer_angle`.
+#[derive(Debug, Clone)]
+pub struct SpotLight {
+    pub(crate) pos: Point3<f32>,
+    pub(crate) direction: Vector3<f32>,
+    pub(crate) color: Color,
+    pub(crate) intensity: f32,
+    /// Angles from the axis, in radians
+    pub(crate) inner_angle: f32,
+    pub(crate) outer_angle: f32,
+}
+
+impl SpotLight {
+    /// Creates a spot light.  Both angles are measured from the axis in radians, and
+    /// `inner_angle` is clamped so it never exceeds `outer_angle`.
+    pub fn new(
+        pos: Point3<f32>,
+        direction: Vector3<f32>,
+        color: Color,
+        intensity: f32,
+        inner_angle: f32,
+        outer_angle: f32,
+    ) -> SpotLight {
+        SpotLight {
+            pos,
+            direction: unit_vector(&direction),
+            color,
+            intensity,
+            inner_angle: inner_angle.min(outer_angle),
+            outer_angle,
+        }
+    }
+
+    /// How much of the light's strength goes out in `direction`, from 0 outside the cone to 1
+    /// inside the inner angle.
+    fn falloff(&self, direction: &Vector3<f32>) -> f32 {
+        let cos_theta = self.direction.dot(direction);
+        let (cos_inner, cos_outer) = (self.inner_angle.cos(), self.outer_angle.cos());
+        if cos_inner - cos_outer <= f32::EPSILON {
+            return if cos_theta >= cos_outer { 1.0 } else { 0.0 };
+        }
+        let t = ((cos_theta - cos_outer) / (cos_inner - cos_outer)).clamp(0.0, 1.0);
+        t * t
+    }
+
+    pub fn illuminate(&self, scene: &Scene, surface: &SurfaceInteraction) -> Option<LightSample> {
+        let light_direction = self.pos - surface.point;
         let norm = light_direction.norm();
-        let shadow_ray = Ray::new(i.point, light_direction);
-        let shadow_intersection = scene.trace(&shadow_ray, depth + 1);
-        let visible = shadow_intersection.is_none() || shadow_intersection.unwrap().dist > norm;
-
-        let intensity = if visible {
-            self.intensity / (4.0 * std::f32::consts::PI * norm)
-        } else {
-            0.0
-        };
-        let power = i.surface_normal().dot(&light_direction).max(0.0) * intensity;
-        self.color * power
+        let wi = light_direction / norm;
+        let falloff = self.falloff(&-wi);
+        if falloff <= 0.0 || scene.occluded(&surface.spawn_ray_to(self.pos)) {
+            return None;
+        }
+        Some(LightSample {
+            wi,
+            radiance: self.color * (falloff * self.intensity / (4.0 * std::f32::consts::PI * norm)),
+        })
     }
 }
 
-impl Into<Light> for SphericalLight {
-    fn into(self) -> Light {
-        Light::Spherical(self)
+impl From<SpotLight> for Light {
+    fn from(light: SpotLight) -> Self {
+        Light::Spot(light)
     }
 }
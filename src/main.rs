@@ -0,0 +1,717 @@
+//! Renders a scene file from the command line.
+
+use clap::{Parser, ValueEnum};
+use raytracer::prelude::*;
+
+use std::error::Error;
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
+use std::ops::Range;
+use std::path::{Path, PathBuf};
+use std::process::ExitCode;
+use std::thread;
+use std::time::{Duration, Instant, SystemTime};
+
+/// Image formats the renderer can write.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+enum Format {
+    Ppm,
+    Png,
+    /// OpenEXR, keeping the full range of the image and any output variables in one file
+    Exr,
+}
+
+impl Format {
+    /// Guesses the format from a file's extension.
+    fn from_path(path: &Path) -> Option<Format> {
+        let extension = path.extension()?.to_str()?.to_ascii_lowercase();
+        match extension.as_str() {
+            "ppm" => Some(Format::Ppm),
+            "png" => Some(Format::Png),
+            "exr" => Some(Format::Exr),
+            _ => None,
+        }
+    }
+}
+
+/// Command line names for `ExrPrecision`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+enum Precision {
+    Half,
+    Float,
+}
+
+/// Command line names for `ExrCompression`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+enum ExrCompressionArg {
+    None,
+    Zip,
+    Piz,
+}
+
+/// Command line names for `ToneMap`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+enum ToneMapArg {
+    Clamp,
+    Reinhard,
+    ExtendedReinhard,
+    Hable,
+    Aces,
+}
+
+/// Renders a scene file to an image.  Options given here override the scene's own settings.
+#[derive(Debug, Parser)]
+#[command(name = "raytracer", version)]
+struct Args {
+    /// The scene file to render
+    scene: PathBuf,
+
+    /// Where to write the image
+    #[arg(short, long, default_value = "render.png")]
+    output: PathBuf,
+
+    /// The image format, guessed from the output's extension if not given
+    #[arg(short, long, value_enum)]
+    format: Option<Format>,
+
+    /// Size of the image, as WIDTHxHEIGHT in pixels
+    #[arg(short, long, value_parser = parse_resolution)]
+    resolution: Option<(u32, u32)>,
+
+    /// Samples taken for each pixel
+    #[arg(short, long)]
+    samples: Option<u32>,
+
+    /// How many times rays may bounce around the scene
+    #[arg(short, long)]
+    depth: Option<u32>,
+
+    /// Seed for the random numbers used while rendering
+    #[arg(long)]
+    seed: Option<u64>,
+
+    /// Threads to render with [default: one per core]
+    #[arg(short = 'j', long)]
+    threads: Option<usize>,
+
+    /// Render only part of the image, given as X,Y,WIDTH,HEIGHT in pixels
+    #[arg(long, value_parser = parse_region)]
+    crop: Option<Region>,
+
+    /// Also write these output variables, each next to the image with its name added, such as
+    /// render.depth.png, or as extra channels of OpenEXR images [possible values: depth, normal,
+    /// albedo, object_id, material_id, direct, indirect, all]
+    #[arg(
+        long,
+        value_name = "NAMES",
+        value_delimiter = ',',
+        value_parser = parse_aov,
+        conflicts_with_all = ["progressive", "time_limit", "checkpoint"]
+    )]
+    aov: Vec<Vec<Aov>>,
+
+    /// Remove noise from the image, guided by its albedo, normals and depth
+    #[arg(long, conflicts_with_all = ["progressive", "time_limit", "checkpoint"])]
+    denoise: bool,
+
+    /// Also write the image from before it was denoised, with .noisy added to its name
+    #[arg(long, requires = "denoise")]
+    keep_noisy: bool,
+
+    /// How precisely OpenEXR images store their values
+    #[arg(long, value_enum, default_value = "half")]
+    exr_precision: Precision,
+
+    /// How OpenEXR images are compressed
+    #[arg(long, value_enum, default_value = "zip")]
+    exr_compression: ExrCompressionArg,
+
+    /// Brighten or darken PNG and PPM images by this many stops
+    #[arg(
+        short,
+        long,
+        value_name = "EV",
+        default_value = "0",
+        allow_hyphen_values = true
+    )]
+    exposure: f32,
+
+    /// How PNG and PPM images fit bright highlights into range
+    #[arg(long, value_enum, default_value = "clamp")]
+    tone_map: ToneMapArg,
+
+    /// The luminance that becomes white with the extended-reinhard tone map
+    #[arg(long, value_name = "LUMINANCE", default_value = "4")]
+    white_point: f32,
+
+    /// The color space to write the image in.  OpenEXR images can be in any of them, PNG images
+    /// in srgb or display-p3, and PPM images only in srgb [possible values: srgb, display-p3,
+    /// acescg]
+    #[arg(long, value_name = "SPACE", default_value = "srgb")]
+    color_space: ColorSpace,
+
+    /// Render one sample per pixel at a time, writing the image as it improves
+    #[arg(short, long)]
+    progressive: bool,
+
+    /// Write the image after every N passes of a progressive render
+    #[arg(long, value_name = "N", requires = "progressive")]
+    snapshot_passes: Option<u32>,
+
+    /// Write the image every so many seconds during a progressive render
+    #[arg(long, value_name = "SECONDS", requires = "progressive", value_parser = parse_seconds)]
+    snapshot_interval: Option<Duration>,
+
+    /// Render for this long, taking as many samples as fit in the time, up to --samples if given
+    #[arg(short, long, value_name = "SECONDS", value_parser = parse_seconds)]
+    time_limit: Option<Duration>,
+
+    /// Save the render's progress to this file now and then, so it can be resumed
+    #[arg(long, value_name = "FILE")]
+    checkpoint: Option<PathBuf>,
+
+    /// How often to save the checkpoint
+    #[arg(
+        long,
+        value_name = "SECONDS",
+        default_value = "60",
+        value_parser = parse_seconds,
+        requires = "checkpoint"
+    )]
+    checkpoint_interval: Duration,
+
+    /// Carry on from the checkpoint file if there is one
+    #[arg(long, requires = "checkpoint")]
+    resume: bool,
+
+    /// Keep running, rendering again whenever the scene or a file it uses changes
+    #[arg(short, long)]
+    watch: bool,
+}
+
+/// Samples per pixel in the quick preview drawn first in watch mode.
+const PREVIEW_SAMPLES: u32 = 1;
+
+/// How often watch mode checks whether the scene has changed.
+const POLL_INTERVAL: Duration = Duration::from_millis(250);
+
+fn parse_resolution(text: &str) -> Result<(u32, u32), String> {
+    let error = || format!("`{}` isn't a resolution like 1920x1080", text);
+    let (width, height) = text.split_once(['x', 'X']).ok_or_else(error)?;
+    let width = width.trim().parse::<u32>().map_err(|_| error())?;
+    let height = height.trim().parse::<u32>().map_err(|_| error())?;
+    if width == 0 || height == 0 {
+        return Err("images need at least one pixel".to_string());
+    }
+    Ok((width, height))
+}
+
+fn parse_region(text: &str) -> Result<Region, String> {
+    let numbers = text
+        .split(',')
+        .map(|n| n.trim().parse::<u32>())
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|_| format!("`{}` isn't a region like X,Y,WIDTH,HEIGHT", text))?;
+    match numbers[..] {
+        [x, y, width, height] if width > 0 && height > 0 => Ok(Region::new(x, y, width, height)),
+        [_, _, _, _] => Err("regions need at least one pixel".to_string()),
+        _ => Err(format!("`{}` isn't a region like X,Y,WIDTH,HEIGHT", text)),
+    }
+}
+
+fn parse_seconds(text: &str) -> Result<Duration, String> {
+    let seconds = text
+        .trim()
+        .parse::<f64>()
+        .map_err(|_| format!("`{}` isn't a number of seconds", text))?;
+    Duration::try_from_secs_f64(seconds).map_err(|_| format!("`{}` isn't a length of time", text))
+}
+
+fn parse_aov(text: &str) -> Result<Vec<Aov>, String> {
+    match text.trim() {
+        "all" => Ok(Aov::ALL.to_vec()),
+        name => Ok(vec![name.parse()?]),
+    }
+}
+
+/// Where to write another image that goes with the output, such as an output variable: next to
+/// it, with `name` added to its file name.
+fn sibling_path(output: &Path, name: &str) -> PathBuf {
+    let mut file_name = output.file_stem().unwrap_or_default().to_owned();
+    file_name.push(".");
+    file_name.push(name);
+    if let Some(extension) = output.extension() {
+        file_name.push(".");
+        file_name.push(extension);
+    }
+    output.with_file_name(file_name)
+}
+
+/// Works out the format to write, and checks that it can say which color space it's in.
+fn output_format(args: &Args) -> Result<Format, Box<dyn Error>> {
+    let format = match args.format {
+        Some(format) => format,
+        None => Format::from_path(&args.output).ok_or_else(|| {
+            format!(
+                "can't tell the format of {} from its extension, use --format",
+                args.output.display()
+            )
+        })?,
+    };
+    let (name, tagged) = match format {
+        Format::Ppm => ("PPM", args.color_space == ColorSpace::LinearSrgb),
+        Format::Png => ("PNG", args.color_space.cicp_primaries().is_some()),
+        Format::Exr => ("OpenEXR", true),
+    };
+    if !tagged {
+        return Err(format!("{} images can't be in {}", name, args.color_space).into());
+    }
+    Ok(format)
+}
+
+/// Loads the scene file, applying the options that override its settings, and works out which
+/// part of the image to render.
+fn load_scene(args: &Args) -> Result<(Scene, Region), Box<dyn Error>> {
+    let mut scene = Scene::from_file(&args.scene)?;
+    if let Some((width, height)) = args.resolution {
+        scene.set_resolution(width, height);
+    }
+    if let Some(samples) = args.samples {
+        scene.set_samples(samples);
+    }
+    if let Some(depth) = args.depth {
+        scene.set_tracing_depth(depth);
+    }
+    if let Some(seed) = args.seed {
+        scene.set_seed(seed);
+    }
+    let full = Region::new(0, 0, scene.width(), scene.height());
+    let region = args.crop.unwrap_or(full);
+    if !region.fits(scene.width(), scene.height()) {
+        return Err(format!(
+            "the crop region doesn't fit in the {}x{} image",
+            scene.width(),
+            scene.height()
+        )
+        .into());
+    }
+    Ok((scene, region))
+}
+
+impl Args {
+    fn exr_settings(&self) -> (ExrPrecision, ExrCompression) {
+        let precision = match self.exr_precision {
+            Precision::Half => ExrPrecision::Half,
+            Precision::Float => ExrPrecision::Float,
+        };
+        let compression = match self.exr_compression {
+            ExrCompressionArg::None => ExrCompression::None,
+            ExrCompressionArg::Zip => ExrCompression::Zip,
+            ExrCompressionArg::Piz => ExrCompression::Piz,
+        };
+        (precision, compression)
+    }
+
+    fn tone_map(&self) -> ToneMap {
+        match self.tone_map {
+            ToneMapArg::Clamp => ToneMap::Clamp,
+            ToneMapArg::Reinhard => ToneMap::Reinhard,
+            ToneMapArg::ExtendedReinhard => ToneMap::ExtendedReinhard {
+                white: self.white_point,
+            },
+            ToneMapArg::Hable => ToneMap::Hable,
+            ToneMapArg::Aces => ToneMap::Aces,
+        }
+    }
+}
+
+fn write_image(image: &Framebuffer, path: &Path, format: Format, args: &Args) -> io::Result<()> {
+    let mut file = BufWriter::new(File::create(path)?);
+    match format {
+        Format::Ppm => image.write_ppm(&mut file)?,
+        Format::Png => image.write_png(&mut file)?,
+        Format::Exr => {
+            let (precision, compression) = args.exr_settings();
+            image.write_exr(&mut file, precision, compression)?
+        }
+    }
+    file.flush()?;
+    Ok(())
+}
+
+/// Writes a rendered image in the chosen color space, exposed and tone mapped unless it's going
+/// into an OpenEXR file, which keeps the radiance as it is.
+fn write_render(image: &Framebuffer, path: &Path, format: Format, args: &Args) -> io::Result<()> {
+    match format {
+        Format::Exr => write_image(&image.converted(args.color_space), path, format, args),
+        Format::Ppm | Format::Png => {
+            let display = image.tone_mapped(args.exposure, args.tone_map());
+            write_image(&display.converted(args.color_space), path, format, args)
+        }
+    }
+}
+
+fn run(args: &Args) -> Result<(), Box<dyn Error>> {
+    let format = output_format(args)?;
+    let (scene, region) = load_scene(args)?;
+    let full = Region::new(0, 0, scene.width(), scene.height());
+
+    let pool = rayon::ThreadPoolBuilder::new()
+        .num_threads(args.threads.unwrap_or(0))
+        .build()?;
+    let start = Instant::now();
+    // samples per pixel that were already taken before resuming, and so weren't timed
+    let mut resumed = 0;
+    let mut exr_aovs = None;
+    let progressive = args.progressive || args.time_limit.is_some() || args.checkpoint.is_some();
+    let (image, samples_per_pixel) = if progressive {
+        let mut progressive = match &args.checkpoint {
+            Some(path) if args.resume && path.exists() => {
+                let progressive = Progressive::resume(&scene, path)?;
+                if progressive.region() != region {
+                    return Err("the checkpoint is for a different part of the image".into());
+                }
+                eprintln!(
+                    "resuming from {} with {} samples per pixel",
+                    path.display(),
+                    progressive.passes()
+                );
+                resumed = progressive.passes();
+                progressive
+            }
+            _ => Progressive::new(&scene, region),
+        };
+        if let Some(path) = &args.checkpoint {
+            progressive.set_checkpoint(path, args.checkpoint_interval);
+        }
+        if let Some(limit) = args.time_limit {
+            progressive.set_time_limit(limit);
+            progressive.set_target(args.samples.unwrap_or(u32::MAX));
+        }
+        if let Some(passes) = args.snapshot_passes {
+            progressive.set_snapshot_passes(passes);
+        }
+        if let Some(interval) = args.snapshot_interval {
+            progressive.set_snapshot_interval(interval);
+        }
+        let image = pool.install(|| {
+            progressive.render(|image, samples| {
+                write_render(image, &args.output, format, args)?;
+                eprintln!(
+                    "wrote {} with {} samples per pixel",
+                    args.output.display(),
+                    samples
+                );
+                Ok::<_, io::Error>(())
+            })
+        })?;
+        (image, progressive.passes())
+    } else if !args.aov.is_empty() || args.denoise {
+        let requested = args.aov.concat();
+        let mut aovs = Vec::new();
+        let guide_aovs = [Aov::Albedo, Aov::Normal, Aov::Depth];
+        let needed = guide_aovs.iter().filter(|_| args.denoise);
+        for &aov in requested.iter().chain(needed) {
+            if !aovs.contains(&aov) {
+                aovs.push(aov);
+            }
+        }
+        let mut images = pool.install(|| scene.render_aovs(region, &aovs));
+        if args.denoise {
+            let guides = DenoiseGuides::from_aovs(&images);
+            let clean = pool.install(|| Denoiser::default().denoise(&images.beauty, &guides));
+            let noisy = std::mem::replace(&mut images.beauty, clean);
+            if args.keep_noisy {
+                let path = sibling_path(&args.output, "noisy");
+                write_render(&noisy, &path, format, args)?;
+                eprintln!("wrote {}", path.display());
+            }
+            // only write the guides if they were asked for
+            for aov in guide_aovs.iter().filter(|aov| !requested.contains(aov)) {
+                images.remove(*aov);
+            }
+        }
+        if format == Format::Exr {
+            // written along with the image, into the same file
+            let beauty = images.beauty.clone();
+            exr_aovs = Some(images);
+            (beauty, scene.samples())
+        } else {
+            for (aov, image) in images.aovs() {
+                let path = sibling_path(&args.output, aov.name());
+                write_image(&aov.visualize(image), &path, format, args)?;
+                eprintln!("wrote {}", path.display());
+            }
+            (images.beauty, scene.samples())
+        }
+    } else {
+        (
+            pool.install(|| scene.render_region(region)),
+            scene.samples(),
+        )
+    };
+    let elapsed = start.elapsed();
+
+    match exr_aovs {
+        Some(images) => {
+            let images = images.converted(args.color_space);
+            let (precision, compression) = args.exr_settings();
+            let mut file = BufWriter::new(File::create(&args.output)?);
+            images.write_exr(&mut file, precision, compression)?;
+            file.flush()?;
+        }
+        None => write_render(&image, &args.output, format, args)?,
+    }
+
+    let samples = region.pixel_count() * u64::from(samples_per_pixel);
+    let seconds = elapsed.as_secs_f64();
+    let stderr = io::stderr();
+    let mut out = stderr.lock();
+    writeln!(out, "wrote {}", args.output.display())?;
+    if region == full {
+        writeln!(out, "  image:    {}x{}", scene.width(), scene.height())?;
+    } else {
+        writeln!(
+            out,
+            "  image:    {}x{} at {},{} of {}x{}",
+            region.width,
+            region.height,
+            region.x,
+            region.y,
+            scene.width(),
+            scene.height()
+        )?;
+    }
+    writeln!(
+        out,
+        "  samples:  {} per pixel, {} in all",
+        samples_per_pixel, samples
+    )?;
+    writeln!(out, "  threads:  {}", pool.current_num_threads())?;
+    writeln!(out, "  time:     {:.2} s", seconds)?;
+    let timed = region.pixel_count() * u64::from(samples_per_pixel - resumed);
+    writeln!(
+        out,
+        "  speed:    {:.0} samples/s",
+        timed as f64 / seconds.max(1e-9)
+    )?;
+    Ok(())
+}
+
+/// When each of the scene's files was last modified, to notice when one changes.  Files that
+/// can't be read show up as `None`, so they count as changed once they appear.
+fn modification_times(scene: &Path) -> Vec<(PathBuf, Option<SystemTime>)> {
+    let files = Scene::source_files(scene).unwrap_or_else(|_| vec![scene.to_path_buf()]);
+    files
+        .into_iter()
+        .map(|file| {
+            let modified = file.metadata().and_then(|m| m.modified()).ok();
+            (file, modified)
+        })
+        .collect()
+}
+
+/// The samples to render in each pass of watch mode: a quick preview, then batches that double in
+/// size until `samples` have been taken.
+fn passes(samples: u32) -> Vec<Range<u32>> {
+    let mut passes = Vec::new();
+    let mut start = 0;
+    let mut batch = PREVIEW_SAMPLES;
+    while start < samples {
+        let end = start.saturating_add(batch).min(samples);
+        passes.push(start..end);
+        batch = batch.saturating_mul(2);
+        start = end;
+    }
+    passes
+}
+
+/// Averages two images rendered from `a` and `b` samples per pixel.
+fn combine(first: &Framebuffer, a: u32, second: &Framebuffer, b: u32) -> Framebuffer {
+    let total = (a + b) as f32;
+    let (a, b) = (a as f32 / total, b as f32 / total);
+    let pixels = first
+        .pixels()
+        .iter()
+        .zip(second.pixels())
+        .map(|(&x, &y)| x * a + y * b)
+        .collect();
+    Framebuffer::from_pixels(first.width(), first.height(), pixels)
+}
+
+/// Renders the scene again and again, refining the image a pass at a time and starting over with
+/// a fresh preview whenever the scene's files change.  Runs until interrupted.
+fn watch(args: &Args) -> Result<(), Box<dyn Error>> {
+    let format = output_format(args)?;
+    let pool = rayon::ThreadPoolBuilder::new()
+        .num_threads(args.threads.unwrap_or(0))
+        .build()?;
+    loop {
+        let times = modification_times(&args.scene);
+        let changed = || modification_times(&args.scene) != times;
+        match load_scene(args) {
+            Ok((scene, region)) => {
+                let start = Instant::now();
+                let mut image: Option<Framebuffer> = None;
+                for pass in passes(scene.samples()) {
+                    if changed() {
+                        break;
+                    }
+                    let count = pass.len() as u32;
+                    let done = pass.start;
+                    let batch = pool.install(|| scene.render_samples(region, pass.clone()));
+                    let refined = match image {
+                        Some(image) => combine(&image, done, &batch, count),
+                        None => batch,
+                    };
+                    write_render(&refined, &args.output, format, args)?;
+                    eprintln!(
+                        "wrote {} with {} of {} samples per pixel after {:.2} s",
+                        args.output.display(),
+                        pass.end,
+                        scene.samples(),
+                        start.elapsed().as_secs_f64()
+                    );
+                    image = Some(refined);
+                }
+            }
+            Err(e) => eprintln!("error: {}", e),
+        }
+        while !changed() {
+            thread::sleep(POLL_INTERVAL);
+        }
+        eprintln!("{} changed, rendering again", args.scene.display());
+    }
+}
+
+fn main() -> ExitCode {
+    let args = Args::parse();
+    let result = if args.watch { watch(&args) } else { run(&args) };
+    match result {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(e) => {
+            eprintln!("error: {}", e);
+            ExitCode::FAILURE
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use clap::CommandFactory;
+
+    #[test]
+    fn test_arguments() {
+        Args::command().debug_assert();
+        let args = Args::try_parse_from([
+            "raytracer",
+            "scene.toml",
+            "-o",
+            "out.ppm",
+            "-r",
+            "320x180",
+            "--crop",
+            "10,20,30,40",
+            "-j",
+            "2",
+        ])
+        .unwrap();
+        assert_eq!(args.resolution, Some((320, 180)));
+        assert_eq!(args.crop, Some(Region::new(10, 20, 30, 40)));
+        assert_eq!(args.threads, Some(2));
+        assert_eq!(Format::from_path(&args.output), Some(Format::Ppm));
+
+        let args = Args::try_parse_from([
+            "raytracer",
+            "scene.toml",
+            "--progressive",
+            "--snapshot-passes",
+            "4",
+            "--snapshot-interval",
+            "2.5",
+        ])
+        .unwrap();
+        assert_eq!(args.snapshot_passes, Some(4));
+        assert_eq!(args.snapshot_interval, Some(Duration::from_millis(2500)));
+        assert!(
+            Args::try_parse_from(["raytracer", "scene.toml", "--snapshot-passes", "4"]).is_err()
+        );
+        assert!(parse_seconds("-1").is_err());
+
+        let args = Args::try_parse_from(["raytracer", "scene.toml", "-t", "90"]).unwrap();
+        assert_eq!(args.time_limit, Some(Duration::from_secs(90)));
+
+        let args = Args::try_parse_from([
+            "raytracer",
+            "scene.toml",
+            "--checkpoint",
+            "render.ckpt",
+            "--resume",
+        ])
+        .unwrap();
+        assert_eq!(args.checkpoint, Some(PathBuf::from("render.ckpt")));
+        assert_eq!(args.checkpoint_interval, Duration::from_secs(60));
+        assert!(Args::try_parse_from(["raytracer", "scene.toml", "--resume"]).is_err());
+
+        let args =
+            Args::try_parse_from(["raytracer", "scene.toml", "--aov", "depth,normal"]).unwrap();
+        assert_eq!(args.aov.concat(), vec![Aov::Depth, Aov::Normal]);
+        let args = Args::try_parse_from(["raytracer", "scene.toml", "--aov", "all"]).unwrap();
+        assert_eq!(args.aov.concat(), Aov::ALL.to_vec());
+        assert!(Args::try_parse_from(["raytracer", "scene.toml", "--aov", "shadow"]).is_err());
+        assert!(Args::try_parse_from(["raytracer", "scene.toml", "--keep-noisy"]).is_err());
+
+        let args = Args::try_parse_from([
+            "raytracer",
+            "scene.toml",
+            "-e",
+            "-1.5",
+            "--tone-map",
+            "extended-reinhard",
+            "--white-point",
+            "8",
+        ])
+        .unwrap();
+        assert_eq!(args.exposure, -1.5);
+        assert_eq!(args.tone_map(), ToneMap::ExtendedReinhard { white: 8.0 });
+        assert_eq!(args.color_space, ColorSpace::LinearSrgb);
+        let args = Args::try_parse_from(["raytracer", "scene.toml", "--color-space", "acescg"]);
+        assert_eq!(args.unwrap().color_space, ColorSpace::AcesCg);
+        assert!(Args::try_parse_from(["raytracer", "scene.toml", "--color-space", "xyz"]).is_err());
+        let color_space = |output: &str, space: &str| {
+            let args = [
+                "raytracer",
+                "scene.toml",
+                "-o",
+                output,
+                "--color-space",
+                space,
+            ];
+            output_format(&Args::try_parse_from(args).unwrap()).is_ok()
+        };
+        assert!(color_space("out.exr", "acescg"));
+        assert!(color_space("out.png", "display-p3"));
+        assert!(!color_space("out.png", "acescg"));
+        assert!(!color_space("out.ppm", "display-p3"));
+        assert!(Args::try_parse_from(["raytracer", "scene.toml", "--denoise", "-p"]).is_err());
+        assert_eq!(
+            sibling_path(Path::new("out/render.png"), Aov::ObjectId.name()),
+            Path::new("out/render.object_id.png")
+        );
+
+        assert!(parse_resolution("320").is_err());
+        assert!(parse_resolution("0x180").is_err());
+        assert!(parse_region("1,2,3").is_err());
+        assert!(parse_region("1,2,0,4").is_err());
+        assert_eq!(Format::from_path(Path::new("out.EXR")), Some(Format::Exr));
+        assert_eq!(Format::from_path(Path::new("out.tga")), None);
+    }
+
+    #[test]
+    fn test_passes() {
+        assert_eq!(passes(1), vec![0..1]);
+        assert_eq!(passes(10), vec![0..1, 1..3, 3..7, 7..10]);
+        assert!(passes(0).is_empty());
+    }
+}
@@ -1,17 +1,87 @@
 use crate::prelude::*;
 use nalgebra::*;
-use rand::{distributions::*, prelude::*};
+use std::f32::consts::PI;
+use std::fmt::Debug;
+use std::sync::Arc;
 
-/// Colors a scene
-pub trait Colorable {
-    fn color(&self, scene: &Scene, intersection: &Intersection, depth: u32) -> Color;
+/// A direction picked by sampling a BSDF, along with everything needed to weight it.
+#[derive(Debug, Clone)]
+pub struct BsdfSample {
+    /// Direction light arrives from, pointing away from the surface
+    pub wi: Vector3<f32>,
+    /// Value of the BSDF for the outgoing direction and `wi`
+    pub value: Color,
+    /// Probability density of having picked `wi`.  For specular samples, this is the probability
+    /// of having picked the specular lobe at all.
+    pub pdf: f32,
+    /// Whether `wi` was the only direction that could have been picked, as with a perfect mirror
+    pub specular: bool,
 }
 
-/// A diffuse material.  Absorbs light in each ray bounce based on the albedo.
+/// Describes how a surface scatters and emits light.  Implement this to add new kinds of
+/// materials from outside the crate, and wrap them with `Material::custom`.
+///
+/// All directions point away from the surface: `wo` is the direction light leaves towards (i.e.
+/// back along the incoming ray), and `wi` is the direction it arrives from.
+pub trait Bsdf: Debug {
+    /// How much of the light arriving from `wi` is scattered towards `wo`.  Specular lobes can't
+    /// be evaluated and contribute nothing here; they're only reachable through `sample`.
+    fn evaluate(&self, wo: &Vector3<f32>, wi: &Vector3<f32>, surface: &SurfaceInteraction)
+        -> Color;
+
+    /// Picks an incoming direction for light scattered towards `wo`, using the uniformly
+    /// distributed sample `u` in `[0, 1)²`.
+    fn sample(
+        &self,
+        wo: &Vector3<f32>,
+        surface: &SurfaceInteraction,
+        u: Point2<f32>,
+    ) -> Option<BsdfSample>;
+
+    /// Probability density of `sample` picking `wi`, ignoring specular lobes.
+    fn pdf(&self, wo: &Vector3<f32>, wi: &Vector3<f32>, surface: &SurfaceInteraction) -> f32;
+
+    /// Light given off by the surface itself towards `wo`.
+    fn emission(&self, _wo: &Vector3<f32>, _surface: &SurfaceInteraction) -> Color {
+        Color::default()
+    }
+
+    /// Roughly how much light the surface reflects, in each channel, ignoring where it comes
+    /// from and goes to.  Used for the albedo output variable, and as a guide for denoising.
+    /// Defaults to white.
+    fn albedo(&self, _surface: &SurfaceInteraction) -> Color {
+        Color::new(1.0, 1.0, 1.0)
+    }
+}
+
+/// Whether `wo` and `wi` are on the same side of the surface, so light can be reflected between
+/// them.
+fn same_hemisphere(wo: &Vector3<f32>, wi: &Vector3<f32>, surface: &SurfaceInteraction) -> bool {
+    let normal = &surface.shading_normal;
+    wo.dot(normal) * wi.dot(normal) > 0.0
+}
+
+/// Picks a direction around `normal`, distributed proportionally to the cosine of the angle
+/// between them.
+fn cosine_sample_hemisphere(normal: &Vector3<f32>, u: Point2<f32>) -> Vector3<f32> {
+    let (tangent, bitangent) = coordinate_system(normal);
+    let r = u.x.sqrt();
+    let phi = 2.0 * PI * u.y;
+    let z = (1.0 - u.x).max(0.0).sqrt();
+    tangent * (r * phi.cos()) + bitangent * (r * phi.sin()) + normal * z
+}
+
+/// Reflects `w` about `normal`.
+fn reflect(w: &Vector3<f32>, normal: &Vector3<f32>) -> Vector3<f32> {
+    2.0 * w.dot(normal) * normal - w
+}
+
+/// A diffuse material.  Scatters light evenly in all directions, absorbing some of it based on
+/// the albedo.
 #[derive(Debug)]
 pub struct Diffuse {
-    color: Color,
-    albedo: f32,
+    pub(crate) color: Color,
+    pub(crate) albedo: f32,
 }
 
 impl Diffuse {
@@ -20,99 +90,485 @@ impl Diffuse {
     }
 }
 
-impl Colorable for Diffuse {
-    fn color(&self, scene: &Scene, i: &Intersection, depth: u32) -> Color {
-        let normal = i.surface_normal();
-        let sphere_center = i.point + normal;
-
-        let point = {
-            let dist = Uniform::new_inclusive(-1.0, 1.0);
-            let mut rng = thread_rng();
-            let mut point = Vector3::new(
-                dist.sample(&mut rng),
-                dist.sample(&mut rng),
-                dist.sample(&mut rng),
-            );
-            while point.magnitude_squared() >= 1.0 {
-                point = Vector3::new(
-                    dist.sample(&mut rng),
-                    dist.sample(&mut rng),
-                    dist.sample(&mut rng),
-                );
-            }
-            sphere_center + point
-        };
+impl Bsdf for Diffuse {
+    fn evaluate(
+        &self,
+        wo: &Vector3<f32>,
+        wi: &Vector3<f32>,
+        surface: &SurfaceInteraction,
+    ) -> Color {
+        if same_hemisphere(wo, wi, surface) {
+            self.color * (self.albedo / PI)
+        } else {
+            Color::default()
+        }
+    }
 
-        let direction = point - i.point;
-        let secondary_ray = Ray::new(
-            // step away from the surface to prevent "pox" from showing up
-            i.point + 1e-6 * direction,
-            direction,
-        );
-        let traced_color = scene
-            .trace(&secondary_ray, depth + 1)
-            .map_or_else(|| scene.background, |i| i.elem.color(scene, &i, depth + 1));
-        let surface_color = self.color.lerp(traced_color, self.albedo);
+    fn sample(
+        &self,
+        wo: &Vector3<f32>,
+        surface: &SurfaceInteraction,
+        u: Point2<f32>,
+    ) -> Option<BsdfSample> {
+        let wi = cosine_sample_hemisphere(&surface.shading_normal, u);
+        Some(BsdfSample {
+            wi,
+            value: self.evaluate(wo, &wi, surface),
+            pdf: self.pdf(wo, &wi, surface),
+            specular: false,
+        })
+    }
 
-        let reflected = self.albedo / std::f32::consts::PI;
+    fn pdf(&self, wo: &Vector3<f32>, wi: &Vector3<f32>, surface: &SurfaceInteraction) -> f32 {
+        if same_hemisphere(wo, wi, surface) {
+            wi.dot(&surface.shading_normal).abs() / PI
+        } else {
+            0.0
+        }
+    }
 
-        scene
-            .lights
-            .iter()
-            .map(|l| l.color(scene, &i, depth))
-            .map(|c| surface_color * c * reflected)
-            .fold(Color::default(), |acc, item| acc + item)
-            .clamp()
+    fn albedo(&self, _surface: &SurfaceInteraction) -> Color {
+        self.color * self.albedo
     }
 }
 
-impl Into<Material> for Diffuse {
-    fn into(self) -> Material {
-        Material::Diffuse(self)
+impl From<Diffuse> for Material {
+    fn from(diffuse: Diffuse) -> Self {
+        Material::Diffuse(diffuse)
     }
 }
 
+/// A mirror over a diffuse base coat.  The albedo is the fraction of light scattered by the
+/// base coat, and the rest is reflected perfectly.
 #[derive(Debug)]
 pub struct Reflective {
-    color: Color,
-    albedo: f32,
+    pub(crate) color: Color,
+    pub(crate) albedo: f32,
 }
 
 impl Reflective {
     pub fn new(color: Color, albedo: f32) -> Reflective {
         Reflective { color, albedo }
     }
+
+    fn base(&self) -> Diffuse {
+        Diffuse::new(self.color, self.albedo)
+    }
+}
+
+impl Bsdf for Reflective {
+    fn evaluate(
+        &self,
+        wo: &Vector3<f32>,
+        wi: &Vector3<f32>,
+        surface: &SurfaceInteraction,
+    ) -> Color {
+        self.base().evaluate(wo, wi, surface)
+    }
+
+    fn sample(
+        &self,
+        wo: &Vector3<f32>,
+        surface: &SurfaceInteraction,
+        u: Point2<f32>,
+    ) -> Option<BsdfSample> {
+        let mirror = 1.0 - self.albedo;
+        if u.x < mirror {
+            let wi = reflect(wo, &surface.shading_normal);
+            let cos = wi.dot(&surface.shading_normal).abs();
+            Some(BsdfSample {
+                wi,
+                value: Color::new(1.0, 1.0, 1.0) * (mirror / cos),
+                pdf: mirror,
+                specular: true,
+            })
+        } else {
+            // reuse the sample for the base coat, stretched back out to cover [0, 1)
+            let u = Point2::new((u.x - mirror) / self.albedo, u.y);
+            let wi = cosine_sample_hemisphere(&surface.shading_normal, u);
+            Some(BsdfSample {
+                wi,
+                value: self.evaluate(wo, &wi, surface),
+                pdf: self.pdf(wo, &wi, surface),
+                specular: false,
+            })
+        }
+    }
+
+    fn pdf(&self, wo: &Vector3<f32>, wi: &Vector3<f32>, surface: &SurfaceInteraction) -> f32 {
+        self.albedo * self.base().pdf(wo, wi, surface)
+    }
+
+    fn albedo(&self, surface: &SurfaceInteraction) -> Color {
+        let mirror = 1.0 - self.albedo;
+        self.base().albedo(surface) + Color::new(mirror, mirror, mirror)
+    }
+}
+
+impl From<Reflective> for Material {
+    fn from(reflective: Reflective) -> Self {
+        Material::Reflective(reflective)
+    }
+}
+
+/// A metallic-roughness material, the kind used by glTF and most content tools.  Dielectrics
+/// (`metallic` 0) are a diffuse base under a faint specular coat, while metals (`metallic` 1)
+/// reflect their base color.  `roughness` blurs the reflections, from a mirror at 0 to almost
+/// diffuse at 1.
+#[derive(Debug, Clone)]
+pub struct Pbr {
+    pub(crate) base_color: ColorSource,
+    pub(crate) metallic: f32,
+    pub(crate) roughness: f32,
+    /// Scales `metallic` by its blue channel and `roughness` by its green channel
+    pub(crate) metallic_roughness: Option<Arc<Texture>>,
+    pub(crate) emission: ColorSource,
+}
+
+impl Pbr {
+    pub fn new<C: Into<ColorSource>>(base_color: C, metallic: f32, roughness: f32) -> Pbr {
+        Pbr {
+            base_color: base_color.into(),
+            metallic,
+            roughness,
+            metallic_roughness: None,
+            emission: ColorSource::Constant(Color::default()),
+        }
+    }
+
+    pub fn with_metallic_roughness(self, texture: Arc<Texture>) -> Pbr {
+        Pbr {
+            metallic_roughness: Some(texture),
+            ..self
+        }
+    }
+
+    pub fn with_emission<C: Into<ColorSource>>(self, emission: C) -> Pbr {
+        Pbr {
+            emission: emission.into(),
+            ..self
+        }
+    }
+
+    /// Looks up the base color, metallic and roughness at the hit point.  The roughness is
+    /// squared into the GGX `alpha`, which is kept away from zero so mirrors stay well behaved.
+    fn parameters(&self, surface: &SurfaceInteraction) -> (Color, f32, f32) {
+        let base = self.base_color.at(surface);
+        let (mut metallic, mut roughness) = (self.metallic, self.roughness);
+        if let Some(texture) = &self.metallic_roughness {
+            let texel = texture.sample(&surface.uv);
+            metallic *= texel.blue;
+            roughness *= texel.green;
+        }
+        let alpha = (roughness * roughness).max(1e-3);
+        (base, metallic.clamp(0.0, 1.0), alpha)
+    }
+
+    /// Probability of sampling the specular lobe rather than the diffuse one.
+    fn specular_probability(metallic: f32) -> f32 {
+        0.5 + 0.5 * metallic
+    }
+
+    /// The GGX distribution of microfacet normals.
+    fn distribution(cos_h: f32, alpha: f32) -> f32 {
+        let alpha2 = alpha * alpha;
+        let d = cos_h * cos_h * (alpha2 - 1.0) + 1.0;
+        alpha2 / (PI * d * d)
+    }
+
+    /// Smith's shadowing term for a single direction.
+    fn shadowing(cos: f32, alpha: f32) -> f32 {
+        let alpha2 = alpha * alpha;
+        2.0 * cos / (cos + (alpha2 + (1.0 - alpha2) * cos * cos).sqrt())
+    }
+
+    /// The shading normal, flipped to the side `wo` is on.
+    fn normal(wo: &Vector3<f32>, surface: &SurfaceInteraction) -> Vector3<f32> {
+        let normal = surface.shading_normal;
+        if wo.dot(&normal) < 0.0 {
+            -normal
+        } else {
+            normal
+        }
+    }
+
+    fn specular_pdf(wo: &Vector3<f32>, wi: &Vector3<f32>, n: &Vector3<f32>, alpha: f32) -> f32 {
+        let h = (wo + wi).normalize();
+        let cos_h = n.dot(&h).max(0.0);
+        Pbr::distribution(cos_h, alpha) * cos_h / (4.0 * wo.dot(&h).abs().max(1e-6))
+    }
 }
 
-impl Colorable for Reflective {
-    fn color(&self, scene: &Scene, i: &Intersection, depth: u32) -> Color {
-        let reflection = Ray::reflect(i.surface_normal(), i.incoming.direction, &i.point, 1e-6);
-        let reflected_color = scene
-            .trace(&reflection, depth + 1)
-            .map(|i| i.elem.color(scene, &i, depth + 1))
-            .unwrap_or(scene.background);
-        self.color.lerp(reflected_color, self.albedo)
+impl Bsdf for Pbr {
+    fn evaluate(
+        &self,
+        wo: &Vector3<f32>,
+        wi: &Vector3<f32>,
+        surface: &SurfaceInteraction,
+    ) -> Color {
+        if !same_hemisphere(wo, wi, surface) {
+            return Color::default();
+        }
+        let (base, metallic, alpha) = self.parameters(surface);
+        let n = Pbr::normal(wo, surface);
+        let (cos_o, cos_i) = (wo.dot(&n), wi.dot(&n));
+        if cos_o <= 0.0 || cos_i <= 0.0 {
+            return Color::default();
+        }
+
+        let h = (wo + wi).normalize();
+        let f0 = base.lerp(Color::new(0.04, 0.04, 0.04), metallic);
+        let fresnel = Color::new(1.0, 1.0, 1.0).lerp(f0, (1.0 - wo.dot(&h).max(0.0)).powi(5));
+        let specular = Pbr::distribution(n.dot(&h), alpha)
+            * Pbr::shadowing(cos_o, alpha)
+            * Pbr::shadowing(cos_i, alpha)
+            / (4.0 * cos_o * cos_i);
+        base * ((1.0 - metallic) / PI) + fresnel * specular
+    }
+
+    fn sample(
+        &self,
+        wo: &Vector3<f32>,
+        surface: &SurfaceInteraction,
+        u: Point2<f32>,
+    ) -> Option<BsdfSample> {
+        let (_, metallic, alpha) = self.parameters(surface);
+        let n = Pbr::normal(wo, surface);
+        let specular = Pbr::specular_probability(metallic);
+
+        let wi = if u.x < specular {
+            // pick a microfacet normal from the distribution, and reflect off of it
+            let u = Point2::new(u.x / specular, u.y);
+            let cos2 = (1.0 - u.x) / (1.0 + (alpha * alpha - 1.0) * u.x);
+            let (cos, sin) = (cos2.sqrt(), (1.0 - cos2).max(0.0).sqrt());
+            let phi = 2.0 * PI * u.y;
+            let (tangent, bitangent) = coordinate_system(&n);
+            let h = tangent * (sin * phi.cos()) + bitangent * (sin * phi.sin()) + n * cos;
+            reflect(wo, &h)
+        } else {
+            let u = Point2::new((u.x - specular) / (1.0 - specular), u.y);
+            cosine_sample_hemisphere(&n, u)
+        };
+        if wi.dot(&n) <= 0.0 {
+            return None;
+        }
+
+        Some(BsdfSample {
+            wi,
+            value: self.evaluate(wo, &wi, surface),
+            pdf: self.pdf(wo, &wi, surface),
+            specular: false,
+        })
+    }
+
+    fn pdf(&self, wo: &Vector3<f32>, wi: &Vector3<f32>, surface: &SurfaceInteraction) -> f32 {
+        if !same_hemisphere(wo, wi, surface) {
+            return 0.0;
+        }
+        let (_, metallic, alpha) = self.parameters(surface);
+        let n = Pbr::normal(wo, surface);
+        let specular = Pbr::specular_probability(metallic);
+        specular * Pbr::specular_pdf(wo, wi, &n, alpha) + (1.0 - specular) * wi.dot(&n).abs() / PI
+    }
+
+    fn emission(&self, _wo: &Vector3<f32>, surface: &SurfaceInteraction) -> Color {
+        self.emission.at(surface)
+    }
+
+    fn albedo(&self, surface: &SurfaceInteraction) -> Color {
+        self.base_color.at(surface)
     }
 }
 
-impl Into<Material> for Reflective {
-    fn into(self) -> Material {
-        Material::Reflective(self)
+impl From<Pbr> for Material {
+    fn from(pbr: Pbr) -> Self {
+        Material::Pbr(pbr)
     }
 }
 
+/// Refers to a material stored in a `Scene`.  Any number of objects can share one material.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct MaterialId(pub(crate) usize);
+
 /// A generic material.  Allows objects to have any kind of material supported.
 #[derive(Debug)]
 pub enum Material {
     Diffuse(Diffuse),
     Reflective(Reflective),
+    Pbr(Pbr),
+    /// A user-defined material.
+    Custom(Box<dyn Bsdf + Send + Sync>),
 }
 
-impl Colorable for Material {
-    fn color(&self, scene: &Scene, i: &Intersection, depth: u32) -> Color {
+impl Material {
+    /// Wraps a user-defined BSDF so it can be attached to geometry.
+    pub fn custom<B>(bsdf: B) -> Material
+    where
+        B: Bsdf + Send + Sync + 'static,
+    {
+        Material::Custom(Box::new(bsdf))
+    }
+
+    fn bsdf(&self) -> &dyn Bsdf {
         match self {
-            Material::Diffuse(x) => x.color(scene, i, depth),
-            Material::Reflective(r) => r.color(scene, i, depth),
+            Material::Diffuse(d) => d,
+            Material::Reflective(r) => r,
+            Material::Pbr(p) => p,
+            Material::Custom(c) => c.as_ref(),
         }
     }
 }
+
+impl Bsdf for Material {
+    fn evaluate(
+        &self,
+        wo: &Vector3<f32>,
+        wi: &Vector3<f32>,
+        surface: &SurfaceInteraction,
+    ) -> Color {
+        self.bsdf().evaluate(wo, wi, surface)
+    }
+
+    fn sample(
+        &self,
+        wo: &Vector3<f32>,
+        surface: &SurfaceInteraction,
+        u: Point2<f32>,
+    ) -> Option<BsdfSample> {
+        self.bsdf().sample(wo, surface, u)
+    }
+
+    fn pdf(&self, wo: &Vector3<f32>, wi: &Vector3<f32>, surface: &SurfaceInteraction) -> f32 {
+        self.bsdf().pdf(wo, wi, surface)
+    }
+
+    fn emission(&self, wo: &Vector3<f32>, surface: &SurfaceInteraction) -> Color {
+        self.bsdf().emission(wo, surface)
+    }
+
+    fn albedo(&self, surface: &SurfaceInteraction) -> Color {
+        self.bsdf().albedo(surface)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn surface_facing_up() -> SurfaceInteraction {
+        let ray = Ray::new(Point3::new(0.0, 1.0, 0.0), Vector3::new(1.0, -1.0, 0.0));
+        SurfaceInteraction::new(
+            &ray,
+            2.0_f32.sqrt(),
+            Vector3::new(0.0, 1.0, 0.0),
+            Point2::new(0.0, 0.0),
+            Vector3::new(1.0, 0.0, 0.0),
+            Vector3::new(0.0, 0.0, 1.0),
+        )
+    }
+
+    /// Emits a fixed color and scatters nothing.
+    #[derive(Debug)]
+    struct Glow(Color);
+
+    impl Bsdf for Glow {
+        fn evaluate(&self, _: &Vector3<f32>, _: &Vector3<f32>, _: &SurfaceInteraction) -> Color {
+            Color::default()
+        }
+
+        fn sample(
+            &self,
+            _: &Vector3<f32>,
+            _: &SurfaceInteraction,
+            _: Point2<f32>,
+        ) -> Option<BsdfSample> {
+            None
+        }
+
+        fn pdf(&self, _: &Vector3<f32>, _: &Vector3<f32>, _: &SurfaceInteraction) -> f32 {
+            0.0
+        }
+
+        fn emission(&self, _: &Vector3<f32>, _: &SurfaceInteraction) -> Color {
+            self.0
+        }
+    }
+
+    #[test]
+    fn test_diffuse_sample() {
+        let surface = surface_facing_up();
+        let diffuse = Diffuse::new(Color::new(1.0, 0.5, 0.25), 0.5);
+        let wo = Vector3::new(-1.0, 1.0, 0.0).normalize();
+        let sample = diffuse
+            .sample(&wo, &surface, Point2::new(0.3, 0.7))
+            .unwrap();
+
+        assert!(sample.wi.dot(&surface.shading_normal) > 0.0);
+        assert!((sample.wi.norm() - 1.0).abs() < 1e-5);
+        assert!((sample.pdf - diffuse.pdf(&wo, &sample.wi, &surface)).abs() < 1e-6);
+        assert_eq!(sample.value, Color::new(1.0, 0.5, 0.25) * (0.5 / PI));
+        // nothing gets through the surface
+        let below = Vector3::new(0.0, -1.0, 0.0);
+        assert_eq!(diffuse.evaluate(&wo, &below, &surface), Color::default());
+    }
+
+    #[test]
+    fn test_reflective_mirror() {
+        let surface = surface_facing_up();
+        let reflective = Reflective::new(Color::default(), 0.25);
+        let wo = Vector3::new(-1.0, 1.0, 0.0).normalize();
+        let sample = reflective
+            .sample(&wo, &surface, Point2::new(0.1, 0.5))
+            .unwrap();
+
+        assert!(sample.specular);
+        assert!((sample.wi - Vector3::new(1.0, 1.0, 0.0).normalize()).norm() < 1e-6);
+        // the mirror reflects exactly the light the base coat doesn't scatter
+        let cos = sample.wi.dot(&surface.shading_normal);
+        let weight = sample.value * (cos / sample.pdf);
+        assert!((weight.red - 1.0).abs() < 1e-5);
+    }
+
+    #[test]
+    fn test_custom_material() {
+        let mut scene = Scene::new(4, 3, 90.0, 1, Color::default());
+        let glow = scene.add_material(Material::custom(Glow(Color::new(0.25, 0.5, 0.75))));
+        scene.add_geometry(Sphere::new(Point3::new(0.0, 0.0, 10.0), 9.5, glow));
+        let ray = Ray::new(Point3::new(0.0, 0.0, 0.0), Vector3::new(0.0, 0.0, 1.0));
+        let hit = scene.trace(&ray, 0).unwrap();
+        assert_eq!(
+            scene.shade(&hit, 0, &mut rand::thread_rng()),
+            Color::new(0.25, 0.5, 0.75)
+        );
+    }
+
+    #[test]
+    fn test_pbr() {
+        let surface = surface_facing_up();
+        let wo = Vector3::new(-1.0, 1.0, 0.0).normalize();
+        for &(metallic, roughness) in &[(0.0, 0.5), (1.0, 0.2), (0.5, 1.0)] {
+            let pbr = Pbr::new(Color::new(1.0, 1.0, 1.0), metallic, roughness);
+
+            // a white surface can't reflect more light than it receives
+            let (n, mut total) = (64, 0.0);
+            for i in 0..n {
+                for j in 0..n {
+                    let u = Point2::new((i as f32 + 0.5) / n as f32, (j as f32 + 0.5) / n as f32);
+                    if let Some(sample) = pbr.sample(&wo, &surface, u) {
+                        assert!((sample.pdf - pbr.pdf(&wo, &sample.wi, &surface)).abs() < 1e-4);
+                        let cos = sample.wi.dot(&surface.shading_normal);
+                        total += sample.value.green * cos / sample.pdf;
+                    }
+                }
+            }
+            let albedo = total / (n * n) as f32;
+            assert!(albedo > 0.5 && albedo < 1.05, "albedo of {}", albedo);
+        }
+
+        // nothing is transmitted through the surface
+        let pbr = Pbr::new(Color::new(1.0, 1.0, 1.0), 0.0, 0.5);
+        let below = Vector3::new(0.0, -1.0, 0.0);
+        assert_eq!(pbr.evaluate(&wo, &below, &surface), Color::default());
+        assert_eq!(pbr.pdf(&wo, &below, &surface), 0.0);
+    }
+}
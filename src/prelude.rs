@@ -1,5 +1,15 @@
+pub use crate::aov::*;
+pub use crate::colorspace::*;
+pub use crate::denoise::*;
+pub use crate::description::*;
+pub use crate::framebuffer::*;
 pub use crate::geometry::*;
+pub use crate::graph::*;
+pub use crate::import::*;
 pub use crate::lighting::*;
 pub use crate::materials::*;
+pub use crate::progressive::*;
+pub use crate::texture::*;
+pub use crate::tonemap::*;
 pub use crate::util::*;
 pub use crate::*;
@@ -0,0 +1,483 @@
+//! Rendering an image one sample per pixel at a time, so it can be looked at while it improves.
+//! Long renders can save checkpoints along the way, and pick up from one after being stopped.
+
+use std::error::Error;
+use std::fmt::{self, Write as _};
+use std::fs::{self, File};
+use std::io::{self, BufReader, BufWriter, Read, Write};
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+
+use crate::prelude::*;
+
+/// Marks the start of a checkpoint file, followed by the format's version.
+const CHECKPOINT_MAGIC: &[u8; 8] = b"RTCHKPT\0";
+const CHECKPOINT_VERSION: u32 = 1;
+
+/// Errors that can occur while resuming from a checkpoint.
+#[derive(Debug)]
+pub enum CheckpointError {
+    Io(io::Error),
+    /// The file isn't a checkpoint, or is damaged
+    Invalid(String),
+    /// The checkpoint was saved while rendering a different scene
+    SceneChanged,
+}
+
+impl fmt::Display for CheckpointError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CheckpointError::Io(e) => write!(f, "couldn't read checkpoint: {}", e),
+            CheckpointError::Invalid(message) => write!(f, "bad checkpoint: {}", message),
+            CheckpointError::SceneChanged => {
+                f.write_str("the scene has changed since the checkpoint was saved")
+            }
+        }
+    }
+}
+
+impl Error for CheckpointError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            CheckpointError::Io(e) => Some(e),
+            CheckpointError::Invalid(_) | CheckpointError::SceneChanged => None,
+        }
+    }
+}
+
+impl From<io::Error> for CheckpointError {
+    fn from(e: io::Error) -> Self {
+        match e.kind() {
+            io::ErrorKind::UnexpectedEof => {
+                CheckpointError::Invalid("the file ends too soon".to_string())
+            }
+            _ => CheckpointError::Io(e),
+        }
+    }
+}
+
+/// Renders part of a scene in passes, each adding one more sample to every pixel.  Every pixel
+/// always has the same number of samples, so the image can be taken at any point between
+/// passes.
+pub struct Progressive<'a> {
+    scene: &'a Scene,
+    region: Region,
+    /// The sum of every sample taken so far, for each pixel
+    total: Framebuffer,
+    passes: u32,
+    target: u32,
+    time_limit: Option<Duration>,
+    snapshot_passes: Option<u32>,
+    snapshot_interval: Option<Duration>,
+    /// Where to save checkpoints, and how often
+    checkpoint: Option<(PathBuf, Duration)>,
+    /// The scene's fingerprint, worked out once since it's slow for big scenes
+    fingerprint: u64,
+}
+
+impl<'a> Progressive<'a> {
+    /// Starts rendering `region` of the scene, aiming for the scene's own sample count.
+    ///
+    /// # Panics
+    /// Panics if `region` doesn't fit within the image.
+    pub fn new(scene: &'a Scene, region: Region) -> Progressive<'a> {
+        Progressive::with_fingerprint(scene, region, scene.fingerprint())
+    }
+
+    fn with_fingerprint(scene: &'a Scene, region: Region, fingerprint: u64) -> Progressive<'a> {
+        assert!(
+            region.fits(scene.width(), scene.height()),
+            "region lies outside the image"
+        );
+        Progressive {
+            scene,
+            region,
+            total: Framebuffer::new(region.width, region.height),
+            passes: 0,
+            target: scene.samples(),
+            time_limit: None,
+            snapshot_passes: None,
+            snapshot_interval: None,
+            checkpoint: None,
+            fingerprint,
+        }
+    }
+
+    /// Picks up a render from a checkpoint saved by `save_checkpoint`, aiming for the scene's own
+    /// sample count.  Fails if the checkpoint was made from a different scene, though the
+    /// number of samples may differ.
+    pub fn resume<P: AsRef<Path>>(
+        scene: &'a Scene,
+        path: P,
+    ) -> Result<Progressive<'a>, CheckpointError> {
+        Progressive::read_checkpoint(scene, &mut BufReader::new(File::open(path)?))
+    }
+
+    /// Reads a checkpoint written by `write_checkpoint`.
+    pub fn read_checkpoint<R: Read>(
+        scene: &'a Scene,
+        reader: &mut R,
+    ) -> Result<Progressive<'a>, CheckpointError> {
+        let mut magic = [0; 8];
+        reader.read_exact(&mut magic)?;
+        if &magic != CHECKPOINT_MAGIC {
+            return Err(CheckpointError::Invalid(
+                "not a checkpoint file".to_string(),
+            ));
+        }
+        let version = read_u32(reader)?;
+        if version != CHECKPOINT_VERSION {
+            return Err(CheckpointError::Invalid(format!(
+                "unsupported version {}",
+                version
+            )));
+        }
+        let fingerprint = read_u64(reader)?;
+        let seed = read_u64(reader)?;
+        if fingerprint != scene.fingerprint() || seed != scene.seed {
+            return Err(CheckpointError::SceneChanged);
+        }
+        let region = Region::new(
+            read_u32(reader)?,
+            read_u32(reader)?,
+            read_u32(reader)?,
+            read_u32(reader)?,
+        );
+        if !region.fits(scene.width(), scene.height()) {
+            return Err(CheckpointError::Invalid(
+                "the region lies outside the image".to_string(),
+            ));
+        }
+        let passes = read_u32(reader)?;
+
+        let mut progressive = Progressive::with_fingerprint(scene, region, fingerprint);
+        progressive.passes = passes;
+        for total in progressive.total.pixels.iter_mut() {
+            *total = Color::new(read_f32(reader)?, read_f32(reader)?, read_f32(reader)?);
+        }
+        Ok(progressive)
+    }
+
+    /// Saves everything needed to carry on rendering later with `resume`.  The file is replaced
+    /// in one step, so a crash while saving leaves the previous checkpoint intact.
+    pub fn save_checkpoint<P: AsRef<Path>>(&self, path: P) -> io::Result<()> {
+        let path = path.as_ref();
+        let mut partial = path.as_os_str().to_owned();
+        partial.push(".partial");
+        let partial = PathBuf::from(partial);
+        let mut file = BufWriter::new(File::create(&partial)?);
+        self.write_checkpoint(&mut file)?;
+        file.into_inner()?.sync_all()?;
+        fs::rename(partial, path)
+    }
+
+    /// Writes the render's progress: the sum of the samples taken for each pixel, how many
+    /// samples that is, which samples come next, and a fingerprint of the scene to check it
+    /// against when resuming.  Every pixel has the same number of samples, so only one count is
+    /// stored.  Numbers are little endian.
+    pub fn write_checkpoint<W: Write>(&self, writer: &mut W) -> io::Result<()> {
+        writer.write_all(CHECKPOINT_MAGIC)?;
+        writer.write_all(&CHECKPOINT_VERSION.to_le_bytes())?;
+        writer.write_all(&self.fingerprint.to_le_bytes())?;
+        writer.write_all(&self.scene.seed.to_le_bytes())?;
+        let region = self.region;
+        for n in &[region.x, region.y, region.width, region.height, self.passes] {
+            writer.write_all(&n.to_le_bytes())?;
+        }
+        for total in &self.total.pixels {
+            for channel in &[total.red, total.green, total.blue] {
+                writer.write_all(&channel.to_le_bytes())?;
+            }
+        }
+        Ok(())
+    }
+
+    pub fn region(&self) -> Region {
+        self.region
+    }
+
+    /// How many samples each pixel has so far.
+    pub fn passes(&self) -> u32 {
+        self.passes
+    }
+
+    /// Sets how many samples each pixel should end up with.
+    pub fn set_target(&mut self, samples: u32) {
+        self.target = samples;
+    }
+
+    /// Stops rendering before this much time has passed, even if the target hasn't been reached.
+    /// A pass isn't started unless it's expected to finish in time, judging by how long the
+    /// earlier passes took, but the first pass is always taken.
+    pub fn set_time_limit(&mut self, limit: Duration) {
+        self.time_limit = Some(limit);
+    }
+
+    /// Hands out a snapshot of the image after every `passes` passes.
+    pub fn set_snapshot_passes(&mut self, passes: u32) {
+        self.snapshot_passes = Some(passes.max(1));
+    }
+
+    /// Hands out a snapshot of the image whenever this much time has passed since the last one.
+    pub fn set_snapshot_interval(&mut self, interval: Duration) {
+        self.snapshot_interval = Some(interval);
+    }
+
+    /// Saves a checkpoint to `path` whenever this much time has passed since the last one, and
+    /// once more when rendering stops.
+    pub fn set_checkpoint<P: Into<PathBuf>>(&mut self, path: P, interval: Duration) {
+        self.checkpoint = Some((path.into(), interval));
+    }
+
+    /// Whether every pixel has as many samples as it should.
+    pub fn is_finished(&self) -> bool {
+        self.passes >= self.target
+    }
+
+    /// Adds one more sample to every pixel.
+    pub fn pass(&mut self) {
+        let sample = self.passes;
+        let image = self.scene.render_samples(self.region, sample..sample + 1);
+        for (total, color) in self.total.pixels.iter_mut().zip(image.pixels) {
+            *total += color;
+        }
+        self.passes += 1;
+    }
+
+    /// The image as it stands, averaging the samples taken so far.
+    pub fn image(&self) -> Framebuffer {
+        let count = self.passes.max(1) as f32;
+        let pixels = self
+            .total
+            .pixels
+            .iter()
+            .map(|&color| color / count)
+            .collect();
+        Framebuffer::from_pixels(self.region.width, self.region.height, pixels)
+    }
+
+    /// Renders passes until the target or time limit is reached, calling `snapshot` with the
+    /// image and its sample count as often as asked for.  An error from `snapshot`, or from
+    /// saving a checkpoint, stops the render early.  Returns the finished image.
+    pub fn render<F, E>(&mut self, mut snapshot: F) -> Result<Framebuffer, E>
+    where
+        F: FnMut(&Framebuffer, u32) -> Result<(), E>,
+        E: From<io::Error>,
+    {
+        let start = Instant::now();
+        let first_pass = self.passes;
+        let mut last_snapshot = start;
+        let mut last_checkpoint = start;
+        while !self.is_finished() {
+            // always take a pass, so there's something to show and a time to go by
+            if self.passes > first_pass {
+                let elapsed = start.elapsed();
+                let per_pass = elapsed / (self.passes - first_pass);
+                let out_of_time = self
+                    .time_limit
+                    .is_some_and(|limit| elapsed + per_pass > limit);
+                if out_of_time {
+                    break;
+                }
+            }
+            self.pass();
+            if self.is_finished() {
+                break;
+            }
+            if let Some((path, interval)) = &self.checkpoint {
+                if last_checkpoint.elapsed() >= *interval {
+                    self.save_checkpoint(path)?;
+                    last_checkpoint = Instant::now();
+                }
+            }
+            let every_passes = self
+                .snapshot_passes
+                .is_some_and(|passes| self.passes.is_multiple_of(passes));
+            let every_interval = self
+                .snapshot_interval
+                .is_some_and(|interval| last_snapshot.elapsed() >= interval);
+            if every_passes || every_interval {
+                snapshot(&self.image(), self.passes)?;
+                last_snapshot = Instant::now();
+            }
+        }
+        if let Some((path, _)) = &self.checkpoint {
+            self.save_checkpoint(path)?;
+        }
+        Ok(self.image())
+    }
+}
+
+fn read_u32<R: Read>(reader: &mut R) -> io::Result<u32> {
+    let mut bytes = [0; 4];
+    reader.read_exact(&mut bytes)?;
+    Ok(u32::from_le_bytes(bytes))
+}
+
+fn read_u64<R: Read>(reader: &mut R) -> io::Result<u64> {
+    let mut bytes = [0; 8];
+    reader.read_exact(&mut bytes)?;
+    Ok(u64::from_le_bytes(bytes))
+}
+
+fn read_f32<R: Read>(reader: &mut R) -> io::Result<f32> {
+    read_u32(reader).map(f32::from_bits)
+}
+
+/// Hashes text with 64 bit FNV-1a, which unlike the standard library's hasher gives the same
+/// answer in every build, so fingerprints can be saved.
+struct Fnv(u64);
+
+impl fmt::Write for Fnv {
+    fn write_str(&mut self, text: &str) -> fmt::Result {
+        for byte in text.bytes() {
+            self.0 = (self.0 ^ u64::from(byte)).wrapping_mul(0x100_0000_01b3);
+        }
+        Ok(())
+    }
+}
+
+impl Scene {
+    /// A hash of everything that affects how the scene looks, apart from the number of samples,
+    /// used to tell whether a checkpoint belongs to it.
+    pub fn fingerprint(&self) -> u64 {
+        let mut hash = Fnv(0xcbf2_9ce4_8422_2325);
+        write!(
+            hash,
+            "{}x{} {:?} {:?} {:?} {} {} {:?} {:?} {:?} {:?}",
+            self.width,
+            self.height,
+            self.fov,
+            self.background,
+            self.camera,
+            self.tracing_depth,
+            self.seed,
+            self.geometry,
+            self.lights,
+            self.materials,
+            self.graph
+        )
+        .expect("hashing can't fail");
+        hash.0
+    }
+
+    /// Renders `region` for as long as `budget` allows, adding samples to every pixel in turn
+    /// so they all end up with the same number.  Returns the image along with how many samples
+    /// each pixel got.
+    ///
+    /// # Panics
+    /// Panics if `region` doesn't fit within the image.
+    pub fn render_for(&self, region: Region, budget: Duration) -> (Framebuffer, u32) {
+        let mut progressive = Progressive::new(self, region);
+        progressive.set_target(u32::MAX);
+        progressive.set_time_limit(budget);
+        let image = progressive
+            .render(|_, _| Ok::<_, io::Error>(()))
+            .expect("only checkpoints can fail to render");
+        (image, progressive.passes())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use nalgebra::*;
+
+    fn scene() -> Scene {
+        let mut scene = Scene::new(4, 3, 90.0, 4, Color::new(0.1, 0.2, 0.3));
+        let white = scene.add_material(Diffuse::new(Color::new(1.0, 1.0, 1.0), 0.5));
+        scene.add_geometry(Sphere::new(Point3::new(0.5, 0.0, 3.0), 1.5, white));
+        scene.add_light(SphericalLight::new(
+            Point3::new(0.0, 3.0, 0.0),
+            Color::new(1.0, 1.0, 1.0),
+            50.0,
+        ));
+        scene
+    }
+
+    #[test]
+    fn test_progressive() {
+        let scene = scene();
+        let region = Region::new(0, 0, 4, 3);
+        let mut progressive = Progressive::new(&scene, region);
+        progressive.set_snapshot_passes(2);
+        let mut snapshots = Vec::new();
+        let image = progressive
+            .render(|_, samples| {
+                snapshots.push(samples);
+                Ok::<_, io::Error>(())
+            })
+            .unwrap();
+
+        // the passes add up to the same samples as rendering all at once
+        assert_eq!(image, scene.render_image());
+        assert_eq!(progressive.passes(), 4);
+        assert_eq!(snapshots, vec![2]);
+    }
+
+    #[test]
+    fn test_stop_early() {
+        let scene = scene();
+        let mut progressive = Progressive::new(&scene, Region::new(1, 1, 2, 2));
+        progressive.set_snapshot_passes(1);
+        let result = progressive.render(|_, samples| {
+            if samples < 2 {
+                Ok(())
+            } else {
+                Err(io::Error::other("stop"))
+            }
+        });
+        assert!(result.is_err());
+        assert_eq!(progressive.passes(), 2);
+
+        let mut progressive = Progressive::new(&scene, Region::new(0, 0, 4, 3));
+        progressive.set_time_limit(Duration::from_secs(0));
+        progressive.render(|_, _| Ok::<_, io::Error>(())).unwrap();
+        assert_eq!(progressive.passes(), 1);
+    }
+
+    #[test]
+    fn test_render_for() {
+        let scene = scene();
+        let region = Region::new(0, 0, 4, 3);
+        let (image, samples) = scene.render_for(region, Duration::from_millis(50));
+        assert!(samples >= 1);
+
+        // every pixel has the same samples, so it matches an ordinary render of that many
+        let mut expected = scene;
+        expected.set_samples(samples);
+        assert_eq!(image, expected.render_image());
+    }
+
+    #[test]
+    fn test_checkpoint() {
+        let original = scene();
+        let region = Region::new(1, 0, 3, 2);
+        let mut progressive = Progressive::new(&original, region);
+        progressive.pass();
+        let mut checkpoint = Vec::new();
+        progressive.write_checkpoint(&mut checkpoint).unwrap();
+
+        // carrying on from the checkpoint gives the same image as rendering straight through
+        let mut resumed = Progressive::read_checkpoint(&original, &mut &checkpoint[..]).unwrap();
+        assert_eq!(resumed.region(), region);
+        assert_eq!(resumed.passes(), 1);
+        let image = resumed.render(|_, _| Ok::<_, io::Error>(())).unwrap();
+        assert_eq!(image, original.render_region(region));
+
+        // asking for more samples is fine, but not changing the scene
+        let mut more = scene();
+        more.set_samples(10);
+        assert!(Progressive::read_checkpoint(&more, &mut &checkpoint[..]).is_ok());
+        let mut moved = scene();
+        moved.set_fov(60.0);
+        assert!(matches!(
+            Progressive::read_checkpoint(&moved, &mut &checkpoint[..]),
+            Err(CheckpointError::SceneChanged)
+        ));
+        assert!(matches!(
+            Progressive::read_checkpoint(&original, &mut &checkpoint[..20]),
+            Err(CheckpointError::Invalid(_))
+        ));
+    }
+}
@@ -0,0 +1,127 @@
+//! Images mapped onto surfaces through their texture coordinates.
+
+use nalgebra::*;
+use std::sync::Arc;
+
+use crate::prelude::*;
+
+/// An image of linear colors, looked up by texture coordinates.  `(0, 0)` is the top left corner
+/// of the image and `(1, 1)` the bottom right; coordinates outside of that range wrap around.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Texture {
+    pub(crate) width: usize,
+    pub(crate) height: usize,
+    pub(crate) pixels: Vec<Color>,
+}
+
+impl Texture {
+    /// Creates a texture from its pixels, stored one row at a time.
+    pub fn new(width: usize, height: usize, pixels: Vec<Color>) -> Texture {
+        assert!(width > 0 && height > 0, "textures can't be empty");
+        assert_eq!(pixels.len(), width * height, "wrong number of pixels");
+        Texture {
+            width,
+            height,
+            pixels,
+        }
+    }
+
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    pub fn height(&self) -> usize {
+        self.height
+    }
+
+    /// Looks up the color at `uv`, blending between the four nearest pixels.
+    pub fn sample(&self, uv: &Point2<f32>) -> Color {
+        // pixel centers sit at half-integer coordinates
+        let x = uv.x * self.width as f32 - 0.5;
+        let y = uv.y * self.height as f32 - 0.5;
+        let (x0, y0) = (x.floor(), y.floor());
+        let (tx, ty) = (x - x0, y - y0);
+
+        let pixel = |x: f32, y: f32| {
+            let x = (x as i64).rem_euclid(self.width as i64) as usize;
+            let y = (y as i64).rem_euclid(self.height as i64) as usize;
+            self.pixels[y * self.width + x]
+        };
+        let top = pixel(x0 + 1.0, y0).lerp(pixel(x0, y0), tx);
+        let bottom = pixel(x0 + 1.0, y0 + 1.0).lerp(pixel(x0, y0 + 1.0), tx);
+        bottom.lerp(top, ty)
+    }
+}
+
+/// A color that may vary over a surface.
+#[derive(Debug, Clone)]
+pub enum ColorSource {
+    Constant(Color),
+    /// A texture, with every lookup multiplied by a constant factor
+    Texture(Arc<Texture>, Color),
+    /// The colors of a mesh's vertices, multiplied by a constant factor.  Surfaces without
+    /// vertex colors use the factor alone.
+    Vertex(Color),
+}
+
+impl ColorSource {
+    /// The color at the hit point.
+    pub fn at(&self, surface: &SurfaceInteraction) -> Color {
+        match self {
+            ColorSource::Constant(color) => *color,
+            ColorSource::Texture(texture, factor) => texture.sample(&surface.uv) * *factor,
+            ColorSource::Vertex(factor) => surface.color.map_or(*factor, |c| c * *factor),
+        }
+    }
+}
+
+impl From<Color> for ColorSource {
+    fn from(color: Color) -> Self {
+        ColorSource::Constant(color)
+    }
+}
+
+impl From<Arc<Texture>> for ColorSource {
+    fn from(texture: Arc<Texture>) -> Self {
+        ColorSource::Texture(texture, Color::new(1.0, 1.0, 1.0))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_sample() {
+        let black = Color::default();
+        let white = Color::new(1.0, 1.0, 1.0);
+        let texture = Texture::new(2, 1, vec![black, white]);
+
+        // pixel centers come out exactly
+        assert_eq!(texture.sample(&Point2::new(0.25, 0.5)), black);
+        assert_eq!(texture.sample(&Point2::new(0.75, 0.5)), white);
+        // halfway between them is a blend
+        assert_eq!(texture.sample(&Point2::new(0.5, 0.5)), white * 0.5);
+        // and the texture repeats
+        assert_eq!(texture.sample(&Point2::new(1.25, -3.5)), black);
+        assert_eq!(texture.sample(&Point2::new(0.0, 0.5)), white * 0.5);
+
+        let ray = Ray::new(Point3::new(0.0, 0.0, 0.0), Vector3::new(0.0, 0.0, 1.0));
+        let mut surface = SurfaceInteraction::new(
+            &ray,
+            1.0,
+            Vector3::new(0.0, 0.0, -1.0),
+            Point2::new(0.75, 0.5),
+            Vector3::new(1.0, 0.0, 0.0),
+            Vector3::new(0.0, 1.0, 0.0),
+        );
+        let tinted = ColorSource::Texture(Arc::new(texture), Color::new(1.0, 0.5, 0.0));
+        assert_eq!(tinted.at(&surface), Color::new(1.0, 0.5, 0.0));
+
+        // vertex colors fall back to the factor on surfaces that have none
+        let vertex = ColorSource::Vertex(Color::new(0.5, 0.5, 0.5));
+        assert_eq!(vertex.at(&surface), Color::new(0.5, 0.5, 0.5));
+        surface.color = Some(Color::new(1.0, 0.0, 0.5));
+        assert_eq!(vertex.at(&surface), Color::new(0.5, 0.0, 0.25));
+    }
+}
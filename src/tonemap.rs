@@ -0,0 +1,161 @@
+//! Turning the unbounded radiance of a rendered image into colors a display can show.
+
+use crate::prelude::*;
+
+/// Ways of squeezing radiance into `[0, 1]`.  Every operator leaves black alone and maps all
+/// colors into range; they differ in how they treat highlights.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ToneMap {
+    /// Cuts off anything brighter than 1, so highlights blow out to white
+    Clamp,
+    /// Reinhard's `L / (1 + L)` curve on luminance, which never quite reaches white
+    Reinhard,
+    /// Reinhard's curve extended to reach white at the given luminance
+    ExtendedReinhard { white: f32 },
+    /// John Hable's filmic curve from Uncharted 2, with a toe and a soft shoulder
+    Hable,
+    /// Stephen Hill's fit of the ACES reference rendering and sRGB output transforms
+    Aces,
+}
+
+/// Rec. 709 luminance of a linear color.
+fn luminance(color: Color) -> f32 {
+    0.2126 * color.red + 0.7152 * color.green + 0.0722 * color.blue
+}
+
+/// Scales `color` so its luminance becomes `target`, keeping its hue.
+fn with_luminance(color: Color, target: f32) -> Color {
+    let current = luminance(color);
+    if current > 0.0 {
+        color * (target / current)
+    } else {
+        color
+    }
+}
+
+/// The curve from Hable's filmic tone mapper, before it's scaled to reach white.
+fn hable_curve(x: f32) -> f32 {
+    const A: f32 = 0.15; // shoulder strength
+    const B: f32 = 0.50; // linear strength
+    const C: f32 = 0.10; // linear angle
+    const D: f32 = 0.20; // toe strength
+    const E: f32 = 0.02; // toe numerator
+    const F: f32 = 0.30; // toe denominator
+    (x * (A * x + C * B) + D * E) / (x * (A * x + B) + D * F) - E / F
+}
+
+/// Multiplies a color by a matrix, given a row at a time.
+fn transform(m: &[[f32; 3]; 3], c: Color) -> Color {
+    let row = |r: &[f32; 3]| r[0] * c.red + r[1] * c.green + r[2] * c.blue;
+    Color::new(row(&m[0]), row(&m[1]), row(&m[2]))
+}
+
+impl ToneMap {
+    /// Maps a linear color into `[0, 1]`, still linear.
+    pub fn apply(&self, color: Color) -> Color {
+        let per_channel =
+            |f: &dyn Fn(f32) -> f32| Color::new(f(color.red), f(color.green), f(color.blue));
+        let mapped = match *self {
+            ToneMap::Clamp => color,
+            ToneMap::Reinhard => {
+                let l = luminance(color);
+                with_luminance(color, l / (1.0 + l))
+            }
+            ToneMap::ExtendedReinhard { white } => {
+                let l = luminance(color);
+                let white2 = (white * white).max(1e-6);
+                with_luminance(color, l * (1.0 + l / white2) / (1.0 + l))
+            }
+            ToneMap::Hable => {
+                const EXPOSURE_BIAS: f32 = 2.0;
+                const WHITE: f32 = 11.2;
+                let scale = 1.0 / hable_curve(WHITE);
+                per_channel(&|x| hable_curve(x * EXPOSURE_BIAS) * scale)
+            }
+            ToneMap::Aces => {
+                // sRGB to the ACES rendering space, and back, with the RRT saturation folded in
+                const INPUT: [[f32; 3]; 3] = [
+                    [0.59719, 0.35458, 0.04823],
+                    [0.07600, 0.90834, 0.01566],
+                    [0.02840, 0.13383, 0.83777],
+                ];
+                const OUTPUT: [[f32; 3]; 3] = [
+                    [1.60475, -0.53108, -0.07367],
+                    [-0.10208, 1.10813, -0.00605],
+                    [-0.00327, -0.07276, 1.07602],
+                ];
+                let v = transform(&INPUT, color);
+                let fit = |v: f32| {
+                    (v * (v + 0.024_578_6) - 0.000_090_537)
+                        / (v * (0.983_729 * v + 0.432_951) + 0.238_081)
+                };
+                transform(&OUTPUT, Color::new(fit(v.red), fit(v.green), fit(v.blue)))
+            }
+        };
+        mapped.clamp()
+    }
+}
+
+impl Framebuffer {
+    /// Prepares the image for display: scales it by `2^exposure`, then tone maps it into
+    /// `[0, 1]`.  The result is still linear; gamma is applied when it's saved.
+    pub fn tone_mapped(&self, exposure: f32, tone_map: ToneMap) -> Framebuffer {
+        let scale = exposure.exp2();
+        let pixels = self
+            .pixels()
+            .iter()
+            .map(|&color| tone_map.apply(color * scale))
+            .collect();
+        Framebuffer::from_pixels(self.width(), self.height(), pixels)
+            .with_color_space(self.color_space)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const OPERATORS: [ToneMap; 5] = [
+        ToneMap::Clamp,
+        ToneMap::Reinhard,
+        ToneMap::ExtendedReinhard { white: 4.0 },
+        ToneMap::Hable,
+        ToneMap::Aces,
+    ];
+
+    #[test]
+    fn test_tone_map() {
+        for tone_map in &OPERATORS {
+            // black stays black, and everything lands in range, brighter staying brighter
+            let black = tone_map.apply(Color::default());
+            assert!(luminance(black) < 1e-3, "{:?}", tone_map);
+            let mut previous = luminance(black);
+            for &value in &[0.01, 0.1, 0.5, 1.0, 2.0, 10.0, 1000.0] {
+                let mapped = tone_map.apply(Color::new(value, value, value));
+                let l = luminance(mapped);
+                assert!((0.0..=1.0).contains(&l), "{:?} of {}", tone_map, value);
+                assert!(l >= previous, "{:?} of {}", tone_map, value);
+                previous = l;
+            }
+        }
+
+        let extended = ToneMap::ExtendedReinhard { white: 4.0 };
+        assert!((luminance(extended.apply(Color::new(4.0, 4.0, 4.0))) - 1.0).abs() < 1e-5);
+        let reinhard = ToneMap::Reinhard.apply(Color::new(2.0, 1.0, 0.0));
+        assert!((reinhard.red / reinhard.green - 2.0).abs() < 1e-5);
+        assert_eq!(
+            ToneMap::Clamp.apply(Color::new(0.5, 7.0, -1.0)),
+            Color::new(0.5, 1.0, 0.0)
+        );
+    }
+
+    #[test]
+    fn test_exposure() {
+        let mut image = Framebuffer::new(1, 1);
+        image.set_pixel(0, 0, Color::new(0.25, 0.5, 1.0));
+        let brighter = image.tone_mapped(1.0, ToneMap::Clamp);
+        assert_eq!(brighter.pixel(0, 0), Color::new(0.5, 1.0, 1.0));
+        let darker = image.tone_mapped(-2.0, ToneMap::Clamp);
+        assert_eq!(darker.pixel(0, 0), Color::new(0.0625, 0.125, 0.25));
+    }
+}
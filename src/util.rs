@@ -1,4 +1,3 @@
-use itertools::Itertools;
 use nalgebra::*;
 use rand::prelude::*;
 use rayon::prelude::*;
@@ -6,6 +5,7 @@ use rayon::prelude::*;
 use std::io;
 use std::io::prelude::*;
 use std::ops::*;
+use std::sync::Arc;
 
 use crate::prelude::*;
 
@@ -26,25 +26,28 @@ impl Color {
         *self * amount + other * (1.0 - amount)
     }
 
+    /// Quantizes the color to 8 bits a channel, rounding to the nearest value.
     pub fn to_rgb(&self) -> (u8, u8, u8) {
         (
-            (self.red * 255.0) as u8,
-            (self.green * 255.0) as u8,
-            (self.blue * 255.0) as u8,
+            (self.red * 255.0).round() as u8,
+            (self.green * 255.0).round() as u8,
+            (self.blue * 255.0).round() as u8,
         )
     }
 
     pub fn clamp(&self) -> Color {
         Color {
-            red: self.red.min(1.0).max(0.0),
-            green: self.green.min(1.0).max(0.0),
-            blue: self.blue.min(1.0).max(0.0),
+            red: self.red.clamp(0.0, 1.0),
+            green: self.green.clamp(0.0, 1.0),
+            blue: self.blue.clamp(0.0, 1.0),
         }
     }
 
     pub const GAMMA: f32 = 2.2;
     pub const INV_GAMMA: f32 = 1.0 / 2.2;
 
+    /// Decodes a color encoded with a plain 2.2 power curve.  This is close to, but not quite,
+    /// sRGB; use `from_srgb` for colors taken from images and color pickers.
     pub fn from_gamma(&self) -> Color {
         Color {
             red: self.red.powf(Color::GAMMA),
@@ -60,6 +63,31 @@ impl Color {
             blue: self.blue.powf(Color::INV_GAMMA),
         }
     }
+
+    /// Decodes an sRGB encoded color into linear light, using the exact piecewise curve from
+    /// IEC 61966-2-1.
+    pub fn from_srgb(&self) -> Color {
+        let decode = |c: f32| {
+            if c <= 0.04045 {
+                c / 12.92
+            } else {
+                ((c + 0.055) / 1.055).powf(2.4)
+            }
+        };
+        Color::new(decode(self.red), decode(self.green), decode(self.blue))
+    }
+
+    /// Encodes a linear color with the sRGB curve, ready to be stored in an 8 bit image.
+    pub fn to_srgb(&self) -> Color {
+        let encode = |c: f32| {
+            if c <= 0.003_130_8 {
+                c * 12.92
+            } else {
+                1.055 * c.powf(1.0 / 2.4) - 0.055
+            }
+        };
+        Color::new(encode(self.red), encode(self.green), encode(self.blue))
+    }
 }
 
 impl Add for Color {
@@ -121,30 +149,143 @@ impl Div<f32> for Color {
     }
 }
 
-/// A ray used in tracing the scene.
+/// Scales `v` to unit length.  Vectors whose length is already within the rounding error
+/// `normalize` leaves behind are kept as they are, which makes normalizing idempotent: a
+/// normalized vector, saved and loaded again, comes back with exactly the same bits.
+pub(crate) fn unit_vector(v: &Vector3<f32>) -> Vector3<f32> {
+    if (v.norm() - 1.0).abs() <= 2.0 * f32::EPSILON {
+        *v
+    } else {
+        v.normalize()
+    }
+}
+
+/// Builds a pair of unit vectors that, together with `normal`, form a right-handed orthonormal
+/// basis.  `normal` must be normalized.
+pub fn coordinate_system(normal: &Vector3<f32>) -> (Vector3<f32>, Vector3<f32>) {
+    let helper = if normal.x.abs() > 0.9 {
+        Vector3::new(0.0, 1.0, 0.0)
+    } else {
+        Vector3::new(1.0, 0.0, 0.0)
+    };
+    let tangent = helper.cross(normal).normalize();
+    let bitangent = normal.cross(&tangent);
+    (tangent, bitangent)
+}
+
+/// A ray used in tracing the scene.  Only hits with a distance in `[tmin, tmax]` count.
 #[derive(Debug, Clone)]
 pub struct Ray {
     pub(crate) source: Point3<f32>,
     pub(crate) direction: Vector3<f32>,
+    pub(crate) tmin: f32,
+    pub(crate) tmax: f32,
 }
 
 impl Ray {
+    /// Creates a ray that extends infinitely far from `source`.
     pub fn new(source: Point3<f32>, direction: Vector3<f32>) -> Ray {
         Ray {
             source,
             direction: direction.normalize(),
+            tmin: 0.0,
+            tmax: f32::INFINITY,
         }
     }
 
-    pub fn reflect(
-        normal: Vector3<f32>,
-        incident: Vector3<f32>,
-        intersection: &Point3<f32>,
-        bias: f32,
-    ) -> Ray {
-        Ray::new(
-            intersection + (normal * bias),
-            incident - (2.0 * incident.dot(&normal) * normal),
+    /// Creates a ray that only reports hits strictly between `from` and `to`.
+    pub fn segment(from: Point3<f32>, to: Point3<f32>) -> Ray {
+        let direction = to - from;
+        Ray {
+            source: from,
+            direction: direction.normalize(),
+            tmin: 0.0,
+            tmax: direction.norm() * (1.0 - Ray::SHADOW_EPSILON),
+        }
+    }
+
+    /// Restricts the ray to hits with a distance between `tmin` and `tmax`.
+    pub fn with_interval(self, tmin: f32, tmax: f32) -> Ray {
+        Ray { tmin, tmax, ..self }
+    }
+
+    pub fn source(&self) -> Point3<f32> {
+        self.source
+    }
+
+    /// The direction of the ray.  Always normalized.
+    pub fn direction(&self) -> Vector3<f32> {
+        self.direction
+    }
+
+    pub fn tmin(&self) -> f32 {
+        self.tmin
+    }
+
+    pub fn tmax(&self) -> f32 {
+        self.tmax
+    }
+
+    /// Whether a hit at distance `t` lies within the ray's interval.
+    pub fn contains(&self, t: f32) -> bool {
+        t >= self.tmin && t <= self.tmax
+    }
+
+    /// Reflects `incident` about the shading normal of `surface`, starting at the hit point.
+    pub fn reflect(surface: &SurfaceInteraction, incident: Vector3<f32>) -> Ray {
+        let normal = surface.shading_normal;
+        surface.spawn_ray(incident - (2.0 * incident.dot(&normal) * normal))
+    }
+
+    /// Relative distance a ray origin is pushed off of a surface to keep it from hitting the
+    /// surface it was spawned from.  Scaled by the magnitude of the hit point, since that's what
+    /// the floating point error of the hit point depends on.
+    pub const ORIGIN_EPSILON: f32 = 1e-4;
+
+    /// Fraction of a segment's length cut off at its far end, so that surfaces sitting exactly at
+    /// the target (such as the surface being lit) don't count as blockers.
+    pub const SHADOW_EPSILON: f32 = 1e-4;
+}
+
+/// Where the scene is viewed from: a camera at `eye` looking towards `target`, with `up`
+/// pointing towards the top of the image.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Camera {
+    pub(crate) eye: Point3<f32>,
+    pub(crate) target: Point3<f32>,
+    pub(crate) up: Vector3<f32>,
+}
+
+impl Camera {
+    pub fn look_at(eye: Point3<f32>, target: Point3<f32>, up: Vector3<f32>) -> Camera {
+        Camera { eye, target, up }
+    }
+
+    pub fn eye(&self) -> Point3<f32> {
+        self.eye
+    }
+
+    pub fn target(&self) -> Point3<f32> {
+        self.target
+    }
+
+    pub fn up(&self) -> Vector3<f32> {
+        self.up
+    }
+
+    /// Moves rays from camera space, where the camera looks down +z with +y up, into the world.
+    fn transform(&self) -> Isometry3<f32> {
+        Isometry3::face_towards(&self.eye, &self.target, &self.up)
+    }
+}
+
+impl Default for Camera {
+    /// A camera at the origin looking down +z.
+    fn default() -> Camera {
+        Camera::look_at(
+            Point3::new(0.0, 0.0, 0.0),
+            Point3::new(0.0, 0.0, 1.0),
+            Vector3::new(0.0, 1.0, 0.0),
         )
     }
 }
@@ -154,14 +295,26 @@ pub struct Scene {
     pub(crate) height: u32,
     pub(crate) width: u32,
     pub(crate) samples: u32,
+    /// Vertical field of view, in degrees
     pub(crate) fov: f32,
     pub(crate) background: Color,
+    pub(crate) camera: Camera,
     pub(crate) geometry: Vec<Geometry>,
     pub(crate) tracing_depth: u32,
+    /// Seeds the random numbers used to sample each pixel
+    pub(crate) seed: u64,
     pub(crate) lights: Vec<Light>,
+    pub(crate) materials: Vec<Arc<Material>>,
+    pub(crate) graph: Node,
+    /// Geometry from the scene graph, placed in world space
+    pub(crate) graph_geometry: Vec<Geometry>,
+    /// Lights from the scene graph, placed in world space
+    pub(crate) graph_lights: Vec<Light>,
 }
 
 impl Scene {
+    /// Creates an empty scene rendered at `width` by `height` pixels, seeing `fov` degrees from
+    /// the top of the image to the bottom.
     pub fn new(width: u32, height: u32, fov: f32, samples: u32, background: Color) -> Scene {
         Scene {
             height,
@@ -169,12 +322,152 @@ impl Scene {
             samples,
             fov,
             background,
+            camera: Camera::default(),
             geometry: Vec::new(),
             tracing_depth: 3,
+            seed: 0,
             lights: Vec::new(),
+            materials: Vec::new(),
+            graph: Node::new("root"),
+            graph_geometry: Vec::new(),
+            graph_lights: Vec::new(),
         }
     }
 
+    pub fn width(&self) -> u32 {
+        self.width
+    }
+
+    pub fn height(&self) -> u32 {
+        self.height
+    }
+
+    /// Changes the size of the image, in pixels.
+    pub fn set_resolution(&mut self, width: u32, height: u32) {
+        self.width = width;
+        self.height = height;
+    }
+
+    /// The number of samples taken for each pixel.
+    pub fn samples(&self) -> u32 {
+        self.samples
+    }
+
+    pub fn set_samples(&mut self, samples: u32) {
+        self.samples = samples;
+    }
+
+    pub fn camera(&self) -> &Camera {
+        &self.camera
+    }
+
+    pub fn set_camera(&mut self, camera: Camera) {
+        self.camera = camera;
+    }
+
+    /// Sets the vertical field of view, in degrees.
+    pub fn set_fov(&mut self, fov: f32) {
+        self.fov = fov;
+    }
+
+    /// Sets how many times rays may bounce around the scene.
+    pub fn set_tracing_depth(&mut self, depth: u32) {
+        self.tracing_depth = depth;
+    }
+
+    /// Sets the seed for the random numbers used while rendering.  Rendering a scene twice with
+    /// the same seed produces the same image.
+    pub fn set_seed(&mut self, seed: u64) {
+        self.seed = seed;
+    }
+
+    /// The root of the scene graph.
+    pub fn graph(&self) -> &Node {
+        &self.graph
+    }
+
+    /// Looks up a node in the scene graph by its path from the root, such as `"table/cup"`.
+    pub fn node(&self, path: &str) -> Option<&Node> {
+        self.graph.find(path)
+    }
+
+    /// Adds a node under the root of the scene graph.
+    pub fn add_node(&mut self, node: Node) {
+        self.edit_node("", |root| {
+            root.add_child(node);
+        });
+    }
+
+    /// Changes the node at `path`, e.g. to move it or attach something to it.  Returns whether
+    /// the node was found.
+    pub fn edit_node<F>(&mut self, path: &str, edit: F) -> bool
+    where
+        F: FnOnce(&mut Node),
+    {
+        match self.graph.find_mut(path) {
+            Some(node) => edit(node),
+            None => return false,
+        }
+        self.flatten_graph();
+        true
+    }
+
+    /// Rebuilds the world space geometry and lights used for rendering from the scene graph.
+    fn flatten_graph(&mut self) {
+        self.graph_geometry.clear();
+        self.graph_lights.clear();
+        self.graph.flatten(
+            &Affine3::identity(),
+            &mut self.graph_geometry,
+            &mut self.graph_lights,
+        );
+    }
+
+    /// Every object in the scene, whether added directly or through the scene graph.
+    pub(crate) fn objects(&self) -> impl Iterator<Item = &Geometry> {
+        self.geometry.iter().chain(&self.graph_geometry)
+    }
+
+    /// Every light in the scene, whether added directly or through the scene graph.
+    pub(crate) fn all_lights(&self) -> impl Iterator<Item = &Light> {
+        self.lights.iter().chain(&self.graph_lights)
+    }
+
+    /// Adds a material to the scene, returning a handle that geometry can use to refer to it.
+    pub fn add_material<M>(&mut self, material: M) -> MaterialId
+    where
+        M: Into<Material>,
+    {
+        self.add_shared_material(Arc::new(material.into()))
+    }
+
+    /// Adds a material that may also be used by other scenes.
+    pub fn add_shared_material(&mut self, material: Arc<Material>) -> MaterialId {
+        self.materials.push(material);
+        MaterialId(self.materials.len() - 1)
+    }
+
+    /// Looks up a material added to this scene.
+    ///
+    /// # Panics
+    /// Panics if `id` didn't come from this scene.
+    pub fn material(&self, id: MaterialId) -> &Material {
+        &self.materials[id.0]
+    }
+
+    /// Gets a shared handle to a material, so it can be added to another scene.
+    pub fn shared_material(&self, id: MaterialId) -> Arc<Material> {
+        Arc::clone(&self.materials[id.0])
+    }
+
+    /// Replaces a material.  Every object using `id` picks up the new material.
+    pub fn set_material<M>(&mut self, id: MaterialId, material: M)
+    where
+        M: Into<Material>,
+    {
+        self.materials[id.0] = Arc::new(material.into());
+    }
+
     /// Adds an object to the scene.
     pub fn add_geometry<G>(&mut self, object: G)
     where
@@ -192,76 +485,166 @@ impl Scene {
 
     /// Creates a prime ray for the pixel at the coordinate (x, y) in image space.  This uses the
     /// convention that (0, 0) in image space corresponds to the upper left corner
-    fn create_camera_ray(&self, x: u32, y: u32) -> Ray {
-        // for now, assume that images are wider than they are tall
-        assert!(self.height < self.width);
+    pub(crate) fn create_camera_ray<R: Rng>(&self, x: u32, y: u32, rng: &mut R) -> Ray {
         let fov_adjustment = (self.fov.to_radians() / 2.0).tan();
         let aspect_ratio = (self.width as f32) / (self.height as f32);
 
-        let camera_x = (((x as f32 + random::<f32>()) / self.width as f32) * 2.0 - 1.0)
+        let camera_x = (((x as f32 + rng.gen::<f32>()) / self.width as f32) * 2.0 - 1.0)
             * aspect_ratio
             * fov_adjustment;
-        let camera_y = 1.0 - ((y as f32 + random::<f32>()) / self.height as f32) * 2.0;
-        Ray {
-            source: Point3::new(0.0, 0.0, 0.0),
-            direction: Vector3::new(camera_x, camera_y, 1.0).normalize(),
-        }
+        let camera_y =
+            (1.0 - ((y as f32 + rng.gen::<f32>()) / self.height as f32) * 2.0) * fov_adjustment;
+        let to_world = self.camera.transform();
+        Ray::new(
+            to_world.transform_point(&Point3::new(0.0, 0.0, 0.0)),
+            to_world.transform_vector(&Vector3::new(camera_x, camera_y, 1.0)),
+        )
     }
 
+    /// Finds the closest object hit by `ray`.
     pub(crate) fn trace<'a>(&'a self, ray: &'a Ray, depth: u32) -> Option<Intersection<'a>> {
         if depth >= self.tracing_depth {
-            None
-        } else {
-            self.geometry
-                .iter()
-                .filter_map(|g| g.intersect(&ray).map(|i| Intersection::new(i, &ray, g)))
-                .min_by(|i1, i2| (&i1.dist).partial_cmp(&i2.dist).unwrap())
+            return None;
+        }
+
+        // shrink the ray as closer hits are found, so farther objects can be rejected early
+        let mut clipped = ray.clone();
+        let mut closest = None;
+        for g in self.objects() {
+            if let Some(surface) = g.intersect(&clipped) {
+                clipped.tmax = surface.t;
+                closest = Some(Intersection::new(surface, ray, g));
+            }
         }
+        closest
+    }
+
+    /// Determines whether anything blocks `ray`.  Stops at the first object found, so this is
+    /// much cheaper than `trace` for shadow rays.
+    pub(crate) fn occluded(&self, ray: &Ray) -> bool {
+        self.objects().any(|g| g.occludes(ray))
+    }
+
+    /// The material at the point `i` hit.
+    pub(crate) fn material_at(&self, i: &Intersection) -> MaterialId {
+        i.surface.material.unwrap_or_else(|| i.elem.material())
+    }
+
+    /// Computes the light leaving the surface at `i` back along the ray that hit it.  This is
+    /// radiance, with no upper limit; images are only squeezed into a displayable range by
+    /// tone mapping, when they're saved.
+    pub(crate) fn shade<R: Rng>(&self, i: &Intersection, depth: u32, rng: &mut R) -> Color {
+        let (direct, indirect) = self.shade_parts(i, depth, rng);
+        direct + indirect
+    }
+
+    /// Computes the light leaving the surface at `i` back along the ray that hit it, split into
+    /// the light given off by the surface or arriving straight from the light sources, and the
+    /// light bouncing off of the rest of the scene.
+    pub(crate) fn shade_parts<R: Rng>(
+        &self,
+        i: &Intersection,
+        depth: u32,
+        rng: &mut R,
+    ) -> (Color, Color) {
+        let surface = &i.surface;
+        let material = self.material(self.material_at(i));
+        let normal = surface.shading_normal;
+        let wo = -i.incoming.direction;
+
+        let mut direct = material.emission(&wo, surface);
+
+        // light arriving straight from the light sources
+        for light in self.all_lights() {
+            if let Some(sample) = light.illuminate(self, surface) {
+                let cos = sample.wi.dot(&normal).abs();
+                direct += material.evaluate(&wo, &sample.wi, surface) * sample.radiance * cos;
+            }
+        }
+
+        // light bouncing off of the rest of the scene
+        let mut indirect = Color::default();
+        let u = Point2::new(rng.gen(), rng.gen());
+        if let Some(sample) = material.sample(&wo, surface, u).filter(|s| s.pdf > 0.0) {
+            let bounce = surface.spawn_ray(sample.wi);
+            let incoming = self
+                .trace(&bounce, depth + 1)
+                .map_or(self.background, |i| self.shade(&i, depth + 1, rng));
+            let cos = sample.wi.dot(&normal).abs();
+            indirect = sample.value * incoming * (cos / sample.pdf);
+        }
+
+        (direct, indirect)
+    }
+
+    /// The random numbers used for one sample of one pixel.  Each sample gets its own stream,
+    /// so the image only depends on the seed, no matter how the work is split up.
+    pub(crate) fn sample_rng(&self, x: u32, y: u32, sample: u32) -> StdRng {
+        let pixel = (u64::from(y) << 32 | u64::from(x)).wrapping_mul(0x9e37_79b9_7f4a_7c15);
+        let sample = u64::from(sample).wrapping_mul(0xc2b2_ae3d_27d4_eb4f);
+        StdRng::seed_from_u64(self.seed ^ pixel ^ sample.rotate_left(32))
     }
 
-    /// Trace a ray
-    fn trace_scene_ray(&self, x: u32, y: u32) -> Color {
+    /// Adds up the given samples of the pixel at (x, y).
+    fn sample_pixel(&self, x: u32, y: u32, samples: Range<u32>) -> Color {
         let mut color = Color::default();
-        for _ in 0..self.samples {
-            let ray = self.create_camera_ray(x, y);
+        for sample in samples {
+            let mut rng = self.sample_rng(x, y, sample);
+            let ray = self.create_camera_ray(x, y, &mut rng);
             color += self
                 .trace(&ray, 0)
-                .map_or_else(|| self.background, |i| i.elem.color(self, &i, 0));
+                .map_or_else(|| self.background, |i| self.shade(&i, 0, &mut rng));
         }
-        color / self.samples as f32
+        color
+    }
+
+    /// Renders the whole image.
+    pub fn render_image(&self) -> Framebuffer {
+        self.render_region(Region::new(0, 0, self.width, self.height))
+    }
+
+    /// Renders part of the image.  The pixels come out exactly as they would in the full image.
+    ///
+    /// # Panics
+    /// Panics if `region` doesn't fit within the image.
+    pub fn render_region(&self, region: Region) -> Framebuffer {
+        self.render_samples(region, 0..self.samples)
     }
 
+    /// Renders only some of the samples of each pixel in `region`, averaged together.  Averaging
+    /// the results of several calls that cover `0..samples` between them gives the full image,
+    /// so an image can be refined a few samples at a time.
+    ///
+    /// # Panics
+    /// Panics if `region` doesn't fit within the image.
+    pub fn render_samples(&self, region: Region, samples: Range<u32>) -> Framebuffer {
+        assert!(
+            region.fits(self.width, self.height),
+            "region lies outside the image"
+        );
+        let count = samples.len().max(1) as f32;
+        let mut image = Framebuffer::new(region.width, region.height);
+        let row_length = (region.width as usize).max(1);
+        image
+            .pixels
+            .par_chunks_mut(row_length) // render rows in parallel
+            .enumerate()
+            .for_each(|(row, pixels)| {
+                let y = region.y + row as u32;
+                for (column, pixel) in pixels.iter_mut().enumerate() {
+                    let x = region.x + column as u32;
+                    *pixel = self.sample_pixel(x, y, samples.clone()) / count;
+                }
+            });
+        image
+    }
+
+    /// Renders the image and writes it out as a PPM file.
     pub fn render<T>(&self, writer: &mut T) -> io::Result<()>
     where
         T: Write,
     {
-        let sorted = (0..self.width)
-            .cartesian_product(0..self.height)
-            .par_bridge() // iterate in parallel
-            .map(|(x, y)| (x, y, self.trace_scene_ray(x, y)))
-            .map(|(x, y, color)| (x, y, color.to_gamma().to_rgb()))
-            .collect::<Vec<_>>()
-            .into_iter()
-            .sorted_by(|(x1, y1, _), (x2, y2, _)| {
-                use std::cmp::Ordering;
-                let ycmp = y1.cmp(y2);
-                if ycmp == Ordering::Equal {
-                    x1.cmp(x2)
-                } else {
-                    ycmp
-                }
-            })
-            .map(|(_, _, colors)| colors);
-
-        writeln!(writer, "P3")?;
-        writeln!(writer, "{} {}", self.width, self.height)?;
-        writeln!(writer, "255")?;
-
-        for (red, green, blue) in sorted {
-            writeln!(writer, "{} {} {}", red, green, blue)?;
-        }
-
-        Ok(())
+        self.render_image().write_ppm(writer)
     }
 }
 
@@ -269,17 +652,126 @@ impl Scene {
 mod tests {
     use super::*;
 
+    #[test]
+    fn test_unit_vector() {
+        let mut rng = StdRng::seed_from_u64(3);
+        for _ in 0..10_000 {
+            let scale = 10.0_f32.powf(rng.gen_range(-3.0, 3.0));
+            let v = Vector3::new(rng.gen::<f32>() - 0.5, rng.gen(), rng.gen::<f32>() - 0.5);
+            let once = unit_vector(&(v * scale));
+            assert!((once.norm() - 1.0).abs() < 1e-6);
+            assert_eq!(unit_vector(&once), once);
+        }
+    }
+
     #[test]
     fn test_color() {
         let color = Color::new(1.0, 1.0, 1.0);
-        let sphere = Sphere::new(
-            Point3::new(0.0, 0.0, 1.0),
-            1.0,
-            Diffuse::new(Color::new(1.0, 1.0, 1.0), 1.0).into(),
-        );
         let mut scene = Scene::new(4, 3, 90.0, 100, Color::default());
-        scene.add_geometry(sphere);
-        let result = scene.trace_scene_ray(3, 2);
-        assert_eq!(result, color);
+        let white = scene.add_material(Diffuse::new(Color::new(1.0, 1.0, 1.0), 1.0));
+        // a sphere large enough to fill the view, lit brightly from behind the camera
+        scene.add_geometry(Sphere::new(Point3::new(0.0, 0.0, 10.0), 9.5, white));
+        scene.add_light(GlobalLight::new(
+            Vector3::new(0.0, 0.0, 1.0),
+            Color::new(1.0, 1.0, 1.0),
+            1000.0,
+        ));
+        let result = scene.render_image().pixel(3, 2);
+        assert_eq!(ToneMap::Clamp.apply(result), color);
+    }
+
+    #[test]
+    fn test_render_region() {
+        let mut scene = Scene::new(4, 3, 90.0, 2, Color::new(0.1, 0.2, 0.3));
+        let white = scene.add_material(Diffuse::new(Color::new(1.0, 1.0, 1.0), 0.5));
+        scene.add_geometry(Sphere::new(Point3::new(0.5, 0.0, 3.0), 1.5, white));
+        scene.add_light(SphericalLight::new(
+            Point3::new(0.0, 3.0, 0.0),
+            Color::new(1.0, 1.0, 1.0),
+            50.0,
+        ));
+
+        // a cropped render matches the same pixels of the full image
+        let full = scene.render_image();
+        let crop = scene.render_region(Region::new(1, 1, 2, 2));
+        assert_eq!((crop.width(), crop.height()), (2, 2));
+        for (x, y) in (0..2).flat_map(|x| (0..2).map(move |y| (x, y))) {
+            assert_eq!(crop.pixel(x, y), full.pixel(x + 1, y + 1));
+        }
+
+        // so does averaging renders of the samples taken separately
+        let first = scene.render_samples(Region::new(1, 1, 2, 2), 0..1);
+        let second = scene.render_samples(Region::new(1, 1, 2, 2), 1..2);
+        for (x, y) in (0..2).flat_map(|x| (0..2).map(move |y| (x, y))) {
+            let average = (first.pixel(x, y) + second.pixel(x, y)) / 2.0;
+            let expected = full.pixel(x + 1, y + 1);
+            assert!((average.red - expected.red).abs() < 1e-6);
+            assert!((average.green - expected.green).abs() < 1e-6);
+            assert!((average.blue - expected.blue).abs() < 1e-6);
+        }
+    }
+
+    #[test]
+    fn test_occluded() {
+        let mut scene = Scene::new(4, 3, 90.0, 1, Color::default());
+        let white = scene.add_material(Diffuse::new(Color::new(1.0, 1.0, 1.0), 1.0));
+        scene.add_geometry(Sphere::new(Point3::new(0.0, 0.0, 5.0), 1.0, white));
+        let origin = Point3::new(0.0, 0.0, 0.0);
+        assert!(scene.occluded(&Ray::new(origin, Vector3::new(0.0, 0.0, 1.0))));
+        assert!(!scene.occluded(&Ray::new(origin, Vector3::new(0.0, 0.0, -1.0))));
+        // the blocker lies past the end of the segment
+        assert!(!scene.occluded(&Ray::segment(origin, Point3::new(0.0, 0.0, 3.0))));
+    }
+
+    #[test]
+    fn test_shared_material() {
+        let mut scene = Scene::new(4, 3, 90.0, 1, Color::default());
+        let paint = scene.add_material(Diffuse::new(Color::new(1.0, 1.0, 1.0), 1.0));
+        scene.add_geometry(Sphere::new(Point3::new(-2.0, 0.0, 5.0), 1.0, paint));
+        scene.add_geometry(Sphere::new(Point3::new(2.0, 0.0, 5.0), 1.0, paint));
+
+        // both spheres see the edit
+        scene.set_material(paint, Reflective::new(Color::default(), 0.5));
+        for x in &[-2.0, 2.0] {
+            let ray = Ray::new(Point3::new(*x, 0.0, 0.0), Vector3::new(0.0, 0.0, 1.0));
+            let hit = scene.trace(&ray, 0).unwrap();
+            let material = scene.material(hit.elem.material());
+            assert!(matches!(material, Material::Reflective(_)));
+        }
+
+        // and a second scene can share it
+        let mut other = Scene::new(4, 3, 90.0, 1, Color::default());
+        let shared = other.add_shared_material(scene.shared_material(paint));
+        assert!(Arc::ptr_eq(
+            &other.shared_material(shared),
+            &scene.shared_material(paint)
+        ));
+    }
+
+    #[test]
+    fn test_move_assembly() {
+        let mut scene = Scene::new(4, 3, 90.0, 1, Color::default());
+        let white = scene.add_material(Diffuse::new(Color::new(1.0, 1.0, 1.0), 1.0));
+        let mut table = Node::new("table");
+        table.add_child(Node::new("ball")).add_geometry(Sphere::new(
+            Point3::new(0.0, 0.0, 5.0),
+            1.0,
+            white,
+        ));
+        scene.add_node(table);
+
+        let ray = Ray::new(Point3::new(0.0, 0.0, 0.0), Vector3::new(0.0, 0.0, 1.0));
+        assert!(scene.occluded(&ray));
+
+        // moving the parent moves everything on it
+        assert!(scene.edit_node("table", |table| {
+            table.set_transform(convert(Translation3::new(10.0, 0.0, 0.0)))
+        }));
+        assert!(!scene.occluded(&ray));
+        let moved = Ray::new(Point3::new(10.0, 0.0, 0.0), Vector3::new(0.0, 0.0, 1.0));
+        assert!((scene.trace(&moved, 0).unwrap().surface.t - 4.0).abs() < 1e-5);
+
+        assert!(scene.node("table/ball").is_some());
+        assert!(!scene.edit_node("chair", |_| ()));
     }
 }